@@ -0,0 +1,176 @@
+use plotly::Plot;
+use serde_json::{json, Value};
+
+// plotly.rs のテンプレートと同じ CDN 版（plotly.rs にはフレームを扱う API がないので HTML は自前で書く）
+const PLOTLY_JS: &str = "https://cdn.plot.ly/plotly-2.12.1.min.js";
+
+// スライダーと再生ボタンを置くために下の余白を広げる (px)
+const CONTROLS_MARGIN: u64 = 140;
+
+// 1コマ分の図。name はスライダーの目盛りに出る
+pub struct Frame {
+    pub name: String,
+    pub plot: Plot,
+}
+
+fn to_value(plot: &Plot) -> Value {
+    serde_json::from_str(&plot.to_json()).expect("plot serializes to JSON")
+}
+
+// 再生・一時停止のボタンと、コマを選ぶスライダー
+fn animation_controls(names: &[String], frame_ms: u64) -> (Value, Value) {
+    let buttons = json!([{
+        "type": "buttons",
+        "direction": "left",
+        "showactive": false,
+        "x": 0.0,
+        "xanchor": "left",
+        "y": 0.0,
+        "yanchor": "top",
+        "pad": {"t": 60, "r": 10},
+        "buttons": [
+            {
+                "label": "Play",
+                "method": "animate",
+                "args": [null, {
+                    "frame": {"duration": frame_ms, "redraw": true},
+                    "transition": {"duration": 0},
+                    "fromcurrent": true,
+                    "mode": "immediate",
+                }],
+            },
+            {
+                "label": "Pause",
+                "method": "animate",
+                "args": [[null], {
+                    "frame": {"duration": 0, "redraw": false},
+                    "transition": {"duration": 0},
+                    "mode": "immediate",
+                }],
+            },
+        ],
+    }]);
+    // スライダーを動かすとそのコマに飛ぶ（再生中でも手で戻して見比べられる）
+    let steps: Vec<Value> = names
+        .iter()
+        .map(|name| {
+            json!({
+                "label": name,
+                "method": "animate",
+                "args": [[name], {
+                    "frame": {"duration": 0, "redraw": true},
+                    "transition": {"duration": 0},
+                    "mode": "immediate",
+                }],
+            })
+        })
+        .collect();
+    let sliders = json!([{
+        "active": 0,
+        "x": 0.12,
+        "len": 0.88,
+        "y": 0.0,
+        "yanchor": "top",
+        "pad": {"t": 50},
+        "currentvalue": {"visible": true, "xanchor": "left"},
+        "steps": steps,
+    }]);
+    (buttons, sliders)
+}
+
+// 最初のコマを初期表示にし、全コマを frames として添えた図（plotly.js の figure の形）
+// 各コマはトレースと注釈・図形だけを持ち、軸やグリッドは初期表示のものをそのまま使う
+pub fn animated_figure(frames: &[Frame], frame_ms: u64) -> Value {
+    let mut figure = frames
+        .first()
+        .map(|frame| to_value(&frame.plot))
+        .unwrap_or_else(|| json!({"data": [], "layout": {}}));
+    let names: Vec<String> = frames.iter().map(|frame| frame.name.clone()).collect();
+    let (buttons, sliders) = animation_controls(&names, frame_ms);
+    let layout = &mut figure["layout"];
+    layout["updatemenus"] = buttons;
+    layout["sliders"] = sliders;
+    let bottom = layout["margin"]["b"].as_u64().unwrap_or(0);
+    layout["margin"]["b"] = json!(bottom.max(CONTROLS_MARGIN));
+
+    figure["frames"] = frames
+        .iter()
+        .map(|frame| {
+            let plot = to_value(&frame.plot);
+            let layout = &plot["layout"];
+            json!({
+                "name": frame.name,
+                "data": plot["data"],
+                "layout": {
+                    "annotations": layout.get("annotations").cloned().unwrap_or(json!([])),
+                    "shapes": layout.get("shapes").cloned().unwrap_or(json!([])),
+                },
+            })
+        })
+        .collect();
+    figure
+}
+
+// animated_figure を1枚の HTML にする。ブラウザは計算済みのコマを再生するだけ
+pub fn animated_html(figure: &Value) -> String {
+    // JSON の中の "</script>" でスクリプトが途切れないようにする
+    let figure = figure.to_string().replace("</", "<\\/");
+    format!(
+        r#"<!doctype html>
+<html lang="en">
+
+<head>
+    <meta charset="utf-8" />
+</head>
+
+<body>
+    <div>
+        <script src="{}"></script>
+
+        <div id="plotly-html-element" class="plotly-graph-div" style="height:100%; width:100%;"></div>
+
+        <script type="module">
+            const graph_div = document.getElementById("plotly-html-element");
+            await Plotly.newPlot(graph_div, {});
+        </script>
+    </div>
+</body>
+
+</html>
+"#,
+        PLOTLY_JS, figure
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use plotly::Scatter;
+
+    #[test]
+    fn frames_and_slider_steps_line_up() {
+        let frames: Vec<Frame> = (1..=3)
+            .map(|k| {
+                let mut plot = Plot::new();
+                plot.add_trace(Scatter::new(vec![0.0, 1.0], vec![0.0, k as f64]));
+                Frame {
+                    name: format!("frame {}", k),
+                    plot,
+                }
+            })
+            .collect();
+        let figure = animated_figure(&frames, 100);
+        assert_eq!(figure["frames"].as_array().unwrap().len(), 3);
+        assert_eq!(figure["frames"][2]["name"], "frame 3");
+        assert_eq!(figure["frames"][2]["data"][0]["y"][1], 3.0);
+        // 初期表示は最初のコマ
+        assert_eq!(figure["data"][0]["y"][1], 1.0);
+        let steps = figure["layout"]["sliders"][0]["steps"].as_array().unwrap();
+        assert_eq!(steps.len(), 3);
+        assert_eq!(steps[1]["args"][0][0], "frame 2");
+        let buttons = &figure["layout"]["updatemenus"][0]["buttons"];
+        assert_eq!(buttons[0]["label"], "Play");
+        assert_eq!(buttons[0]["args"][1]["frame"]["duration"], 100);
+        assert!(animated_html(&figure).contains("\"frames\":["));
+    }
+}
@@ -0,0 +1,859 @@
+use std::path::PathBuf;
+
+use crate::config::MAX_BIT_DEPTH;
+use crate::layout::{AmplitudeScale, GridMode, SampleLine, TimeAxis};
+use crate::presets::{parse_frequency, parse_sample_rate, preset_by_name};
+use crate::reconstruct::{ReconstructionMode, Window};
+use crate::signal::QuantizerConfig;
+
+// plotly の line.smoothing の上限
+pub const MAX_SMOOTHING: f64 = 1.3;
+
+pub const USAGE: &str = "\
+Usage: graph-builder [OPTIONS]
+
+Options:
+  --preset <NAME>     Signal parameter preset (default: aliasing)
+  --config <PATH>     Load signal parameters from a TOML or JSON file
+  --watch             With --config, re-render whenever the config file is saved
+  --config-dir <DIR>  Render every .toml file in DIR (in parallel) as if given to --config,
+                      each to <out-dir>/<file stem>/
+  --validate-config <PATH>
+                      Check a config file and exit without rendering
+  --list-presets      List available signal presets
+  --reference         Append a 1kHz, 0 dBFS, non-decaying reference tone (48kHz, 16-bit)
+                      as an extra signal for calibration
+  --sr <RATE>         Override every signal's sampling rate: 44.1k, 48000, 96kHz,
+                      or a name (telephone, wideband, half-cd, cd, dvd, hi-res, hi-res-192)
+  --freq <FREQ>       Override every signal's frequency: Hz (440, 1.5k) or an equal-tempered
+                      note name with optional cents (A4, C#5, Bb3, A4+25c)
+  --dpi <PRESET>      Image size preset: screen | print | thumbnail
+  --width <PX>        Image width in pixels (overrides --dpi)
+  --height <PX>       Image height in pixels (overrides --dpi)
+  --scale <FACTOR>    Image scale factor (overrides --dpi)
+  --pdf-report <PATH> Render every preset (or the --config signals) to one page each
+                      of a PDF instead of writing the usual figures
+  --dump-json <PATH>  Write the comparison figure as plotly JSON instead of rendering
+  --metrics-json <PATH>
+                      Write each signal's metrics (SQNR, ENOB, THD, aliasing, ...) as JSON,
+                      then exit
+  --report-md <PATH>  Write a Markdown table of each signal's frequencies, Nyquist ratio,
+                      samples/cycle, bit depth and SQNR, then exit
+  --out-dir <DIR>     Directory to write figures to (default: export)
+  --output <NAME[,NAME...]>
+                      What to write to <out-dir>, in order; repeatable:
+                      comparison | spectrum | iq | metrics (metrics.json) | report (report.md)
+                      (default: comparison,spectrum,iq)
+  --html              Write interactive HTML instead of PNG images
+  --show              Open each figure in the default browser instead of writing files
+  --wav               Also write each signal's samples to <out-dir>/<name>.wav
+  --no-normalize      WAV: write samples as-is instead of normalizing the peak to -1 dBFS
+  --single            Write one standalone figure per signal (waveform, samples and metrics)
+                      to <out-dir>/single_<name>, then exit
+  --staircase         Write one quantization figure per signal to <out-dir>/staircase_<name>:
+                      the analog input, the held quantized staircase and the shaded
+                      error between them, annotated with the step size, then exit
+  --tile <SECONDS>    Generate and write each signal in SECONDS-long tiles to
+                      <out-dir>/tile_<name>_NNNN with a common amplitude range, then exit
+  --playhead <FPS>    Write the comparison figure as frames playhead_NNNN with a vertical
+                      cursor at t = frame / FPS (for video, e.g. with --wav), then exit
+  --sweep-html <FRAMES>
+                      Write an interactive HTML animation of the comparison figure with every
+                      signal's frequency swept from 0.1 to 2 times Nyquist in FRAMES steps,
+                      with play/pause buttons and a slider, to <out-dir>/frequency_sweep.html,
+                      then exit
+  --decimate <POINTS> HTML: thin the continuous traces to at most POINTS points per trace,
+                      keeping each interval's minimum and maximum
+  --no-mode-bar       HTML: hide the plotly mode bar
+  --no-download       HTML: remove the download-image button from the mode bar
+  --scroll-zoom       HTML: zoom with the mouse wheel
+  --lock-axes         HTML: static figure, no zoom, pan or hover
+  --metrics-annotation
+                      Keep the comparison panel titles short and show SQNR, THD and ENOB
+                      in a separate annotation in each panel's lower right corner
+  --callouts          Draw an arrow from the title of each aliasing panel to the point
+                      where the samples miss the signal the most
+  --severity-border   Outline each waveform panel by aliasing severity
+  --transparent       Export with a transparent background
+  --theme-file <PATH> Load colors and fonts from a TOML or JSON theme file; omitted fields
+                      keep the default (light) theme
+  --layout <LAYOUT>   Comparison figure layout: grid | side-by-side
+  --aspect <W:H>      Lock each comparison panel to this width:height (e.g. 16:9 or 2),
+                      shrinking it within its grid cell
+  --ticks-per-period <N>
+                      Place N time-axis ticks in every period of each panel's signal,
+                      starting at t = 0
+  --sample-line <SHAPE>
+                      How the line through the samples is drawn: linear | spline | hv
+                      (staircase). Display only, unrelated to --reconstruction
+  --smooth <TENSION>  Draw the continuous signal as a spline with this smoothing (0-1.3):
+                      a rounder curve from the same points; cosmetic only
+  --x-axis <AXIS>     Horizontal axis of the waveform panels: time (seconds, default) |
+                      samples (sample index n, continuous signal drawn at n = t*fs)
+  --grid <MODE>       Axis linking of the comparison grid: independent | coupled
+                      (coupled: each column shares its x axis, each row its y axis)
+  --reconstruction <MODE[,MODE...]>
+                      Reconstruction shown in the error fill:
+                      linear | zoh | sinc | windowed-sinc (default: linear)
+                      Several comma-separated modes are overlaid as lines
+  --response          Plot the magnitude response and group delay of the --reconstruction
+                      modes, then exit
+  --oversampling      Plot the reconstruction SNR of a pure sine at the first signal's frequency
+                      against the oversampling ratio Fs / 2f (1 to 64) for each --reconstruction
+                      mode, with sinc interpolation for comparison, then exit
+  --replicas          Plot the two-sided input spectrum and its replicas around ±Fs and ±2Fs,
+                      shading where they overlap, then exit
+  --eye               Plot an eye diagram: the first --reconstruction of each signal cut
+                      into one-period windows and overlaid, then exit
+  --taps <N>          Kernel length for windowed-sinc (default: 16)
+  --window <WINDOW>   Kernel window for windowed-sinc: hann | blackman | rectangular
+  --coherent          Snap each signal frequency to the nearest FFT bin (integer cycles
+                      in the window) so the spectrum shows no leakage
+  --period            Mark one period of the continuous signal on each waveform panel
+  --code-labels       Label each sample with its quantized integer code
+  --amplitude-colors  Color the continuous signal by instantaneous amplitude
+  --stems             Drop a thin vertical line from each sample to the time axis
+  --ideal-samples     Overlay the unquantized sample values as hollow markers
+  --nyquist-limited   Overlay the continuous signal lowpass-filtered to Nyquist, the part of
+                      it that sampling can preserve
+  --continuous-spectrum
+                      Overlay the spectrum of the continuous signal (its fine grid) on each
+                      sampled spectrum, showing where they diverge above Nyquist
+  --dbfs              Show time-domain amplitude in dBFS
+  --log-amp           Show time-domain |amplitude| on a log axis
+  --jitter <SECONDS>  Perturb every sample instant by Gaussian clock jitter (std. dev.)
+  --dc-offset <V>     Add a constant V to every signal before quantization
+  --input-noise <STD> Add Gaussian noise (std. dev., relative to full scale) to every signal
+                      before sampling, modeling a noisy analog front end
+  --analyze           Print SQNR/ENOB/THD, RMS and crest factor for each signal instead of
+                      rendering
+  --rms-window <SECONDS>
+                      Measure RMS and crest factor over consecutive windows of this length
+                      and report the loudest one, instead of over the whole signal
+  --ab <A>,<B>        Plot the sample-by-sample and error-spectrum difference between two
+                      quantizer settings, <quantizer>[+<dither>][+shaped]
+                      (e.g. linear,linear+tpdf), then exit
+  --bit-diff <A>,<B>  Plot the spectra of each signal quantized to A and B bits and the
+                      spectrum of their difference, the noise added by dropping bits
+                      (e.g. 16,8), then exit
+  --a-weight          Apply the A-weighting curve to the spectrum figures (dB(A)), so
+                      that noise and aliases are shown by how loud they sound
+  --folding           Plot the folding diagram: apparent versus true frequency (both / Fs)
+                      from 0 to 3 Fs, with each signal marked on it, then exit
+  --constellation     Plot each signal's I/Q samples before and after quantization over the
+                      grid of I and Q quantization levels, then exit
+  --wagon-wheel       Plot apparent versus true rotation speed (both / frame rate) from 0 to
+                      2 Fs, with each signal marked as a rotating wheel (e.g. --preset
+                      wagon-wheel), then exit
+  --sqnr-sweep        Plot measured SQNR against bit depth (1-16) with the 6.02N + 1.76 dB line,
+                      then exit
+  --lloyd-max         Plot the SQNR of a uniform quantizer and of a Lloyd-Max quantizer fitted
+                      to each signal's amplitude distribution at 1-8 bits, then exit
+  --harmonics <N>     Print which of the first N harmonics alias, then exit
+  --trials <N>        With --analyze, average N trials with random phase
+  --seed <SEED>       Random seed for --trials, --jitter, --input-noise and dither (default: 0)
+  --profile           Print the time spent in signal generation, FFT, figure build and
+                      image write after rendering
+  -h, --help          Print this help";
+
+// 画像出力サイズのプリセット
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DpiPreset {
+    Screen,    // 画面表示・スライド向け
+    Print,     // 印刷向けの高解像度
+    Thumbnail, // 一覧用の縮小画像
+}
+
+impl DpiPreset {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "screen" => Some(Self::Screen),
+            "print" => Some(Self::Print),
+            "thumbnail" => Some(Self::Thumbnail),
+            _ => None,
+        }
+    }
+
+    pub fn size(self) -> ExportSize {
+        match self {
+            Self::Screen => ExportSize::new(1200, 800, 1.0),
+            Self::Print => ExportSize::new(2400, 1600, 2.0),
+            Self::Thumbnail => ExportSize::new(600, 400, 1.0),
+        }
+    }
+}
+
+// 図の出力形式
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum OutputFormat {
+    #[default]
+    Png, // kaleido で静止画に書き出す
+    Html, // ホバーやズームができるインタラクティブな HTML
+    // --out-dir には書かず、一時ディレクトリの HTML を既定のブラウザで開く
+    Browser,
+}
+
+impl OutputFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Png => "png",
+            Self::Html | Self::Browser => "html",
+        }
+    }
+}
+
+// 1回の実行で書き出すもの（--output）。生成した信号をまとめて使い、実行を分けずに済ませる
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Output {
+    Comparison, // 比較図 digital_audio_comparison
+    Spectrum,   // スペクトル digital_audio_spectrum
+    Iq,         // 複素サンプリングのスペクトル iq_spectrum
+    Metrics,    // 指標の JSON metrics.json
+    Report,     // Markdown の表 report.md
+}
+
+impl Output {
+    // --output も設定ファイルの outputs もなければこの3枚
+    pub const DEFAULT: [Output; 3] = [Output::Comparison, Output::Spectrum, Output::Iq];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "comparison" => Some(Self::Comparison),
+            "spectrum" => Some(Self::Spectrum),
+            "iq" => Some(Self::Iq),
+            "metrics" => Some(Self::Metrics),
+            "report" => Some(Self::Report),
+            _ => None,
+        }
+    }
+
+    pub fn is_figure(self) -> bool {
+        !matches!(self, Self::Metrics | Self::Report)
+    }
+}
+
+// 比較図のレイアウト
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum FigureLayout {
+    #[default]
+    Grid, // 2x2 グリッドに波形を並べる
+    SideBySide, // 信号ごとに1行、左に波形・右にスペクトル
+}
+
+impl FigureLayout {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "grid" => Some(Self::Grid),
+            "side-by-side" => Some(Self::SideBySide),
+            _ => None,
+        }
+    }
+}
+
+// write_image に渡す (width, height, scale)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ExportSize {
+    pub width: usize,
+    pub height: usize,
+    pub scale: f64,
+}
+
+impl ExportSize {
+    pub fn new(width: usize, height: usize, scale: f64) -> Self {
+        Self {
+            width,
+            height,
+            scale,
+        }
+    }
+}
+
+impl Default for ExportSize {
+    // プリセット未指定時の出力サイズ
+    fn default() -> Self {
+        Self::new(1200, 800, 4.0)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Options {
+    pub help: bool,
+    pub preset: String,
+    pub list_presets: bool,
+    pub sample_rate: Option<i64>,
+    pub signal_freq: Option<f64>, // --freq（音名は Hz にしてから持つ）
+    pub reference: bool,
+    pub config: Option<PathBuf>,
+    pub config_dir: Option<PathBuf>, // 中の .toml をそれぞれ --config として描く
+    pub validate_config: Option<PathBuf>,
+    pub watch: bool,
+    pub dpi: Option<DpiPreset>,
+    pub width: Option<usize>,
+    pub height: Option<usize>,
+    pub scale: Option<f64>,
+    pub out_dir: PathBuf,
+    pub pdf_report: Option<PathBuf>,
+    pub dump_json: Option<PathBuf>,
+    pub metrics_json: Option<PathBuf>,
+    pub report_md: Option<PathBuf>,
+    pub format: OutputFormat,
+    pub outputs: Vec<Output>, // 空なら設定ファイルの outputs、それもなければ Output::DEFAULT
+    pub wav: bool,
+    pub normalize: bool,
+    pub decimate: Option<usize>,
+    pub playhead: Option<f64>,     // コマ送りのフレームレート (fps)
+    pub sweep_html: Option<usize>, // 周波数を掃引するアニメーションのコマ数
+    pub single: bool,
+    pub staircase: bool,
+    pub tile: Option<f64>, // 1枚のタイルの長さ (s)
+    pub mode_bar: bool,
+    pub download_button: bool,
+    pub scroll_zoom: bool,
+    pub lock_axes: bool,
+    pub transparent: bool,
+    pub theme_file: Option<PathBuf>,
+    pub severity_borders: bool,
+    pub metrics_annotation: bool,
+    pub callouts: bool,
+    pub profile: bool,
+    pub layout: FigureLayout,
+    pub aspect: Option<f64>,             // パネルの 幅 / 高さ
+    pub ticks_per_period: Option<usize>, // 信号の1周期あたりの時間軸の目盛り数
+    pub smooth: Option<f64>, // 連続信号の線をスプラインにするときの smoothing（plotly は 0〜1.3）
+    pub sample_line: Option<SampleLine>, // None なら設定ファイルの sample_line、それも無ければ Linear
+    pub time_axis: TimeAxis,             // 波形パネルの横軸（秒かサンプル番号か）
+    pub grid: Option<GridMode>,          // None なら設定ファイルの grid、それも無ければ Independent
+    pub amplitude_scale: AmplitudeScale,
+    pub amplitude_colors: bool,
+    pub stems: bool,
+    pub ideal_samples: bool,
+    pub nyquist_limited: bool,
+    pub continuous_spectrum: bool,
+    pub code_labels: bool,
+    pub period: bool,
+    pub coherent: bool,
+    pub reconstructions: Vec<ReconstructionMode>,
+    pub response: bool,
+    pub oversampling: bool,
+    pub eye: bool,
+    pub replicas: bool,
+    pub jitter: Option<f64>,
+    pub input_noise: Option<f64>,
+    pub dc_offset: Option<f64>,
+    pub rms_window: Option<f64>, // RMS を測る区間の長さ (s)
+    pub analyze: bool,
+    pub sqnr_sweep: bool,
+    pub lloyd_max: bool,
+    pub folding: bool,
+    pub a_weight: bool,
+    pub wagon_wheel: bool,
+    pub constellation: bool,
+    pub ab: Option<(QuantizerConfig, QuantizerConfig)>,
+    pub bit_diff: Option<(u32, u32)>, // 比べる2つのビット数 A, B
+    pub harmonics: Option<usize>,
+    pub trials: usize,
+    pub seed: u64,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            help: false,
+            preset: "aliasing".to_string(),
+            list_presets: false,
+            sample_rate: None,
+            signal_freq: None,
+            reference: false,
+            config: None,
+            config_dir: None,
+            validate_config: None,
+            watch: false,
+            dpi: None,
+            width: None,
+            height: None,
+            scale: None,
+            out_dir: PathBuf::from("export"),
+            pdf_report: None,
+            dump_json: None,
+            metrics_json: None,
+            report_md: None,
+            format: OutputFormat::default(),
+            outputs: Vec::new(),
+            wav: false,
+            normalize: true,
+            decimate: None,
+            playhead: None,
+            sweep_html: None,
+            single: false,
+            staircase: false,
+            tile: None,
+            mode_bar: true,
+            download_button: true,
+            scroll_zoom: false,
+            lock_axes: false,
+            transparent: false,
+            theme_file: None,
+            severity_borders: false,
+            metrics_annotation: false,
+            callouts: false,
+            profile: false,
+            layout: FigureLayout::default(),
+            aspect: None,
+            ticks_per_period: None,
+            sample_line: None,
+            smooth: None,
+            time_axis: TimeAxis::Seconds,
+            grid: None,
+            amplitude_scale: AmplitudeScale::default(),
+            amplitude_colors: false,
+            stems: false,
+            ideal_samples: false,
+            nyquist_limited: false,
+            continuous_spectrum: false,
+            code_labels: false,
+            period: false,
+            coherent: false,
+            reconstructions: vec![ReconstructionMode::default()],
+            response: false,
+            oversampling: false,
+            eye: false,
+            replicas: false,
+            jitter: None,
+            rms_window: None,
+            input_noise: None,
+            dc_offset: None,
+            analyze: false,
+            sqnr_sweep: false,
+            lloyd_max: false,
+            folding: false,
+            a_weight: false,
+            wagon_wheel: false,
+            constellation: false,
+            ab: None,
+            bit_diff: None,
+            harmonics: None,
+            trials: 1,
+            seed: 0,
+        }
+    }
+}
+
+impl Options {
+    pub fn outputs(&self) -> &[Output] {
+        if self.outputs.is_empty() {
+            &Output::DEFAULT
+        } else {
+            &self.outputs
+        }
+    }
+
+    // プリセットを基準に、個別指定があれば上書きする
+    pub fn export_size(&self) -> ExportSize {
+        let base = self.dpi.map(DpiPreset::size).unwrap_or_default();
+        ExportSize {
+            width: self.width.unwrap_or(base.width),
+            height: self.height.unwrap_or(base.height),
+            scale: self.scale.unwrap_or(base.scale),
+        }
+    }
+}
+
+// "16:9" のような 幅:高さ、または "1.5" のような比を 幅 / 高さ にする。正の有限値のみ
+fn parse_aspect(text: &str) -> Option<f64> {
+    let aspect = match text.split_once(':') {
+        Some((width, height)) => {
+            width.trim().parse::<f64>().ok()? / height.trim().parse::<f64>().ok()?
+        }
+        None => text.parse::<f64>().ok()?,
+    };
+    (aspect.is_finite() && aspect > 0.0).then_some(aspect)
+}
+
+// 引数の値をパースする（失敗時はオプション名を含むメッセージを返す）
+fn parse_value<T: std::str::FromStr>(flag: &str, value: Option<String>) -> Result<T, String> {
+    let value = value.ok_or_else(|| format!("{} requires a value", flag))?;
+    value
+        .parse()
+        .map_err(|_| format!("invalid value for {}: {}", flag, value))
+}
+
+pub fn parse_args<I: IntoIterator<Item = String>>(args: I) -> Result<Options, String> {
+    let mut options = Options::default();
+    let mut args = args.into_iter();
+    // windowed-sinc のパラメータは --reconstruction より後に指定されてもよいので最後に組み立てる
+    let mut reconstruction = "linear".to_string();
+    let mut taps = 16;
+    let mut window = Window::default();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => options.help = true,
+            "--preset" => {
+                let name: String = parse_value(&arg, args.next())?;
+                if preset_by_name(&name).is_none() {
+                    return Err(format!("unknown --preset: {}", name));
+                }
+                options.preset = name;
+            }
+            "--list-presets" => options.list_presets = true,
+            "--reference" => options.reference = true,
+            "--freq" => {
+                let text: String = parse_value(&arg, args.next())?;
+                let frequency = parse_frequency(&text)
+                    .ok_or_else(|| format!("invalid frequency for --freq: {}", text))?;
+                options.signal_freq = Some(frequency);
+            }
+            "--sr" => {
+                let text: String = parse_value(&arg, args.next())?;
+                let rate = parse_sample_rate(&text)
+                    .ok_or_else(|| format!("invalid sampling rate for --sr: {}", text))?;
+                options.sample_rate = Some(rate);
+            }
+            "--config" => options.config = Some(parse_value(&arg, args.next())?),
+            "--watch" => options.watch = true,
+            "--config-dir" => options.config_dir = Some(parse_value(&arg, args.next())?),
+            "--validate-config" => options.validate_config = Some(parse_value(&arg, args.next())?),
+            "--dpi" => {
+                let name: String = parse_value(&arg, args.next())?;
+                let preset = DpiPreset::from_name(&name)
+                    .ok_or_else(|| format!("unknown --dpi preset: {}", name))?;
+                options.dpi = Some(preset);
+            }
+            "--width" => options.width = Some(parse_value(&arg, args.next())?),
+            "--height" => options.height = Some(parse_value(&arg, args.next())?),
+            "--scale" => options.scale = Some(parse_value(&arg, args.next())?),
+            "--pdf-report" => options.pdf_report = Some(parse_value(&arg, args.next())?),
+            "--metrics-json" => options.metrics_json = Some(parse_value(&arg, args.next())?),
+            "--report-md" => options.report_md = Some(parse_value(&arg, args.next())?),
+            "--dump-json" => options.dump_json = Some(parse_value(&arg, args.next())?),
+            "--out-dir" => options.out_dir = parse_value(&arg, args.next())?,
+            "--output" => {
+                let names: String = parse_value(&arg, args.next())?;
+                for name in names.split(',') {
+                    let output = Output::from_name(name.trim())
+                        .ok_or_else(|| format!("unknown --output: {}", name))?;
+                    if !options.outputs.contains(&output) {
+                        options.outputs.push(output);
+                    }
+                }
+            }
+            "--html" => options.format = OutputFormat::Html,
+            "--show" => options.format = OutputFormat::Browser,
+            "--wav" => options.wav = true,
+            "--single" => options.single = true,
+            "--staircase" => options.staircase = true,
+            "--tile" => options.tile = Some(parse_value(&arg, args.next())?),
+            "--no-normalize" => options.normalize = false,
+            "--smooth" => options.smooth = Some(parse_value(&arg, args.next())?),
+            "--decimate" => options.decimate = Some(parse_value(&arg, args.next())?),
+            "--playhead" => options.playhead = Some(parse_value(&arg, args.next())?),
+            "--sweep-html" => options.sweep_html = Some(parse_value(&arg, args.next())?),
+            "--no-mode-bar" => options.mode_bar = false,
+            "--no-download" => options.download_button = false,
+            "--scroll-zoom" => options.scroll_zoom = true,
+            "--lock-axes" => options.lock_axes = true,
+            "--severity-border" => options.severity_borders = true,
+            "--metrics-annotation" => options.metrics_annotation = true,
+            "--callouts" => options.callouts = true,
+            "--profile" => options.profile = true,
+            "--transparent" => options.transparent = true,
+            "--theme-file" => options.theme_file = Some(parse_value(&arg, args.next())?),
+            "--layout" => {
+                let name: String = parse_value(&arg, args.next())?;
+                options.layout = FigureLayout::from_name(&name)
+                    .ok_or_else(|| format!("unknown --layout: {}", name))?;
+            }
+            "--ticks-per-period" => {
+                options.ticks_per_period = Some(parse_value(&arg, args.next())?)
+            }
+            "--aspect" => {
+                let text: String = parse_value(&arg, args.next())?;
+                options.aspect = Some(
+                    parse_aspect(&text)
+                        .ok_or_else(|| format!("invalid value for --aspect: {}", text))?,
+                );
+            }
+            "--sample-line" => {
+                let name: String = parse_value(&arg, args.next())?;
+                options.sample_line = Some(
+                    SampleLine::from_name(&name)
+                        .ok_or_else(|| format!("unknown --sample-line: {}", name))?,
+                );
+            }
+            "--x-axis" => {
+                let name: String = parse_value(&arg, args.next())?;
+                options.time_axis = TimeAxis::from_name(&name)
+                    .ok_or_else(|| format!("unknown --x-axis: {}", name))?;
+            }
+            "--grid" => {
+                let name: String = parse_value(&arg, args.next())?;
+                options.grid = Some(
+                    GridMode::from_name(&name)
+                        .ok_or_else(|| format!("unknown --grid: {}", name))?,
+                );
+            }
+            "--reconstruction" => reconstruction = parse_value(&arg, args.next())?,
+            "--response" => options.response = true,
+            "--oversampling" => options.oversampling = true,
+            "--eye" => options.eye = true,
+            "--replicas" => options.replicas = true,
+            "--taps" => taps = parse_value(&arg, args.next())?,
+            "--window" => {
+                let name: String = parse_value(&arg, args.next())?;
+                window = Window::from_name(&name)
+                    .ok_or_else(|| format!("unknown --window: {}", name))?;
+            }
+            "--code-labels" => options.code_labels = true,
+            "--period" => options.period = true,
+            "--coherent" => options.coherent = true,
+            "--amplitude-colors" => options.amplitude_colors = true,
+            "--stems" => options.stems = true,
+            "--ideal-samples" => options.ideal_samples = true,
+            "--nyquist-limited" => options.nyquist_limited = true,
+            "--continuous-spectrum" => options.continuous_spectrum = true,
+            "--dbfs" => options.amplitude_scale = AmplitudeScale::Dbfs,
+            "--log-amp" => options.amplitude_scale = AmplitudeScale::LogAbs,
+            "--input-noise" => options.input_noise = Some(parse_value(&arg, args.next())?),
+            "--dc-offset" => options.dc_offset = Some(parse_value(&arg, args.next())?),
+            "--jitter" => options.jitter = Some(parse_value(&arg, args.next())?),
+            "--rms-window" => options.rms_window = Some(parse_value(&arg, args.next())?),
+            "--analyze" => options.analyze = true,
+            "--sqnr-sweep" => options.sqnr_sweep = true,
+            "--lloyd-max" => options.lloyd_max = true,
+            "--folding" => options.folding = true,
+            "--a-weight" => options.a_weight = true,
+            "--wagon-wheel" => options.wagon_wheel = true,
+            "--constellation" => options.constellation = true,
+            "--ab" => {
+                let value: String = parse_value(&arg, args.next())?;
+                let (a, b) = value
+                    .split_once(',')
+                    .ok_or_else(|| format!("--ab requires two settings: {}", value))?;
+                let parse = |name: &str| {
+                    QuantizerConfig::from_name(name.trim()).ok_or_else(|| {
+                        // Qm.n は語長 m + n が MAX_BIT_DEPTH まで（from_name が弾く）
+                        format!(
+                            "unknown --ab setting: {} (quantizer: linear or Qm.n with \
+                                 m >= 1 and m + n <= {})",
+                            name, MAX_BIT_DEPTH
+                        )
+                    })
+                };
+                options.ab = Some((parse(a)?, parse(b)?));
+            }
+            "--bit-diff" => {
+                let value: String = parse_value(&arg, args.next())?;
+                let (a, b) = value
+                    .split_once(',')
+                    .ok_or_else(|| format!("--bit-diff requires two bit depths: {}", value))?;
+                let parse = |text: &str| match text.trim().parse::<u32>() {
+                    Ok(bits) if (1..=MAX_BIT_DEPTH).contains(&bits) => Ok(bits),
+                    _ => Err(format!(
+                        "invalid --bit-diff bit depth: {} (expected 1 to {})",
+                        text, MAX_BIT_DEPTH
+                    )),
+                };
+                options.bit_diff = Some((parse(a)?, parse(b)?));
+            }
+            "--harmonics" => options.harmonics = Some(parse_value(&arg, args.next())?),
+            "--trials" => options.trials = parse_value(&arg, args.next())?,
+            "--seed" => options.seed = parse_value(&arg, args.next())?,
+            _ => return Err(format!("unknown option: {}", arg)),
+        }
+    }
+
+    if taps == 0 {
+        return Err("--taps must be at least 1".to_string());
+    }
+    options.reconstructions = reconstruction
+        .split(',')
+        .map(|name| {
+            ReconstructionMode::from_name(name.trim(), taps, window)
+                .ok_or_else(|| format!("unknown --reconstruction: {}", name))
+        })
+        .collect::<Result<_, _>>()?;
+
+    if options
+        .jitter
+        .is_some_and(|jitter| !(jitter.is_finite() && jitter >= 0.0))
+    {
+        return Err("--jitter must be a non-negative time in seconds".to_string());
+    }
+    if options
+        .input_noise
+        .is_some_and(|noise| !(noise.is_finite() && noise >= 0.0))
+    {
+        return Err("--input-noise must be a non-negative standard deviation".to_string());
+    }
+    if options.dc_offset.is_some_and(|offset| !offset.is_finite()) {
+        return Err("--dc-offset must be finite".to_string());
+    }
+
+    if options
+        .playhead
+        .is_some_and(|fps| !(fps.is_finite() && fps > 0.0))
+    {
+        return Err("--playhead must be a positive frame rate".to_string());
+    }
+    if options.sweep_html.is_some_and(|frames| frames < 2) {
+        return Err("--sweep-html needs at least 2 frames".to_string());
+    }
+    if options
+        .tile
+        .is_some_and(|tile| !(tile.is_finite() && tile > 0.0))
+    {
+        return Err("--tile must be a positive time in seconds".to_string());
+    }
+    if options
+        .rms_window
+        .is_some_and(|window| !(window.is_finite() && window > 0.0))
+    {
+        return Err("--rms-window must be a positive time in seconds".to_string());
+    }
+    if options.ticks_per_period == Some(0) {
+        return Err("--ticks-per-period must be at least 1".to_string());
+    }
+    // コマやタイルごとにブラウザのタブが開いてしまう
+    if options.format == OutputFormat::Browser
+        && (options.playhead.is_some() || options.tile.is_some())
+    {
+        return Err("--show cannot be combined with --playhead or --tile".to_string());
+    }
+    if options
+        .smooth
+        .is_some_and(|tension| !(tension > 0.0 && tension <= MAX_SMOOTHING))
+    {
+        return Err(format!(
+            "--smooth must be greater than 0 and at most {}",
+            MAX_SMOOTHING
+        ));
+    }
+    if options.decimate.is_some_and(|points| points < 2) {
+        return Err("--decimate must keep at least 2 points".to_string());
+    }
+    // 0 px の画像や倍率は kaleido が描けないまま失敗する
+    if options.width == Some(0) {
+        return Err("--width must be greater than 0".to_string());
+    }
+    if options.height == Some(0) {
+        return Err("--height must be greater than 0".to_string());
+    }
+    if options
+        .scale
+        .is_some_and(|scale| !(scale.is_finite() && scale > 0.0))
+    {
+        return Err("--scale must be greater than 0".to_string());
+    }
+
+    if options.watch && options.config.is_none() {
+        return Err("--watch requires --config".to_string());
+    }
+    // 1か所に書く出力は設定ごとに上書きされてしまう
+    // --show は図の名前ごとに決まった一時ファイルに書くので、並列に描くと上書きし合う
+    if options.config_dir.is_some()
+        && (options.config.is_some()
+            || options.watch
+            || options.format == OutputFormat::Browser
+            || options.pdf_report.is_some()
+            || options.dump_json.is_some()
+            || options.metrics_json.is_some()
+            || options.report_md.is_some())
+    {
+        return Err(
+            "--config-dir cannot be combined with --config, --watch, --show, --pdf-report, \
+             --dump-json, --metrics-json or --report-md"
+                .to_string(),
+        );
+    }
+
+    if options.trials == 0 {
+        return Err("--trials must be at least 1".to_string());
+    }
+
+    Ok(options)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Options, String> {
+        parse_args(args.iter().map(|arg| arg.to_string()))
+    }
+
+    fn error(args: &[&str]) -> String {
+        match parse(args) {
+            Ok(_) => panic!("{:?} should be rejected", args),
+            Err(message) => message,
+        }
+    }
+
+    #[test]
+    fn values_are_parsed_and_outputs_deduplicated() {
+        let options = parse(&[
+            "--freq",
+            "A4",
+            "--sr",
+            "44.1k",
+            "--output",
+            "spectrum,metrics",
+            "--output",
+            "spectrum",
+            "--html",
+        ])
+        .unwrap();
+        assert_eq!(options.signal_freq, Some(440.0));
+        assert_eq!(options.sample_rate, Some(44100));
+        assert_eq!(options.outputs, vec![Output::Spectrum, Output::Metrics]);
+        assert_eq!(options.format, OutputFormat::Html);
+
+        let defaults = parse(&[]).unwrap();
+        assert_eq!(defaults.preset, "aliasing");
+        assert_eq!(defaults.outputs(), Output::DEFAULT.to_vec());
+    }
+
+    #[test]
+    fn invalid_values_are_reported() {
+        assert_eq!(error(&["--bogus"]), "unknown option: --bogus");
+        assert_eq!(error(&["--width"]), "--width requires a value");
+        assert_eq!(
+            error(&["--width", "wide"]),
+            "invalid value for --width: wide"
+        );
+        assert_eq!(
+            error(&["--smooth", "2"]),
+            format!(
+                "--smooth must be greater than 0 and at most {}",
+                MAX_SMOOTHING
+            )
+        );
+        assert_eq!(error(&["--width", "0"]), "--width must be greater than 0");
+        assert_eq!(error(&["--height", "0"]), "--height must be greater than 0");
+        assert_eq!(error(&["--scale", "0"]), "--scale must be greater than 0");
+        assert_eq!(
+            error(&["--scale", "-1.5"]),
+            "--scale must be greater than 0"
+        );
+        assert_eq!(error(&["--scale", "inf"]), "--scale must be greater than 0");
+        assert_eq!(error(&["--scale", "NaN"]), "--scale must be greater than 0");
+        // 語長が MAX_BIT_DEPTH を超える Qm.n は from_name が弾く
+        assert!(error(&["--ab", "Q1.70,linear"]).starts_with("unknown --ab setting: Q1.70"));
+    }
+
+    #[test]
+    fn conflicting_options_are_rejected() {
+        assert_eq!(error(&["--watch"]), "--watch requires --config");
+        assert!(error(&["--show", "--tile", "1"]).starts_with("--show cannot be combined"));
+        for conflict in [
+            &["--config", "a.toml"][..],
+            &["--show"],
+            &["--dump-json", "a.json"],
+        ] {
+            let args: Vec<&str> = ["--config-dir", "configs"]
+                .into_iter()
+                .chain(conflict.iter().copied())
+                .collect();
+            assert!(error(&args).starts_with("--config-dir cannot be combined"));
+        }
+    }
+}
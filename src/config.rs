@@ -0,0 +1,733 @@
+use std::fmt;
+use std::path::Path;
+
+use serde::Deserialize;
+
+use crate::cli::Output;
+use crate::layout::{GridMode, SampleLine};
+use crate::presets::parse_frequency;
+use crate::signal::{code_bits, Dither, EnvelopeMode, Quantizer, SignalParams, Waveform};
+use crate::theme::{AxisFont, AxisKind, Theme};
+
+// 1つの図に並べられる信号の数（plotly.rs の軸は8組まで）
+pub const MAX_SIGNALS: usize = 8;
+
+// 軸フォントの大きさの上限 (pt)
+pub const MAX_FONT_SIZE: usize = 72;
+
+// 量子化ビット数の上限（2^bit_depth を u32 で計算するため）
+pub const MAX_BIT_DEPTH: u32 = 31;
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(String),                                 // ファイルを読めない
+    Parse(String), // TOML/JSON として不正、または型が合わない・未知のフィールドがある
+    Invalid { field: String, message: String }, // 値の範囲外・必須フィールドの欠落
+}
+
+impl ConfigError {
+    fn invalid(field: impl Into<String>, message: impl Into<String>) -> Self {
+        Self::Invalid {
+            field: field.into(),
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Io(message) => write!(f, "cannot read config: {}", message),
+            Self::Parse(message) => write!(f, "invalid config: {}", message),
+            Self::Invalid { field, message } => write!(f, "{}: {}", field, message),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+// signal_freq は Hz の数値か、"A4" "C#5+20c" のような音名（presets::parse_frequency）
+#[derive(Clone, Debug, Deserialize)]
+#[serde(untagged)]
+pub enum FrequencyConfig {
+    Hz(f64),
+    Text(String),
+}
+
+// 設定ファイルの1信号分。必須フィールドも Option にして、欠けていればフィールド名付きで報告する
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SignalConfig {
+    pub name: Option<String>,
+    pub signal_freq: Option<FrequencyConfig>,
+    pub sampling_rate: Option<i64>,
+    pub bit_depth: Option<u32>,
+    pub phase: Option<f64>,
+    pub duration: Option<f64>,   // 表示・サンプリングする時間 (s)。既定 2.0
+    pub decay_rate: Option<f64>, // エンベロープの減衰係数 (1/s)。0 で一定振幅
+    pub amplitude: Option<f64>,  // 信号のピーク振幅。既定 1.0
+    pub full_scale: Option<f64>, // ADC のフルスケール。既定 1.0
+    pub jitter: Option<f64>,     // サンプリング時刻の揺らぎの標準偏差 (s)
+    pub input_noise: Option<f64>, // サンプリング前の雑音の標準偏差 (FS)
+    pub dc_offset: Option<f64>,  // 全サンプルに足す直流成分。既定 0.0
+    pub waveform: Option<String>, // sine | square | band-limited-square
+    pub harmonics: Option<usize>, // band-limited-square で合成する項数（省略でナイキストまで）
+    pub envelope_mode: Option<String>, // quantized | display-only
+    pub quantizer: Option<String>, // linear | Qm.n（例: Q1.15）| custom
+    pub levels: Option<Vec<f64>>, // custom の量子化レベル（昇順、±1 がフルスケール）
+    // Qm.n の範囲外を飽和させるか（既定 true）。false で回り込む
+    pub saturate: Option<bool>,
+    pub dither: Option<String>,      // none | rpdf | tpdf
+    pub noise_shaping: Option<bool>, // 量子化誤差を次のサンプルに持ち越す（既定 false）
+    pub seed: Option<u64>, // ジッタ・入力雑音・ディザの乱数シード。省略時は --seed にパネル番号を足す
+    pub color: Option<String>, // #rgb | #rrggbb | #rrggbbaa | rgb(...) | rgba(...)
+    // false にすると検査も描画もせずに飛ばす（グリッドの枠も使わない）。既定 true
+    pub enabled: Option<bool>,
+}
+
+// 軸1種類分のフォントの上書き。省略した項目はテーマと図ごとの既定値を使う
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AxisFontConfig {
+    pub family: Option<String>,
+    pub title_size: Option<usize>, // 軸タイトルの大きさ (pt)
+    pub tick_size: Option<usize>,  // 目盛りラベルの大きさ (pt)
+}
+
+// [axes.time] は波形パネル、[axes.spectrum] はスペクトルパネルの軸
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AxesConfig {
+    pub time: Option<AxisFontConfig>,
+    pub spectrum: Option<AxisFontConfig>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    #[serde(default)]
+    pub signals: Vec<SignalConfig>,
+    #[serde(default)]
+    pub axes: AxesConfig,
+    pub grid: Option<String>, // independent | coupled。--grid があればそちらを使う
+    pub sample_line: Option<String>, // linear | spline | hv。--sample-line があればそちらを使う
+    pub outputs: Option<Vec<String>>, // 書き出すもの（cli::Output）。--output があればそちらを使う
+}
+
+// plotly がそのまま解釈できる色の書式か
+fn is_valid_color(color: &str) -> bool {
+    if let Some(hex) = color.strip_prefix('#') {
+        return [3, 6, 8].contains(&hex.len()) && hex.chars().all(|c| c.is_ascii_hexdigit());
+    }
+    let (arguments, count) = if let Some(rest) = color.strip_prefix("rgba(") {
+        (rest, 4)
+    } else if let Some(rest) = color.strip_prefix("rgb(") {
+        (rest, 3)
+    } else {
+        return false;
+    };
+    let Some(arguments) = arguments.strip_suffix(')') else {
+        return false;
+    };
+    let values: Vec<&str> = arguments.split(',').map(str::trim).collect();
+    values.len() == count && values.iter().all(|v| v.parse::<f64>().is_ok())
+}
+
+fn required<T: Clone>(value: &Option<T>, field: &str) -> Result<T, ConfigError> {
+    value
+        .clone()
+        .ok_or_else(|| ConfigError::invalid(field, "required field is missing"))
+}
+
+impl SignalConfig {
+    // prefix はエラーメッセージ用のフィールドパス（例: "signals[0]"）
+    pub fn validate(&self, prefix: &str) -> Result<SignalParams, ConfigError> {
+        let field = |name: &str| format!("{}.{}", prefix, name);
+
+        let name = required(&self.name, &field("name"))?;
+        if name.trim().is_empty() {
+            return Err(ConfigError::invalid(field("name"), "must not be empty"));
+        }
+
+        let signal_freq = match required(&self.signal_freq, &field("signal_freq"))? {
+            FrequencyConfig::Hz(frequency) => frequency,
+            FrequencyConfig::Text(text) => parse_frequency(&text).ok_or_else(|| {
+                ConfigError::invalid(
+                    field("signal_freq"),
+                    format!(
+                        "invalid frequency {:?} (expected Hz or a note name such as \"A4\" or \"C#5+20c\")",
+                        text
+                    ),
+                )
+            })?,
+        };
+        if !signal_freq.is_finite() || signal_freq <= 0.0 {
+            return Err(ConfigError::invalid(
+                field("signal_freq"),
+                format!("must be a positive frequency in Hz (got {})", signal_freq),
+            ));
+        }
+
+        let sampling_rate = required(&self.sampling_rate, &field("sampling_rate"))?;
+        if sampling_rate <= 0 {
+            return Err(ConfigError::invalid(
+                field("sampling_rate"),
+                format!("must be a positive rate in Hz (got {})", sampling_rate),
+            ));
+        }
+
+        let quantizer = match &self.quantizer {
+            Some(name) if name == "custom" => {
+                let levels = required(&self.levels, &field("levels"))?;
+                if levels.len() < 2 {
+                    return Err(ConfigError::invalid(
+                        field("levels"),
+                        format!("needs at least 2 levels (got {})", levels.len()),
+                    ));
+                }
+                if !levels.iter().all(|level| level.is_finite())
+                    || levels.windows(2).any(|pair| pair[0] >= pair[1])
+                {
+                    return Err(ConfigError::invalid(
+                        field("levels"),
+                        "must be finite and strictly increasing",
+                    ));
+                }
+                Quantizer::Custom(levels.clone())
+            }
+            Some(name) => {
+                Quantizer::from_name(name, self.saturate.unwrap_or(true)).ok_or_else(|| {
+                    ConfigError::invalid(
+                        field("quantizer"),
+                        format!(
+                            "unknown quantizer {:?} (expected linear, Qm.n with m >= 1 and m + n <= {}, \
+                             e.g. Q1.15, or custom)",
+                            name, MAX_BIT_DEPTH
+                        ),
+                    )
+                })?
+            }
+            None => Quantizer::default(),
+        };
+
+        if self.levels.is_some() && !matches!(quantizer, Quantizer::Custom(_)) {
+            return Err(ConfigError::invalid(
+                field("levels"),
+                "is only used with quantizer = \"custom\"",
+            ));
+        }
+
+        // 固定小数点では語長が bit_depth になるので、省略してもよい。custom ではレベルの表で決まる
+        let bit_depth = match &quantizer {
+            Quantizer::Linear => required(&self.bit_depth, &field("bit_depth"))?,
+            Quantizer::Custom(levels) => {
+                if self.bit_depth.is_some() {
+                    return Err(ConfigError::invalid(
+                        field("bit_depth"),
+                        "is set by the number of levels of a custom quantizer",
+                    ));
+                }
+                code_bits(levels.len())
+            }
+            Quantizer::FixedPoint {
+                int_bits,
+                frac_bits,
+                ..
+            } => {
+                let word_length = int_bits + frac_bits;
+                if self
+                    .bit_depth
+                    .is_some_and(|bit_depth| bit_depth != word_length)
+                {
+                    return Err(ConfigError::invalid(
+                        field("bit_depth"),
+                        format!(
+                            "must match the word length of {} ({} bits)",
+                            self.quantizer.as_deref().unwrap_or_default(),
+                            word_length
+                        ),
+                    ));
+                }
+                word_length
+            }
+        };
+        if !(1..=MAX_BIT_DEPTH).contains(&bit_depth) {
+            return Err(ConfigError::invalid(
+                field("bit_depth"),
+                format!(
+                    "must be between 1 and {} (got {})",
+                    MAX_BIT_DEPTH, bit_depth
+                ),
+            ));
+        }
+
+        let mut params = SignalParams::new(&name, signal_freq, sampling_rate, bit_depth)
+            .with_quantizer(quantizer);
+
+        if let Some(phase) = self.phase {
+            if !phase.is_finite() {
+                return Err(ConfigError::invalid(
+                    field("phase"),
+                    "must be a finite angle in radians",
+                ));
+            }
+            params = params.with_phase(phase);
+        }
+        if let Some(duration) = self.duration {
+            if !(duration.is_finite() && duration > 0.0) {
+                return Err(ConfigError::invalid(
+                    field("duration"),
+                    format!("must be a positive time in seconds (got {})", duration),
+                ));
+            }
+            params = params.with_duration(duration);
+        }
+        if let Some(decay_rate) = self.decay_rate {
+            if !(decay_rate.is_finite() && decay_rate >= 0.0) {
+                return Err(ConfigError::invalid(
+                    field("decay_rate"),
+                    format!("must be a non-negative rate in 1/s (got {})", decay_rate),
+                ));
+            }
+            params = params.with_decay_rate(decay_rate);
+        }
+        for (name, value) in [
+            ("amplitude", self.amplitude),
+            ("full_scale", self.full_scale),
+        ] {
+            if let Some(value) = value {
+                if !(value.is_finite() && value > 0.0) {
+                    return Err(ConfigError::invalid(
+                        field(name),
+                        format!("must be a positive amplitude (got {})", value),
+                    ));
+                }
+            }
+        }
+        if let Some(amplitude) = self.amplitude {
+            params = params.with_amplitude(amplitude);
+        }
+        if let Some(full_scale) = self.full_scale {
+            params = params.with_full_scale(full_scale);
+        }
+        if let Some(jitter) = self.jitter {
+            if !(jitter.is_finite() && jitter >= 0.0) {
+                return Err(ConfigError::invalid(
+                    field("jitter"),
+                    format!("must be a non-negative time in seconds (got {})", jitter),
+                ));
+            }
+            params = params.with_jitter(jitter);
+        }
+        if let Some(input_noise) = self.input_noise {
+            if !(input_noise.is_finite() && input_noise >= 0.0) {
+                return Err(ConfigError::invalid(
+                    field("input_noise"),
+                    format!(
+                        "must be a non-negative standard deviation (got {})",
+                        input_noise
+                    ),
+                ));
+            }
+            params = params.with_input_noise(input_noise);
+        }
+        if let Some(dc_offset) = self.dc_offset {
+            if !dc_offset.is_finite() {
+                return Err(ConfigError::invalid(field("dc_offset"), "must be finite"));
+            }
+            params = params.with_dc_offset(dc_offset);
+        }
+        if let Some(name) = &self.waveform {
+            let waveform = Waveform::from_name(name).ok_or_else(|| {
+                ConfigError::invalid(
+                    field("waveform"),
+                    format!(
+                        "unknown waveform {:?} (expected sine, square or band-limited-square)",
+                        name
+                    ),
+                )
+            })?;
+            params = params.with_waveform(waveform);
+        }
+        if let Some(harmonics) = self.harmonics {
+            if harmonics == 0 {
+                return Err(ConfigError::invalid(
+                    field("harmonics"),
+                    "must be at least 1 (the fundamental)",
+                ));
+            }
+            params = params.with_harmonics(harmonics);
+        }
+        if let Some(name) = &self.dither {
+            let dither = Dither::from_name(name).ok_or_else(|| {
+                ConfigError::invalid(
+                    field("dither"),
+                    format!("unknown dither {:?} (expected none, rpdf or tpdf)", name),
+                )
+            })?;
+            params = params.with_dither(dither);
+        }
+        if let Some(noise_shaping) = self.noise_shaping {
+            params = params.with_noise_shaping(noise_shaping);
+        }
+        if let Some(name) = &self.envelope_mode {
+            let envelope_mode = EnvelopeMode::from_name(name).ok_or_else(|| {
+                ConfigError::invalid(
+                    field("envelope_mode"),
+                    format!(
+                        "unknown envelope mode {:?} (expected quantized or display-only)",
+                        name
+                    ),
+                )
+            })?;
+            params = params.with_envelope_mode(envelope_mode);
+        }
+
+        if let Some(color) = &self.color {
+            if !is_valid_color(color) {
+                return Err(ConfigError::invalid(
+                    field("color"),
+                    format!(
+                        "invalid color {:?} (expected #rrggbb or rgba(r, g, b, a))",
+                        color
+                    ),
+                ));
+            }
+            params = params.with_color(color);
+        }
+
+        Ok(params)
+    }
+}
+
+impl AxisFontConfig {
+    fn validate(&self, prefix: &str) -> Result<AxisFont, ConfigError> {
+        let field = |name: &str| format!("{}.{}", prefix, name);
+
+        if self
+            .family
+            .as_deref()
+            .is_some_and(|family| family.trim().is_empty())
+        {
+            return Err(ConfigError::invalid(field("family"), "must not be empty"));
+        }
+        for (name, size) in [
+            ("title_size", self.title_size),
+            ("tick_size", self.tick_size),
+        ] {
+            if let Some(size) = size {
+                if !(1..=MAX_FONT_SIZE).contains(&size) {
+                    return Err(ConfigError::invalid(
+                        field(name),
+                        format!("must be between 1 and {} (got {})", MAX_FONT_SIZE, size),
+                    ));
+                }
+            }
+        }
+
+        Ok(AxisFont {
+            family: self.family.clone(),
+            title_size: self.title_size,
+            tick_size: self.tick_size,
+        })
+    }
+}
+
+impl AxesConfig {
+    // 指定のあった軸の種類とそのフォント
+    fn validate(&self) -> Result<Vec<(AxisKind, AxisFont)>, ConfigError> {
+        [
+            (AxisKind::Time, "time", &self.time),
+            (AxisKind::Spectrum, "spectrum", &self.spectrum),
+        ]
+        .into_iter()
+        .filter_map(|(kind, name, font)| Some((kind, name, font.as_ref()?)))
+        .map(|(kind, name, font)| Ok((kind, font.validate(&format!("axes.{}", name))?)))
+        .collect()
+    }
+}
+
+impl Config {
+    // 拡張子が .json なら JSON、それ以外は TOML として読む
+    pub fn parse(text: &str, json: bool) -> Result<Self, ConfigError> {
+        if json {
+            serde_json::from_str(text).map_err(|e| ConfigError::Parse(e.to_string()))
+        } else {
+            toml::from_str(text).map_err(|e| ConfigError::Parse(e.to_string()))
+        }
+    }
+
+    // 全フィールドを検査し、描画に使うパラメータに変換する
+    // enabled = false の信号は数えず、エラーメッセージの番号はファイル上の位置のまま
+    pub fn validate(&self) -> Result<Vec<SignalParams>, ConfigError> {
+        self.validate_seeded(0)
+    }
+
+    // seed を指定していない信号には seed + パネル番号（有効な信号の中での順番）を使う
+    // 信号ごとに別の乱数列になるので、雑音やディザがパネル間で相関しない
+    pub fn validate_seeded(&self, seed: u64) -> Result<Vec<SignalParams>, ConfigError> {
+        self.axes.validate()?;
+        self.grid_mode()?;
+        self.sample_line()?;
+        self.outputs()?;
+        if self.signals.is_empty() {
+            return Err(ConfigError::invalid(
+                "signals",
+                "at least one signal is required",
+            ));
+        }
+        let enabled: Vec<(usize, &SignalConfig)> = self
+            .signals
+            .iter()
+            .enumerate()
+            .filter(|(_, signal)| signal.enabled.unwrap_or(true))
+            .collect();
+        if enabled.is_empty() {
+            return Err(ConfigError::invalid(
+                "signals",
+                "at least one signal must be enabled",
+            ));
+        }
+        if enabled.len() > MAX_SIGNALS {
+            return Err(ConfigError::invalid(
+                "signals",
+                format!(
+                    "at most {} enabled signals fit in one figure (got {})",
+                    MAX_SIGNALS,
+                    enabled.len()
+                ),
+            ));
+        }
+        enabled
+            .into_iter()
+            .enumerate()
+            .map(|(panel, (i, signal))| {
+                let params = signal.validate(&format!("signals[{}]", i))?;
+                Ok(params.with_seed(signal.seed.unwrap_or(seed.wrapping_add(panel as u64))))
+            })
+            .collect()
+    }
+
+    pub fn grid_mode(&self) -> Result<Option<GridMode>, ConfigError> {
+        self.grid
+            .as_deref()
+            .map(|name| {
+                GridMode::from_name(name).ok_or_else(|| {
+                    ConfigError::invalid(
+                        "grid",
+                        format!(
+                            "unknown grid mode {:?} (expected independent or coupled)",
+                            name
+                        ),
+                    )
+                })
+            })
+            .transpose()
+    }
+
+    pub fn sample_line(&self) -> Result<Option<SampleLine>, ConfigError> {
+        self.sample_line
+            .as_deref()
+            .map(|name| {
+                SampleLine::from_name(name).ok_or_else(|| {
+                    ConfigError::invalid(
+                        "sample_line",
+                        format!(
+                            "unknown sample line {:?} (expected linear, spline or hv)",
+                            name
+                        ),
+                    )
+                })
+            })
+            .transpose()
+    }
+
+    pub fn outputs(&self) -> Result<Option<Vec<Output>>, ConfigError> {
+        let Some(names) = &self.outputs else {
+            return Ok(None);
+        };
+        if names.is_empty() {
+            return Err(ConfigError::invalid(
+                "outputs",
+                "at least one output is required",
+            ));
+        }
+        let mut outputs = Vec::new();
+        for (i, name) in names.iter().enumerate() {
+            let output = Output::from_name(name).ok_or_else(|| {
+                ConfigError::invalid(
+                    format!("outputs[{}]", i),
+                    format!(
+                        "unknown output {:?} (expected comparison, spectrum, iq, metrics or report)",
+                        name
+                    ),
+                )
+            })?;
+            if !outputs.contains(&output) {
+                outputs.push(output);
+            }
+        }
+        Ok(Some(outputs))
+    }
+
+    // [axes] の上書きをテーマに反映する
+    pub fn apply_axis_fonts(&self, theme: Theme) -> Result<Theme, ConfigError> {
+        Ok(self
+            .axes
+            .validate()?
+            .into_iter()
+            .fold(theme, |theme, (kind, font)| {
+                theme.with_axis_font(kind, font)
+            }))
+    }
+}
+
+// テーマファイルを読み込む。色は設定ファイルの color と同じ書式だけを受け付ける
+pub fn load_theme(path: &Path) -> Result<Theme, ConfigError> {
+    let text = std::fs::read_to_string(path).map_err(|e| ConfigError::Io(e.to_string()))?;
+    let theme: Theme = if path.extension().is_some_and(|ext| ext == "json") {
+        serde_json::from_str(&text).map_err(|e| ConfigError::Parse(e.to_string()))?
+    } else {
+        toml::from_str(&text).map_err(|e| ConfigError::Parse(e.to_string()))?
+    };
+
+    if theme.palette.is_empty() {
+        return Err(ConfigError::invalid(
+            "palette",
+            "at least one color is required",
+        ));
+    }
+    let colors = [
+        ("text_color".to_string(), &theme.text_color),
+        ("background".to_string(), &theme.background),
+        ("grid_color".to_string(), &theme.grid_color),
+        ("guide_color".to_string(), &theme.guide_color),
+        ("zero_line.color".to_string(), &theme.zero_line.color),
+    ]
+    .into_iter()
+    .chain(
+        theme
+            .severity_colors
+            .iter()
+            .enumerate()
+            .map(|(i, color)| (format!("severity_colors[{}]", i), color)),
+    )
+    .chain(
+        theme
+            .palette
+            .iter()
+            .enumerate()
+            .map(|(i, color)| (format!("palette[{}]", i), color)),
+    );
+    for (field, color) in colors {
+        if !is_valid_color(color) {
+            return Err(ConfigError::invalid(
+                field,
+                format!(
+                    "invalid color {:?} (expected #rrggbb or rgba(r, g, b, a))",
+                    color
+                ),
+            ));
+        }
+    }
+    if theme.zero_line.width == 0 {
+        return Err(ConfigError::invalid(
+            "zero_line.width",
+            "must be at least 1",
+        ));
+    }
+    if theme.font_family.trim().is_empty() {
+        return Err(ConfigError::invalid("font_family", "must not be empty"));
+    }
+
+    Ok(theme)
+}
+
+// 設定ファイルを読み込む（検査はしない）
+pub fn read_config(path: &Path) -> Result<Config, ConfigError> {
+    let text = std::fs::read_to_string(path).map_err(|e| ConfigError::Io(e.to_string()))?;
+    let json = path.extension().is_some_and(|ext| ext == "json");
+    Config::parse(&text, json)
+}
+
+// 設定ファイルを読み込んで検査する
+pub fn load_config(path: &Path) -> Result<Vec<SignalParams>, ConfigError> {
+    read_config(path)?.validate()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn validate(text: &str) -> Result<Vec<SignalParams>, ConfigError> {
+        Config::parse(text, false)?.validate()
+    }
+
+    fn error(text: &str) -> String {
+        match validate(text) {
+            Ok(_) => panic!("config should be rejected"),
+            Err(error) => error.to_string(),
+        }
+    }
+
+    const VALID: &str = r#"
+[[signals]]
+name = "Aliasing"
+signal_freq = 10.0
+sampling_rate = 12
+bit_depth = 16
+"#;
+
+    #[test]
+    fn valid_signals_become_params() {
+        let params = validate(&format!("{}\n{}", VALID, VALID.replace("10.0", "\"A4\""))).unwrap();
+        assert_eq!(params.len(), 2);
+        assert_eq!(params[0].name, "Aliasing");
+        assert_eq!(params[0].bit_depth, 16);
+        assert_eq!(params[1].signal_freq, 440.0);
+        // seed を書かない信号はパネル番号でずらす
+        assert_ne!(params[0].seed, params[1].seed);
+    }
+
+    // エラーはファイル上の位置でフィールドを指す
+    #[test]
+    fn errors_name_the_field_path() {
+        let missing = format!("{}\n{}", VALID, VALID.replace("bit_depth = 16\n", ""));
+        assert_eq!(
+            error(&missing),
+            "signals[1].bit_depth: required field is missing"
+        );
+        assert_eq!(
+            error(&VALID.replace("bit_depth = 16", "bit_depth = 32")),
+            format!(
+                "signals[0].bit_depth: must be between 1 and {} (got 32)",
+                MAX_BIT_DEPTH
+            )
+        );
+        assert_eq!(
+            error(&format!("{}levels = [-1.0, 0.0, 1.0]\n", VALID)),
+            "signals[0].levels: is only used with quantizer = \"custom\""
+        );
+        // 無効にした信号は検査しないが、番号は数える
+        let disabled = format!(
+            "{}enabled = false\n{}",
+            VALID.replace("bit_depth = 16", "bit_depth = 0"),
+            VALID.replace("12", "0")
+        );
+        assert_eq!(
+            error(&disabled),
+            "signals[1].sampling_rate: must be a positive rate in Hz (got 0)"
+        );
+    }
+
+    #[test]
+    fn unknown_fields_are_rejected() {
+        let unknown = format!("{}bitdepth = 16\n", VALID);
+        assert!(matches!(validate(&unknown), Err(ConfigError::Parse(_))));
+        assert!(error(&unknown).contains("unknown field `bitdepth`"));
+        assert!(matches!(
+            validate("grid = \"coupled\"\nsignals = []"),
+            Err(ConfigError::Invalid { .. })
+        ));
+    }
+}
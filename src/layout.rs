@@ -0,0 +1,1015 @@
+use plotly::{
+    common::{Anchor, DashType, Font, LineShape},
+    layout::{
+        Annotation, ArrowSide, Axis, AxisType, GridPattern, HAlign, Margin, Shape, ShapeLayer,
+        ShapeLine, ShapeType, VAlign,
+    },
+    Layout, Scatter,
+};
+
+use serde::Serialize;
+
+use crate::metrics::{aliasing_severity, SignalMetrics};
+use crate::signal::SignalParams;
+use crate::single::amplitude_range;
+use crate::spectrum::{Peak, PeakKind};
+use crate::theme::{AxisKind, Theme};
+
+// dBFS 表示の下限（無音部分で -inf にならないように）
+pub const DBFS_FLOOR: f64 = -120.0;
+
+// 時間波形の縦軸の表し方
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum AmplitudeScale {
+    #[default]
+    Linear, // ADC の単位（±full_scale）のままの線形の振幅
+    Dbfs,   // 20*log10(|y| / フルスケール)。指数減衰のエンベロープが直線になる
+    LogAbs, // |y| を対数軸で表示。量子化レベルが水平線として並び、その間隔が量子化ステップを表す
+}
+
+impl AmplitudeScale {
+    // full_scale は信号の SignalParams::full_scale（dBFS の 0 dB になる振幅）
+    pub fn apply(self, values: &[f64], full_scale: f64) -> Vec<f64> {
+        match self {
+            Self::Linear => values.to_vec(),
+            Self::Dbfs => values
+                .iter()
+                .map(|v| (20.0 * (v.abs() / full_scale).log10()).max(DBFS_FLOOR))
+                .collect(),
+            // 対数軸では 0 を描けないので DBFS_FLOOR と同じ振幅で下限を取る
+            Self::LogAbs => values
+                .iter()
+                .map(|v| v.abs().max(10f64.powf(DBFS_FLOOR / 20.0)))
+                .collect(),
+        }
+    }
+
+    pub fn axis_title(self) -> &'static str {
+        match self {
+            Self::Linear => "Amplitude",
+            Self::Dbfs => "Amplitude (dBFS)",
+            Self::LogAbs => "|Amplitude| (log)",
+        }
+    }
+
+    // 対数軸の range は log10 の値で指定する
+    // 線形では信号ごとのフルスケールと直流成分に合わせる（single::amplitude_range）
+    pub fn range(self, params: &SignalParams) -> Vec<f64> {
+        match self {
+            Self::Linear => amplitude_range(params),
+            Self::Dbfs => vec![DBFS_FLOOR, 6.0],
+            Self::LogAbs => vec![DBFS_FLOOR / 20.0, 0.3],
+        }
+    }
+
+    pub fn axis_type(self) -> AxisType {
+        match self {
+            Self::Linear | Self::Dbfs => AxisType::Linear,
+            Self::LogAbs => AxisType::Log,
+        }
+    }
+}
+
+// 比較グリッドのパネル同士の軸のつなぎ方
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum GridMode {
+    #[default]
+    Independent, // パネルごとに別の軸。ズームやパンは1枚だけに効く
+    Coupled, // 同じ列は x 軸、同じ行は y 軸を共有し、範囲が連動する
+}
+
+impl GridMode {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "independent" => Some(Self::Independent),
+            "coupled" => Some(Self::Coupled),
+            _ => None,
+        }
+    }
+
+    pub fn pattern(self) -> GridPattern {
+        match self {
+            Self::Independent => GridPattern::Independent,
+            Self::Coupled => GridPattern::Coupled,
+        }
+    }
+
+    // index番目のパネルの (x, y) 軸が範囲を合わせる先の軸（"x1" ではなく "x"）
+    // Coupled では列の先頭パネルの x 軸と行の先頭パネルの y 軸に合わせ、先頭パネル自身は None
+    pub fn linked_axes(self, index: usize, cols: usize) -> (Option<String>, Option<String>) {
+        let axis_name = |prefix: &str, index: usize| match index {
+            0 => prefix.to_string(),
+            _ => format!("{}{}", prefix, index + 1),
+        };
+        match self {
+            Self::Independent => (None, None),
+            Self::Coupled => {
+                let (column_head, row_head) = (index % cols, index - index % cols);
+                (
+                    (column_head != index).then(|| axis_name("x", column_head)),
+                    (row_head != index).then(|| axis_name("y", row_head)),
+                )
+            }
+        }
+    }
+}
+
+// サンプル点を結ぶ線の描き方。表示だけの設定で、再構成（--reconstruction）とは関係しない
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum SampleLine {
+    #[default]
+    Linear, // 直線で結ぶ
+    Spline,    // 滑らかな曲線で結ぶ
+    Staircase, // 次のサンプルまで水平に引いてから縦に移る（plotly の hv）
+}
+
+impl SampleLine {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "linear" => Some(Self::Linear),
+            "spline" => Some(Self::Spline),
+            "hv" => Some(Self::Staircase),
+            _ => None,
+        }
+    }
+
+    pub fn shape(self) -> LineShape {
+        match self {
+            Self::Linear => LineShape::Linear,
+            Self::Spline => LineShape::Spline,
+            Self::Staircase => LineShape::Hv,
+        }
+    }
+}
+
+// 波形パネルの横軸。Samples ではサンプル点を番号 n = 0, 1, ... に置き、連続信号は n = t·fs に写す
+// 教科書の離散時間信号 x[n] の描き方で、正規化周波数で考えるときの見方
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum TimeAxis {
+    #[default]
+    Seconds,
+    Samples,
+}
+
+impl TimeAxis {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "time" => Some(Self::Seconds),
+            "samples" => Some(Self::Samples),
+            _ => None,
+        }
+    }
+
+    pub fn title(self) -> &'static str {
+        match self {
+            Self::Seconds => "Time (s)",
+            Self::Samples => "Sample index n",
+        }
+    }
+
+    // 時刻 t (s) を横軸の座標にする
+    pub fn position(self, t: f64, sampling_rate: f64) -> f64 {
+        match self {
+            Self::Seconds => t,
+            Self::Samples => t * sampling_rate,
+        }
+    }
+
+    pub fn positions(self, times: &[f64], sampling_rate: f64) -> Vec<f64> {
+        times
+            .iter()
+            .map(|&t| self.position(t, sampling_rate))
+            .collect()
+    }
+
+    // サンプル点の横軸。Samples ではジッタでずれた時刻によらず整数の番号にする
+    pub fn sample_positions(self, sample_x: &[f64]) -> Vec<f64> {
+        match self {
+            Self::Seconds => sample_x.to_vec(),
+            Self::Samples => (0..sample_x.len()).map(|n| n as f64).collect(),
+        }
+    }
+}
+
+// スペクトルの横軸はナイキスト周波数と信号周波数の大きい方の何倍まで表示するか
+const SPECTRUM_SPAN: f64 = 1.25;
+
+// パネル間（および図の端）の余白。paper 座標
+pub const PANEL_GAP: f64 = 0.1;
+
+// rows x cols のグリッドの各パネルのドメイン（x, y）を読み順（左上→右→下の行）で返す
+// 各パネルの周囲に gap/2 ずつ余白を取るので、隣り合うパネルの間隔は gap になる
+pub fn panel_domains(rows: usize, cols: usize, gap: f64) -> Vec<([f64; 2], [f64; 2])> {
+    let width = 1.0 / cols as f64;
+    let height = 1.0 / rows as f64;
+    (0..rows)
+        .flat_map(|row| (0..cols).map(move |col| (row, col)))
+        .map(|(row, col)| {
+            let x0 = col as f64 * width;
+            let y1 = 1.0 - row as f64 * height;
+            (
+                [x0 + gap / 2.0, x0 + width - gap / 2.0],
+                [y1 - height + gap / 2.0, y1 - gap / 2.0],
+            )
+        })
+        .collect()
+}
+
+// 各パネルの縦横比（ピクセルでの 幅 / 高さ）を aspect にそろえる。width, height は図全体のピクセル数
+// 比率より広すぎる方向だけをセルの中央に向かって縮めるので、パネルがセルからはみ出すことはない
+pub fn fit_aspect(
+    domains: Vec<([f64; 2], [f64; 2])>,
+    aspect: f64,
+    width: usize,
+    height: usize,
+) -> Vec<([f64; 2], [f64; 2])> {
+    let (width, height) = (width as f64, height as f64);
+    let shrink = |[start, end]: [f64; 2], size: f64| {
+        let center = (start + end) / 2.0;
+        [center - size / 2.0, center + size / 2.0]
+    };
+    domains
+        .into_iter()
+        .map(|(x, y)| {
+            let (panel_width, panel_height) = ((x[1] - x[0]) * width, (y[1] - y[0]) * height);
+            if panel_width > panel_height * aspect {
+                (shrink(x, panel_height * aspect / width), y)
+            } else {
+                (x, shrink(y, panel_width / aspect / height))
+            }
+        })
+        .collect()
+}
+
+// count 個のパネルを並べるグリッドの (rows, cols)。2列を基本にする
+pub fn grid_dimensions(count: usize) -> (usize, usize) {
+    let cols = count.clamp(1, 2);
+    (count.div_ceil(cols).max(1), cols)
+}
+
+pub fn create_layout_guides(theme: &Theme) -> Vec<Annotation> {
+    let mut guides = Vec::new();
+
+    // ガイド用フォントの設定（本文と同じフォントで、色だけ薄くする）
+    let guide_font = theme.font(8).color(theme.guide_color.clone());
+
+    // X軸ガイド（0.0から1.0まで0.1刻み）
+    for i in 0..=10 {
+        let x = i as f64 * 0.1;
+        guides.push(
+            Annotation::new()
+                .text(format!("x: {:.1}", x))
+                .x_ref("paper")
+                .y_ref("paper")
+                .x(x)
+                .y(0.0) // 下端
+                .show_arrow(false)
+                .font(guide_font.clone()),
+        );
+
+        // 垂直の点線を示すためのテキスト
+        guides.push(
+            Annotation::new()
+                .text("|")
+                .x_ref("paper")
+                .y_ref("paper")
+                .x(x)
+                .y(0.5) // 中央
+                .show_arrow(false)
+                .font(guide_font.clone()),
+        );
+    }
+
+    // Y軸ガイド（0.0から1.0まで0.1刻み）
+    for i in 0..=10 {
+        let y = i as f64 * 0.1;
+        guides.push(
+            Annotation::new()
+                .text(format!("y: {:.1}", y))
+                .x_ref("paper")
+                .y_ref("paper")
+                .x(0.0) // 左端
+                .y(y)
+                .show_arrow(false)
+                .font(guide_font.clone()),
+        );
+
+        // 水平の点線を示すためのテキスト
+        guides.push(
+            Annotation::new()
+                .text("—")
+                .x_ref("paper")
+                .y_ref("paper")
+                .x(0.5) // 中央
+                .y(y)
+                .show_arrow(false)
+                .font(guide_font.clone()),
+        );
+    }
+
+    guides
+}
+
+// 指標ごとの小数点以下の桁数。ここにない指標は DEFAULT_METRIC_PRECISION 桁
+pub const METRIC_PRECISION: &[(&str, usize)] = &[
+    ("Nyquist Ratio", 2),
+    ("Signal", 1),
+    ("Sampling", 0),
+    ("Nyquist", 1),
+    ("Samples/Cycle", 1),
+    ("Signal Level", 1),
+    ("Aliasing Severity", 2),
+    ("Clock Jitter", 2),
+    ("Input Noise", 3),
+    ("SQNR", 1),
+    ("THD", 3),
+    ("RMS", 3),
+    ("Bandwidth", 1),
+];
+
+pub const DEFAULT_METRIC_PRECISION: usize = 2;
+
+pub fn metric_precision(name: &str) -> usize {
+    METRIC_PRECISION
+        .iter()
+        .find(|(metric, _)| *metric == name)
+        .map_or(DEFAULT_METRIC_PRECISION, |&(_, precision)| precision)
+}
+
+// "名前: 値単位" の形にする。単位は前の空白も含めて渡す（"Hz" なら 10.0Hz、" dB" なら 12.3 dB）
+pub fn format_metric(name: &str, value: f64, unit: &str) -> String {
+    format!("{}: {:.*}{}", name, metric_precision(name), value, unit)
+}
+
+// 1周期あたりのサンプル数。2 未満ならナイキスト条件を満たさないことを添える
+fn samples_per_cycle_line(samples: f64) -> String {
+    let line = format_metric("Samples/Cycle", samples, "");
+    if samples < 2.0 {
+        format!("{} (< 2)", line)
+    } else {
+        line
+    }
+}
+
+// 値は metrics（metrics::analyze の結果）から取り、書式は format_metric にそろえる
+pub fn generate_title(params: &SignalParams, metrics: &SignalMetrics) -> String {
+    let notes: Vec<String> = params
+        .quantizer
+        .description()
+        .into_iter()
+        .chain(params.noise_shaping.then(|| "error feedback".to_string()))
+        .collect();
+    let bit_depth = if notes.is_empty() {
+        format!("{}-bit", params.bit_depth)
+    } else {
+        format!("{}-bit ({})", params.bit_depth, notes.join(", "))
+    };
+    let mut lines = vec![
+        format!(
+            "{} ({})",
+            params.name,
+            format_metric("Nyquist Ratio", metrics.nyquist_ratio, "")
+        ),
+        format_metric("Signal", params.signal_freq, "Hz"),
+        format_metric("Sampling", params.sampling_rate as f64, "Hz"),
+        format_metric("Nyquist", params.sampling_rate as f64 / 2.0, "Hz"),
+        samples_per_cycle_line(metrics.samples_per_cycle),
+        format!("Bit Depth: {}", bit_depth),
+        format_metric("Aliasing Severity", metrics.aliasing_severity, ""),
+    ];
+    // フルスケールを使い切らない信号は、その分 SQNR が下がるので水準を示す
+    if params.amplitude != params.full_scale {
+        lines.push(format_metric("Signal Level", params.level_dbfs(), " dBFS"));
+    }
+    if params.jitter > 0.0 {
+        lines.push(format_metric(
+            "Clock Jitter",
+            params.jitter * 1e6,
+            " µs rms",
+        ));
+    }
+    if params.input_noise > 0.0 {
+        lines.push(format_metric("Input Noise", params.input_noise, " FS rms"));
+    }
+    lines.extend(repeat_note(params));
+    lines.join("<br>")
+}
+
+// --report-md の表。1行1信号で、数値の桁数はタイトルと同じ METRIC_PRECISION に従う
+pub fn markdown_report(params: &[SignalParams], metrics: &[SignalMetrics]) -> String {
+    let value = |name: &str, value: f64| format!("{:.*}", metric_precision(name), value);
+    let mut lines = vec![
+        "| Signal | Frequency (Hz) | Sampling Rate (Hz) | Nyquist Ratio | Apparent Frequency (Hz) | Samples/Cycle | Bit Depth | SQNR (dB) |".to_string(),
+        "|---|---:|---:|---:|---:|---:|---:|---:|".to_string(),
+    ];
+    for (param, metrics) in params.iter().zip(metrics) {
+        lines.push(format!(
+            "| {} | {} | {} | {} | {} | {} | {} | {} |",
+            param.name.replace('|', "\\|"), // 表の区切りと混ざらないようにする
+            value("Signal", param.signal_freq),
+            value("Sampling", param.sampling_rate as f64),
+            value("Nyquist Ratio", metrics.nyquist_ratio),
+            value("Signal", metrics.aliased_frequency),
+            value("Samples/Cycle", metrics.samples_per_cycle),
+            param.bit_depth,
+            value("SQNR", metrics.sqnr_db),
+        ));
+    }
+    lines.join("\n") + "\n"
+}
+
+// サンプル列が表示範囲内で繰り返すなら、その周期を注記する
+// 繰り返しがあるとエイリアスはノイズではなく静止した低い周波数の音に見える
+pub fn repeat_note(params: &SignalParams) -> Option<String> {
+    let period = params.sample_period()?;
+    (period < params.num_samples() as u64)
+        .then(|| format!("Samples repeat every {} points", period))
+}
+
+// 信号周波数がちょうどナイキスト周波数のときの注意書き
+pub const NYQUIST_PHASE_NOTE: &str = "Signal is exactly at Nyquist (Fs/2):<br>\
+samples hit the same phase every half cycle,<br>\
+so the result depends only on the starting phase<br>\
+(0° → all zeros, 90° → ±full amplitude)";
+
+// ナイキスト周波数ちょうどの信号なら、位相依存を説明する注記をパネル左下に付ける
+pub fn nyquist_note(
+    params: &SignalParams,
+    index: usize,
+    font: Font,
+    theme: &Theme,
+) -> Option<Annotation> {
+    if !params.is_at_nyquist() {
+        return None;
+    }
+    Some(
+        Annotation::new()
+            .show_arrow(false)
+            .text(NYQUIST_PHASE_NOTE)
+            .font(font.color("rgba(214, 39, 40, 1.0)"))
+            .align(HAlign::Left)
+            .x_anchor(Anchor::Left)
+            .y_anchor(Anchor::Bottom)
+            .x_ref(format!("x{} domain", index + 1))
+            .y_ref(format!("y{} domain", index + 1))
+            .x(0.02)
+            .y(0.02)
+            .border_color("rgba(214, 39, 40, 1.0)")
+            .border_pad(2.0)
+            .background_color(theme.background.clone()),
+    )
+}
+
+// --metrics-annotation のときのタイトル。generate_title の1行目（信号名とナイキスト比）だけにする
+pub fn short_title(params: &SignalParams, metrics: &SignalMetrics) -> String {
+    format!(
+        "{} ({})",
+        params.name,
+        format_metric("Nyquist Ratio", metrics.nyquist_ratio, "")
+    )
+}
+
+// 測定した指標（SQNR・THD・ENOB・RMS・クレストファクタ）をパネルの右下に別の注記として置く
+// 左下は nyquist_note が使うので避ける
+pub fn metrics_annotation(
+    metrics: &SignalMetrics,
+    index: usize,
+    font: Font,
+    theme: &Theme,
+) -> Annotation {
+    let text = [
+        format_metric("SQNR", metrics.sqnr_db, " dB"),
+        format_metric("THD", metrics.thd_percent, " %"),
+        format_metric("ENOB", metrics.enob, " bits"),
+        format_metric("RMS", metrics.rms, ""),
+        format_metric("Crest Factor", metrics.crest_factor, ""),
+    ]
+    .join("<br>");
+    Annotation::new()
+        .show_arrow(false)
+        .text(text)
+        .font(font)
+        .align(HAlign::Left)
+        .x_anchor(Anchor::Right)
+        .y_anchor(Anchor::Bottom)
+        .x_ref(format!("x{} domain", index + 1))
+        .y_ref(format!("y{} domain", index + 1))
+        .x(0.98)
+        .y(0.02)
+        .border_color(theme.text_color.clone())
+        .border_pad(2.0)
+        .background_color(theme.background.clone())
+}
+
+// トレースを index番目（0始まり）のパネルの x/y 軸に載せる
+// パネルごとの軸名（"x1".."x8"）を書き並べずに済むよう、トレースのビルダーにつなげて使う
+pub trait PanelTrace {
+    fn on_panel(self, index: usize) -> Self;
+}
+
+impl<X, Y> PanelTrace for Box<Scatter<X, Y>>
+where
+    X: Serialize + Clone + 'static,
+    Y: Serialize + Clone + 'static,
+{
+    fn on_panel(self, index: usize) -> Self {
+        self.x_axis(format!("x{}", index + 1))
+            .y_axis(format!("y{}", index + 1))
+    }
+}
+
+// index番目（0始まり）のパネルに x/y 軸を設定する。plotly.rs の軸は8組まで
+pub fn set_panel_axes(layout: Layout, index: usize, x_axis: Axis, y_axis: Axis) -> Layout {
+    match index {
+        0 => layout.x_axis(x_axis).y_axis(y_axis),
+        1 => layout.x_axis2(x_axis).y_axis2(y_axis),
+        2 => layout.x_axis3(x_axis).y_axis3(y_axis),
+        3 => layout.x_axis4(x_axis).y_axis4(y_axis),
+        4 => layout.x_axis5(x_axis).y_axis5(y_axis),
+        5 => layout.x_axis6(x_axis).y_axis6(y_axis),
+        6 => layout.x_axis7(x_axis).y_axis7(y_axis),
+        7 => layout.x_axis8(x_axis).y_axis8(y_axis),
+        _ => panic!("plotly.rs supports up to 8 subplots (got index {})", index),
+    }
+}
+
+// パネル右上に置くタイトルボックス（軸ドメイン基準で配置）
+pub fn panel_title(text: String, index: usize, font: Font, theme: &Theme) -> Annotation {
+    Annotation::new()
+        .show_arrow(false)
+        .text(text)
+        .font(font)
+        .align(HAlign::Left)
+        .valign(VAlign::Top)
+        .x_anchor(Anchor::Right)
+        .y_anchor(Anchor::Top)
+        .x_ref(format!("x{} domain", index + 1))
+        .y_ref(format!("y{} domain", index + 1))
+        .x(0.98)
+        .y(0.98)
+        .border_color(theme.text_color.clone())
+        .border_pad(2.0)
+        .background_color(theme.background.clone())
+}
+
+// panel_title と同じ位置のタイトルボックスから、パネル内のデータ座標 target へ矢印を引く
+// 矢印の根元（ax, ay）を軸ドメイン基準にしてボックスを右上に残し、先端（x, y）をデータ座標に置く
+pub fn panel_callout(
+    text: String,
+    index: usize,
+    font: Font,
+    target: (f64, f64),
+    theme: &Theme,
+) -> Annotation {
+    panel_title(text, index, font, theme)
+        .x_ref(format!("x{}", index + 1))
+        .y_ref(format!("y{}", index + 1))
+        .x(target.0)
+        .y(target.1)
+        .show_arrow(true)
+        .arrow_head(2)
+        .arrow_color(theme.text_color.clone())
+        .ax_ref(format!("x{} domain", index + 1))
+        .ay_ref(format!("y{} domain", index + 1))
+        .ax(0.98)
+        .ay(0.98)
+}
+
+// スペクトル上のピークに周波数ラベルを付ける（基本波とエイリアスで色分け）
+pub fn peak_annotation(peak: &Peak, magnitude_db: f64, index: usize, font: Font) -> Annotation {
+    let color = match peak.kind {
+        PeakKind::Fundamental => "rgba(44, 160, 44, 1.0)", // 緑：本来の信号
+        PeakKind::Alias => "rgba(214, 39, 40, 1.0)",       // 赤：折り返し
+        PeakKind::Other => "rgba(127, 127, 127, 1.0)",     // 灰：その他
+    };
+    Annotation::new()
+        .text(format!("{:.1}Hz", peak.frequency))
+        .x_ref(format!("x{}", index + 1))
+        .y_ref(format!("y{}", index + 1))
+        .x(peak.frequency)
+        .y(magnitude_db)
+        .show_arrow(true)
+        .arrow_color(color)
+        .arrow_head(2)
+        .ax(0)
+        .ay(-20)
+        .font(font.color(color))
+}
+
+// 連続信号の1周期（t = 0 〜 T）にかかる両矢印と、周期・1周期あたりのサンプル数のラベル
+// 1周期のサンプル数が 2 を下回る（ナイキスト条件を満たさない）ときは赤で示す
+pub fn period_annotations(
+    params: &SignalParams,
+    index: usize,
+    font: Font,
+    time_axis: TimeAxis,
+    theme: &Theme,
+) -> Vec<Annotation> {
+    let period = params.period();
+    let end = time_axis.position(period, params.sampling_rate as f64);
+    let samples = params.samples_per_cycle();
+    let color = if samples < 2.0 {
+        "rgba(214, 39, 40, 1.0)".to_string()
+    } else {
+        theme.text_color.clone()
+    };
+    let (x_ref, y_ref) = (format!("x{}", index + 1), format!("y{} domain", index + 1));
+    // 矢印は x 軸の座標で (ax, y) → (x, y) に引く。ay は 0px で水平にする
+    let arrow = Annotation::new()
+        .text("")
+        .x_ref(x_ref.clone())
+        .y_ref(y_ref.clone())
+        .x(end)
+        .y(0.9)
+        .show_arrow(true)
+        .arrow_side(ArrowSide::StartEnd)
+        .arrow_head(2)
+        .start_arrow_head(2)
+        .arrow_color(color.clone())
+        .ax_ref(x_ref.clone())
+        .ax(0.0)
+        .ay_ref("pixel")
+        .ay(0);
+    let label = Annotation::new()
+        .show_arrow(false)
+        .text(format!(
+            "T = {:.1} ms<br>{:.2} samples/period",
+            period * 1000.0,
+            samples
+        ))
+        .font(font.color(color))
+        .x_ref(x_ref)
+        .y_ref(y_ref)
+        .x(end / 2.0)
+        .y(0.9)
+        .y_anchor(Anchor::Bottom);
+    vec![arrow, label]
+}
+
+// パネルを囲む枠。色はエイリアシングの深刻度に応じてテーマから選ぶ
+pub fn severity_border(params: &SignalParams, index: usize, theme: &Theme) -> Shape {
+    Shape::new()
+        .shape_type(ShapeType::Rect)
+        .layer(ShapeLayer::Below)
+        .x_ref(format!("x{} domain", index + 1))
+        .y_ref(format!("y{} domain", index + 1))
+        .x0(0.0)
+        .x1(1.0)
+        .y0(0.0)
+        .y1(1.0)
+        .line(
+            ShapeLine::new()
+                .color(theme.severity_color(aliasing_severity(params)))
+                .width(2.0),
+        )
+}
+
+// ナイキスト周波数より上の帯域を半透明で塗りつぶす
+// この帯域に現れるエネルギーはサンプリング後には必ず折り返される
+pub fn nyquist_band(nyquist: f64, max_freq: f64, index: usize) -> Shape {
+    Shape::new()
+        .shape_type(ShapeType::Rect)
+        .layer(ShapeLayer::Below)
+        .x_ref(format!("x{}", index + 1))
+        .y_ref(format!("y{} domain", index + 1))
+        .x0(nyquist)
+        .x1(max_freq)
+        .y0(0.0)
+        .y1(1.0)
+        .fill_color("rgba(214, 39, 40, 0.12)")
+        .line(ShapeLine::new().width(0.0))
+}
+
+// レプリカどうしが重なる周波数帯（エイリアスが生じる帯域）の塗りつぶし
+pub fn replica_overlap_band(start: f64, end: f64, index: usize) -> Shape {
+    Shape::new()
+        .shape_type(ShapeType::Rect)
+        .layer(ShapeLayer::Below)
+        .x_ref(format!("x{}", index + 1))
+        .y_ref(format!("y{} domain", index + 1))
+        .x0(start)
+        .x1(end)
+        .y0(0.0)
+        .y1(1.0)
+        .fill_color("rgba(255, 127, 14, 0.2)")
+        .line(ShapeLine::new().width(0.0))
+}
+
+// ベースバンド（-fs/2〜fs/2）の両端を示す破線
+pub fn baseband_edges(nyquist: f64, index: usize) -> [Shape; 2] {
+    [-nyquist, nyquist].map(|x| {
+        Shape::new()
+            .shape_type(ShapeType::Line)
+            .x_ref(format!("x{}", index + 1))
+            .y_ref(format!("y{} domain", index + 1))
+            .x0(x)
+            .x1(x)
+            .y0(0.0)
+            .y1(1.0)
+            .line(
+                ShapeLine::new()
+                    .width(1.0)
+                    .color("#888")
+                    .dash(DashType::Dash),
+            )
+    })
+}
+
+// 再生位置 t (s) を示す縦線。index 番目のパネルの時間軸に描く
+pub fn playhead(t: f64, index: usize) -> Shape {
+    Shape::new()
+        .shape_type(ShapeType::Line)
+        .x_ref(format!("x{}", index + 1))
+        .y_ref(format!("y{} domain", index + 1))
+        .x0(t)
+        .x1(t)
+        .y0(0.0)
+        .y1(1.0)
+        .line(ShapeLine::new().width(2.0).color("rgba(214, 39, 40, 0.9)"))
+}
+
+// スペクトルパネルの横軸の上限
+pub fn spectrum_max_freq(params: &SignalParams) -> f64 {
+    let nyquist = params.sampling_rate as f64 / 2.0;
+    nyquist.max(params.signal_freq) * SPECTRUM_SPAN
+}
+
+// スペクトルの縦軸のタイトル。A 特性を掛けたときは dB(A) と明示する
+pub fn magnitude_axis_title(a_weight: bool) -> &'static str {
+    if a_weight {
+        "Magnitude (dB(A))"
+    } else {
+        "Magnitude (dB)"
+    }
+}
+
+// N行2列のレイアウト。各行の左（軸 2r）が波形、右（軸 2r+1）がスペクトル
+// plotly.rs の軸は8組までなので、信号は4つまで
+
+pub fn side_by_side_layout(
+    params: &[SignalParams],
+    metrics: &[SignalMetrics],
+    theme: &Theme,
+    scale: AmplitudeScale,
+    severity_borders: bool,
+    a_weight: bool,
+    time_axis: TimeAxis,
+) -> Layout {
+    assert!(
+        params.len() <= 4,
+        "side-by-side layout supports up to 4 signals (got {})",
+        params.len()
+    );
+
+    let mut layout = theme
+        .apply_background(Layout::new())
+        .margin(
+            Margin::new()
+                .left(0)
+                .right(0)
+                .top(0)
+                .bottom(0)
+                .pad(0)
+                .auto_expand(true),
+        )
+        .show_legend(theme.legend.show)
+        .legend(theme.legend());
+
+    let domains = panel_domains(params.len().max(1), 2, PANEL_GAP);
+    for (row, param) in params.iter().enumerate() {
+        let time_index = 2 * row;
+        let spectrum_index = 2 * row + 1;
+
+        layout = set_panel_axes(
+            layout,
+            time_index,
+            theme
+                .styled_axis(AxisKind::Time, time_axis.title(), 7, 6)
+                .domain(&domains[time_index].0)
+                .anchor(format!("y{}", time_index + 1)),
+            theme
+                .amplitude_axis(theme.styled_axis(AxisKind::Time, scale.axis_title(), 7, 6))
+                .domain(&domains[time_index].1)
+                .anchor(format!("x{}", time_index + 1))
+                .type_(scale.axis_type())
+                .range(scale.range(param)),
+        );
+
+        let nyquist = param.sampling_rate as f64 / 2.0;
+        let max_freq = spectrum_max_freq(param);
+        layout = set_panel_axes(
+            layout,
+            spectrum_index,
+            theme
+                .styled_axis(AxisKind::Spectrum, "Frequency (Hz)", 7, 6)
+                .domain(&domains[spectrum_index].0)
+                .anchor(format!("y{}", spectrum_index + 1))
+                .range(vec![0.0, max_freq]),
+            theme
+                .styled_axis(AxisKind::Spectrum, magnitude_axis_title(a_weight), 7, 6)
+                .domain(&domains[spectrum_index].1)
+                .anchor(format!("x{}", spectrum_index + 1))
+                .range(vec![-160.0, 10.0]),
+        );
+        layout.add_shape(nyquist_band(nyquist, max_freq, spectrum_index));
+
+        if severity_borders {
+            layout.add_shape(severity_border(param, time_index, theme));
+        }
+        if let Some(note) = nyquist_note(param, time_index, theme.font(7), theme) {
+            layout.add_annotation(note);
+        }
+
+        // 波形側に詳細なタイトル、スペクトル側には同じ信号名を付けて対応させる
+        layout.add_annotation(panel_title(
+            generate_title(param, &metrics[row]),
+            time_index,
+            theme.font(8),
+            theme,
+        ));
+        layout.add_annotation(panel_title(
+            format!("{} — Spectrum", param.name),
+            spectrum_index,
+            theme.font(8),
+            theme,
+        ));
+    }
+
+    layout
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metrics::analyze;
+    use crate::signal::create_sine_wave;
+
+    // dBFS はフルスケールを 0 dB とするので、full_scale = 2 の ADC では振幅 2 が 0 dBFS
+    #[test]
+    fn dbfs_is_relative_to_full_scale() {
+        let dbfs = AmplitudeScale::Dbfs.apply(&[1.0, -2.0, 0.0], 2.0);
+        assert!((dbfs[0] + 20.0 * 2f64.log10()).abs() < 1e-12);
+        assert_eq!(dbfs[1], 0.0);
+        assert_eq!(dbfs[2], DBFS_FLOOR);
+        assert_eq!(AmplitudeScale::Linear.apply(&[-2.0], 2.0), vec![-2.0]);
+    }
+
+    #[test]
+    fn format_metric_uses_per_metric_precision() {
+        assert_eq!(format_metric("Signal", 10.0, "Hz"), "Signal: 10.0Hz");
+        assert_eq!(
+            format_metric("Sampling", 44100.0, "Hz"),
+            "Sampling: 44100Hz"
+        );
+        assert_eq!(format_metric("SQNR", 95.349, " dB"), "SQNR: 95.3 dB");
+        assert_eq!(format_metric("Unlisted", 0.5, ""), "Unlisted: 0.50");
+    }
+
+    #[test]
+    fn markdown_report_has_one_row_per_signal() {
+        let params = [
+            SignalParams::new("Aliasing", 10.0, 12, 16),
+            SignalParams::new("A|B", 10.0, 240, 8),
+        ];
+        let metrics: Vec<SignalMetrics> = params
+            .iter()
+            .map(|param| analyze(param, &create_sine_wave(param)))
+            .collect();
+        let report = markdown_report(&params, &metrics);
+        let rows: Vec<&str> = report.lines().collect();
+
+        assert_eq!(rows.len(), 4);
+        assert!(rows[2].starts_with("| Aliasing | 10.0 | 12 | 1.67 | 2.0 | 1.2 | 16 |"));
+        assert!(rows[3].starts_with("| A\\|B |"));
+    }
+
+    #[test]
+    fn title_flags_fewer_than_two_samples_per_cycle() {
+        let title_of = |params: SignalParams| {
+            let data = create_sine_wave(&params);
+            generate_title(&params, &analyze(&params, &data))
+        };
+        let title = title_of(SignalParams::new("Aliasing", 10.0, 16, 16));
+        assert!(
+            title.contains("<br>Samples/Cycle: 1.6 (< 2)<br>"),
+            "{}",
+            title
+        );
+        let title = title_of(SignalParams::new("Fine", 10.0, 240, 16));
+        assert!(title.contains("<br>Samples/Cycle: 24.0<br>"), "{}", title);
+    }
+
+    #[test]
+    fn coupled_grid_links_columns_and_rows() {
+        let links: Vec<_> = (0..4)
+            .map(|i| GridMode::Coupled.linked_axes(i, 2))
+            .collect();
+        assert_eq!(links[0], (None, None));
+        assert_eq!(links[1], (None, Some("y".to_string())));
+        assert_eq!(links[2], (Some("x".to_string()), None));
+        assert_eq!(links[3], (Some("x2".to_string()), Some("y3".to_string())));
+        assert_eq!(GridMode::Independent.linked_axes(3, 2), (None, None));
+    }
+
+    #[test]
+    fn panel_domains_tile_without_overlap() {
+        let domains = panel_domains(2, 2, PANEL_GAP);
+        assert_eq!(domains.len(), 4);
+        let expected = [
+            ([0.05, 0.45], [0.55, 0.95]),
+            ([0.55, 0.95], [0.55, 0.95]),
+            ([0.05, 0.45], [0.05, 0.45]),
+            ([0.55, 0.95], [0.05, 0.45]),
+        ];
+        for ((x, y), (ex, ey)) in domains.iter().zip(expected) {
+            for (a, b) in x.iter().chain(y).zip(ex.iter().chain(&ey)) {
+                assert!((a - b).abs() < 1e-12);
+            }
+        }
+
+        // 隣り合うパネルの間隔はどのサイズでも gap になる
+        let (rows, cols) = (3, 4);
+        let domains = panel_domains(rows, cols, 0.08);
+        for row in 0..rows {
+            for col in 1..cols {
+                let gap = domains[row * cols + col].0[0] - domains[row * cols + col - 1].0[1];
+                assert!((gap - 0.08).abs() < 1e-12);
+            }
+        }
+        for row in 1..rows {
+            let gap = domains[(row - 1) * cols].1[0] - domains[row * cols].1[1];
+            assert!((gap - 0.08).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn fit_aspect_keeps_panels_inside_their_cells() {
+        let cells = panel_domains(2, 2, PANEL_GAP);
+        for aspect in [0.5, 1.0, 16.0 / 9.0, 4.0] {
+            let fitted = fit_aspect(cells.clone(), aspect, 1200, 800);
+            for ((x, y), (cell_x, cell_y)) in fitted.iter().zip(&cells) {
+                let ratio = (x[1] - x[0]) * 1200.0 / ((y[1] - y[0]) * 800.0);
+                assert!((ratio - aspect).abs() < 1e-9, "{} vs {}", ratio, aspect);
+                assert!(x[0] >= cell_x[0] - 1e-12 && x[1] <= cell_x[1] + 1e-12);
+                assert!(y[0] >= cell_y[0] - 1e-12 && y[1] <= cell_y[1] + 1e-12);
+            }
+        }
+    }
+
+    #[test]
+    fn nyquist_note_only_for_signal_at_nyquist() {
+        let (font, theme) = (Font::new(), Theme::default());
+        assert!(nyquist_note(
+            &SignalParams::new("At Nyquist", 10.0, 20, 16),
+            0,
+            font.clone(),
+            &theme
+        )
+        .is_some());
+        assert!(nyquist_note(
+            &SignalParams::new("Near Nyquist", 10.0, 24, 16),
+            0,
+            font.clone(),
+            &theme
+        )
+        .is_none());
+        assert!(nyquist_note(
+            &SignalParams::new("Aliasing", 10.0, 12, 16),
+            0,
+            font,
+            &theme
+        )
+        .is_none());
+    }
+
+    // サンプル番号の軸では、ジッタがあってもサンプル点は整数に並び、連続信号は t·fs に写る
+    #[test]
+    fn sample_index_axis_uses_integer_positions() {
+        let params = SignalParams::new("Jitter", 10.0, 240, 16).with_jitter(1e-4);
+        let data = create_sine_wave(&params);
+        let positions = TimeAxis::Samples.sample_positions(&data.sample_x);
+        assert_eq!(positions.len(), data.sample_x.len());
+        assert!(positions.iter().enumerate().all(|(n, &x)| x == n as f64));
+        assert_eq!(TimeAxis::Samples.position(0.5, 240.0), 120.0);
+        assert_eq!(TimeAxis::Seconds.position(0.5, 240.0), 0.5);
+    }
+
+    // 矢印の先端はデータ座標、根元（タイトルボックス）は軸ドメイン基準の右上
+    #[test]
+    fn panel_callout_points_from_the_title_to_data() {
+        let callout = panel_callout(
+            "Title".to_string(),
+            1,
+            Font::new(),
+            (0.25, -0.5),
+            &Theme::default(),
+        );
+        let json = serde_json::to_value(&callout).unwrap();
+        assert_eq!(json["showarrow"], true);
+        assert_eq!(json["xref"], "x2");
+        assert_eq!(json["x"], 0.25);
+        assert_eq!(json["axref"], "x2 domain");
+        assert_eq!(json["ayref"], "y2 domain");
+        assert_eq!(json["xanchor"], "right");
+    }
+}
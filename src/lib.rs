@@ -0,0 +1,14 @@
+pub mod animation;
+pub mod cli;
+pub mod config;
+pub mod layout;
+pub mod metrics;
+pub mod pdf;
+pub mod presets;
+pub mod profile;
+pub mod reconstruct;
+pub mod signal;
+pub mod single;
+pub mod spectrum;
+pub mod theme;
+pub mod wav;
@@ -1,191 +1,491 @@
+use std::f64::consts::PI;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+use graph_builder::{
+    animation::{animated_figure, animated_html, Frame},
+    cli::{parse_args, FigureLayout, Options, Output, OutputFormat, USAGE},
+    config::{load_config, load_theme, read_config, ConfigError, MAX_SIGNALS},
+    layout::{
+        baseband_edges, create_layout_guides, fit_aspect, format_metric, generate_title,
+        grid_dimensions, magnitude_axis_title, markdown_report, metrics_annotation, nyquist_band,
+        nyquist_note, panel_callout, panel_domains, panel_title, peak_annotation,
+        period_annotations, playhead, replica_overlap_band, set_panel_axes, severity_border,
+        short_title, side_by_side_layout, spectrum_max_freq, AmplitudeScale, PanelTrace, PANEL_GAP,
+    },
+    metrics::{
+        analyze, lloyd_max_sweep, monte_carlo, reconstruction_snrs, sqnr_db, sqnr_sweep,
+        theoretical_sqnr_db, SignalMetrics, COMPARED_RECONSTRUCTIONS, LLOYD_MAX_BIT_DEPTHS,
+        SWEEP_BIT_DEPTHS,
+    },
+    pdf::{write_pdf, JpegImage},
+    presets::{preset_by_name, reference_tone, PRESETS},
+    profile::{self, Stage, Timer},
+    reconstruct::ReconstructionMode,
+    signal::{
+        create_iq_signal, create_signal_window, create_sine_wave, fold_into_windows,
+        fourier_coefficients, min_max_decimate, nyquist_limited_signal, QuantizerConfig,
+        SignalData, SignalParams, INTERPOLATION_FACTOR,
+    },
+    single::{amplitude_range, single_plot, staircase_plot, tile_plot, SignalTraces},
+    spectrum::{
+        a_weighted, aliased_frequency, apparent_rotation, classify_peaks, coherent_frequency,
+        complex_spectrum, find_peaks, folding_diagram, magnitude_db, replica_overlaps,
+        rotation_diagram, spectral_replica, spectrum, wrapped_frequency,
+    },
+    theme::{font_installed, AxisKind, Theme, FALLBACK_FONT_FAMILIES},
+    wav::{normalize_peak, write_wav, NORMALIZE_PEAK_DBFS},
+};
+use notify::{Event, RecursiveMode, Watcher};
 use plotly::{
-    common::{Font, Line, Marker, Mode, Title},
-    layout::{Annotation, Axis, GridPattern, LayoutGrid, Margin},
-    ImageFormat, Layout, Plot, Scatter,
+    common::{
+        ColorScale, ColorScalePalette, DashType, ErrorData, ErrorType, Fill, Font, HoverInfo, Line,
+        LineShape, Marker, MarkerSymbol, Mode, Position, Title,
+    },
+    configuration::{DisplayModeBar, ModeBarButtonName},
+    layout::{Annotation, AxisType, LayoutGrid, Margin},
+    Configuration, ImageFormat, Layout, Plot, Scatter,
 };
-use std::f64::consts::PI;
+use rayon::prelude::*;
+use serde::Serialize;
 
-#[derive(Clone)]
-struct SignalParams {
-    name: String,
-    signal_freq: f64,   // 入力信号の周波数 (Hz)
-    sampling_rate: i64, // サンプリング周波数 (Hz)
-    bit_depth: u32,     // 量子化ビット数
-    nyquist_ratio: f64, // ナイキスト周波数との比率
-}
-
-impl SignalParams {
-    fn new(name: &str, signal_freq: f64, sampling_rate: i64, bit_depth: u32) -> Self {
-        let nyquist_ratio = (2.0 * signal_freq) / (sampling_rate as f64);
-        Self {
-            name: name.to_string(),
-            signal_freq,
-            sampling_rate,
-            bit_depth,
-            nyquist_ratio,
-        }
-    }
-}
-
-fn create_layout_guides() -> Vec<Annotation> {
-    let mut guides = Vec::new();
-
-    // ガイド用フォントの設定
-    let guide_font = Font::new().size(8).color("#999999").family("Fira Code");
-
-    // X軸ガイド（0.0から1.0まで0.1刻み）
-    for i in 0..=10 {
-        let x = i as f64 * 0.1;
-        guides.push(
-            Annotation::new()
-                .text(&format!("x: {:.1}", x))
-                .x_ref("paper")
-                .y_ref("paper")
-                .x(x)
-                .y(0.0) // 下端
-                .show_arrow(false)
-                .font(guide_font.clone()),
-        );
-
-        // 垂直の点線を示すためのテキスト
-        guides.push(
-            Annotation::new()
-                .text("|")
-                .x_ref("paper")
-                .y_ref("paper")
-                .x(x)
-                .y(0.5) // 中央
-                .show_arrow(false)
-                .font(guide_font.clone()),
-        );
-    }
-
-    // Y軸ガイド（0.0から1.0まで0.1刻み）
-    for i in 0..=10 {
-        let y = i as f64 * 0.1;
-        guides.push(
-            Annotation::new()
-                .text(&format!("y: {:.1}", y))
-                .x_ref("paper")
-                .y_ref("paper")
-                .x(0.0) // 左端
-                .y(y)
-                .show_arrow(false)
-                .font(guide_font.clone()),
-        );
-
-        // 水平の点線を示すためのテキスト
-        guides.push(
-            Annotation::new()
-                .text("—")
-                .x_ref("paper")
-                .y_ref("paper")
-                .x(0.5) // 中央
-                .y(y)
-                .show_arrow(false)
-                .font(guide_font.clone()),
-        );
-    }
-
-    guides
-}
-
-fn create_sine_wave(params: &SignalParams) -> (Vec<f64>, Vec<f64>, Vec<f64>, Vec<f64>) {
-    let dt = 1.0 / params.sampling_rate as f64; // サンプリング間隔
-    let time_range = 2.0;
-    let num_samples = (time_range * params.sampling_rate as f64) as i64;
-
-    // 連続信号の表示用パラメータ
-    const INTERPOLATION_FACTOR: i64 = 20; // 補間の細かさ（1サンプル間を何分割するか）オーバーサンプリング率
-
-    // 理想的な連続信号（高解像度でプロット）
-    let continuous_points = num_samples * INTERPOLATION_FACTOR; // 表示用の総ポイント数
-    let continuous_x: Vec<f64> = (0..continuous_points)
-        .map(|i| i as f64 * dt / INTERPOLATION_FACTOR as f64) // より細かい時間間隔
-        .take_while(|&t| t <= time_range)
-        .collect();
+// --watch で、保存直後に続けて届く変更通知をまとめる時間
+const WATCH_DEBOUNCE: Duration = Duration::from_millis(300);
+
+// スペクトル上でラベルを付けるピークの数
+const PEAK_COUNT: usize = 3;
+
+// レプリカを描く範囲（-REPLICA_ORDER·fs〜REPLICA_ORDER·fs を中心とするもの）
+const REPLICA_ORDER: i32 = 2;
+
+// レプリカ図で描く入力の高調波の次数。素朴な矩形波はここで打ち切る
+const REPLICA_HARMONICS: usize = 15;
 
-    // 減衰係数（時定数）
-    let decay_rate = 0.5; // 減衰の速さを調整（大きいほど速く減衰）
+// アイパターンに重ねる周期の数の上限。周期の短い信号で線が多くなりすぎないようにする
+const EYE_MAX_WINDOWS: usize = 200;
 
-    let continuous_y: Vec<f64> = continuous_x
+// 折り返し図の横軸の範囲（fs の何倍まで描くか）
+const FOLDING_SPAN: f64 = 3.0;
+
+// 回転図の横軸の範囲（フレームレートの何倍まで描くか）
+const ROTATION_SPAN: f64 = 2.0;
+
+// コンスタレーション図に量子化の格子を描くレベル数の上限（6-bit の Linear まで）
+// これより細かいと線が塗りつぶしのようになり、点が見えなくなる
+const CONSTELLATION_MAX_LEVELS: usize = 65;
+
+// --sweep-html で掃引する周波数の範囲（ナイキスト周波数の何倍か）と、再生時の1コマの長さ (ms)
+const SWEEP_NYQUIST_RATIOS: (f64, f64) = (0.1, 2.0);
+const SWEEP_FRAME_MS: u64 = 100;
+
+// --oversampling で掃引するオーバーサンプリング比 Fs / 2f（1〜64 倍を 1/4 オクターブ刻み）
+const OVERSAMPLING_STEPS: i32 = 24;
+const OVERSAMPLING_STEPS_PER_OCTAVE: f64 = 4.0;
+
+// 再構成方法を重ねて表示するときの線の色（指定順に使う）
+const RECONSTRUCTION_COLORS: [&str; 5] = [
+    "rgba(214, 39, 40, 0.8)",
+    "rgba(44, 160, 44, 0.8)",
+    "rgba(148, 103, 189, 0.8)",
+    "rgba(255, 127, 14, 0.8)",
+    "rgba(140, 86, 75, 0.8)",
+];
+
+// 波形パネル（axis番目の軸）に連続信号・再構成誤差・サンプル点のトレースを追加する
+fn add_time_traces(
+    plot: &mut Plot,
+    data: SignalData,
+    param: &SignalParams,
+    axis: usize,
+    color: &str,
+    options: &Options,
+    theme: &Theme,
+) {
+    // 凡例は先頭パネルのトレースにだけ出す
+    let show_legend = axis == 0;
+    let scale = options.amplitude_scale;
+    // --x-axis samples では横軸の座標だけを置き換える。再構成やホバーの時刻は秒のまま計算する
+    let time_axis = options.time_axis;
+    let sampling_rate = param.sampling_rate as f64;
+    let sample_x = time_axis.sample_positions(&data.sample_x);
+
+    // HTML 出力でサンプル点にホバーしたときの説明（番号・時刻・量子化前の値・量子化レベル・コード）
+    let full_scale_level = 2i64.pow(param.bit_depth - 1);
+    let codes = data.quantization_codes(param);
+    let hover_text: Vec<String> = data
+        .quantization_levels(param)
         .iter()
-        .map(|&t| {
-            let decay = (-decay_rate * t).exp(); // 指数減衰
-            decay * (2.0 * PI * params.signal_freq * t).sin()
+        .zip(&codes)
+        .enumerate()
+        .map(|(i, (level, code))| {
+            format!(
+                "Sample #{}<br>t = {:.4} s<br>Raw: {:+.6}<br>Quantized: {:+.6}<br>Level: {} / ±{}<br>Code: {}",
+                i, data.sample_x[i], data.ideal_y[i], data.sample_y[i], level, full_scale_level, code
+            )
         })
         .collect();
 
-    // サンプリングと量子化
-    let amplitude_levels = 2u32.pow(params.bit_depth) as f64;
+    // 各サンプルに ±½ LSB のエラーバーを付ける。8bit 以下ではっきり見え、16bit ではほぼ見えない
+    // 誤差幅は線形の振幅で決まるので、dBFS・対数表示では付けない
+    let quantization_bounds = ErrorData::new(ErrorType::Data)
+        .array(data.quantization_bounds(param))
+        .visible(scale == AmplitudeScale::Linear)
+        .color(color.to_string())
+        .thickness(1.0)
+        .width(3);
 
-    let sample_x: Vec<f64> = (0..num_samples).map(|i| i as f64 * dt).collect();
+    // HTML では密な連続波形を間引いて軽くする（PNG は全点で描く）
+    let display = |x: &[f64], y: &[f64]| {
+        let (x, y) = match (options.format, options.decimate) {
+            (OutputFormat::Html | OutputFormat::Browser, Some(points)) => {
+                min_max_decimate(x, y, points)
+            }
+            _ => (x.to_vec(), y.to_vec()),
+        };
+        (time_axis.positions(&x, sampling_rate), y)
+    };
 
-    let sample_y: Vec<f64> = sample_x
+    // 再構成信号を連続信号と同じ時刻で評価する
+    // 方法が1つなら連続信号との差を塗りつぶし、複数なら色分けした線を重ねて比較する
+    let reconstructions = &options.reconstructions;
+    let reconstruction_traces: Vec<_> = reconstructions
         .iter()
-        .map(|&t| {
-            let decay = (-decay_rate * t).exp(); // 指数減衰
-            let raw_sin = decay * (2.0 * PI * params.signal_freq * t).sin();
-            (raw_sin * amplitude_levels / 2.0).round() / (amplitude_levels / 2.0)
+        .enumerate()
+        .map(|(k, mode)| {
+            let reconstructed = mode.reconstruct(
+                &data.sample_x,
+                &data.sample_y,
+                sampling_rate,
+                &data.continuous_x,
+            );
+            let (x, reconstructed) = display(&data.continuous_x, &reconstructed);
+            let trace = Scatter::new(x, scale.apply(&reconstructed, param.full_scale))
+                .show_legend(show_legend)
+                .mode(Mode::Lines)
+                .on_panel(axis);
+            if reconstructions.len() > 1 {
+                let color = RECONSTRUCTION_COLORS[k % RECONSTRUCTION_COLORS.len()];
+                return trace
+                    .name(format!("{} Reconstruction", mode.label()))
+                    .legend_group(format!("reconstruction-{}", k))
+                    .line(Line::new().width(1.0).color(color));
+            }
+            // 直線補間はサンプル点のトレースの線がそのまま再構成信号になるので、塗りだけにする
+            let line = match mode {
+                ReconstructionMode::Linear => Line::new().width(0.0),
+                _ => Line::new().width(1.0).color("rgba(214, 39, 40, 0.8)"),
+            };
+            trace
+                .name(format!("Reconstruction Error ({})", mode.label()))
+                .legend_group("error")
+                .line(line)
+                .fill(Fill::ToNextY) // 直前のトレース（連続信号）との間を塗る
+                .fill_color("rgba(214, 39, 40, 0.2)")
         })
         .collect();
 
-    (continuous_x, continuous_y, sample_x, sample_y)
+    // 理想的な連続信号（オリジナル）とサンプリング点の基本の2本に、このパネル用の装飾を足す
+    let (continuous_x, continuous_y) = display(&data.continuous_x, &data.continuous_y);
+    let SignalTraces {
+        continuous,
+        samples,
+    } = SignalTraces::new(
+        (continuous_x, scale.apply(&continuous_y, param.full_scale)),
+        (
+            sample_x.clone(),
+            scale.apply(&data.sample_y, param.full_scale),
+        ),
+        color,
+        axis,
+    );
+    let continuous = continuous.legend_group("original").show_legend(show_legend);
+    // 瞬時振幅で色付けする場合は細かい点の列として描き、ピーク（赤・青）とゼロ交差（黄）を見分けやすくする
+    let continuous = if options.amplitude_colors {
+        continuous.mode(Mode::Markers).marker(
+            Marker::new()
+                .size(2)
+                .color_array(continuous_y)
+                .color_scale(ColorScale::Palette(ColorScalePalette::Portland))
+                .cmin(-1.0)
+                .cmax(1.0)
+                .show_scale(false),
+        )
+    } else {
+        // --smooth では同じ点をスプラインでつなぎ、点の少ない波形の角を丸める（見た目だけ）
+        let line = Line::new().color("rgba(170, 170, 170, 0.5)");
+        let line = match options.smooth {
+            Some(tension) => line.shape(LineShape::Spline).smoothing(tension),
+            None => line,
+        };
+        continuous.line(line)
+    };
+
+    // 量子化前のサンプル値を白抜きの丸で重ねる。塗りつぶした量子化後の点との差が各サンプルの量子化誤差
+    let ideal_trace = options.ideal_samples.then(|| {
+        Scatter::new(
+            sample_x.clone(),
+            scale.apply(&data.ideal_y, param.full_scale),
+        )
+        .name("Ideal Sample (unquantized)")
+        .legend_group("ideal-samples")
+        .show_legend(show_legend)
+        .mode(Mode::Markers)
+        .marker(
+            Marker::new()
+                .size(8)
+                .symbol(MarkerSymbol::CircleOpen)
+                .color(color.to_string()),
+        )
+        .on_panel(axis)
+    });
+
+    // ナイキスト周波数で帯域制限した連続信号。帯域内の信号では元の信号に重なり、
+    // 帯域外の信号では残る成分だけになる（元の信号との差が折り返す成分）
+    let nyquist_trace = options.nyquist_limited.then(|| {
+        let limited = nyquist_limited_signal(param, &data.continuous_x);
+        let (x, y) = display(&data.continuous_x, &limited);
+        Scatter::new(x, scale.apply(&y, param.full_scale))
+            .name("Nyquist-Limited Signal")
+            .legend_group("nyquist-limited")
+            .show_legend(show_legend)
+            .mode(Mode::Lines)
+            .line(
+                Line::new()
+                    .width(1.5)
+                    .dash(DashType::Dash)
+                    .color("rgba(44, 160, 44, 0.9)"),
+            )
+            .on_panel(axis)
+    });
+
+    // 各サンプルから時間軸（振幅 0、dBFS・対数表示では下限）へ細い縦線を下ろし、等間隔の標本化時刻を示す
+    // 縦線の区切りの NaN は x 側にもあるので、scale.apply で y が下限に置き換わっても線はつながらない
+    let stem_trace = options.stems.then(|| {
+        let lines: Vec<(f64, f64)> = sample_x
+            .iter()
+            .copied()
+            .zip(data.sample_y.iter().copied())
+            .collect();
+        let (x, y) = stems(&lines);
+        Scatter::new(x, scale.apply(&y, param.full_scale))
+            .name("Sample Instants")
+            .legend_group("stems")
+            .show_legend(show_legend)
+            .mode(Mode::Lines)
+            .line(Line::new().width(0.5).color(color.to_string()))
+            .hover_info(HoverInfo::Skip)
+            .on_panel(axis)
+    });
+
+    // サンプリング点と再構成信号
+    let samples = samples
+        .hover_text_array(hover_text)
+        .hover_info(HoverInfo::Text)
+        .error_y(quantization_bounds)
+        .legend_group("samples")
+        .show_legend(show_legend)
+        .line(
+            Line::new()
+                .color(color.to_string())
+                .shape(options.sample_line.unwrap_or_default().shape()),
+        );
+    // 各サンプルの上に ADC の出力コードを表示する（低ビット・短い区間向け）
+    let samples = if options.code_labels {
+        samples
+            .mode(Mode::LinesMarkersText)
+            .text_array(codes.iter().map(|code| code.to_string()).collect())
+            .text_position(Position::TopCenter)
+            .text_font(theme.font(6))
+    } else {
+        samples
+    };
+
+    // 連続信号との差を塗る再構成のトレースは連続信号の直後、縦線はサンプル点の下に置く
+    let between = reconstruction_traces
+        .into_iter()
+        .chain(stem_trace)
+        .collect();
+    SignalTraces {
+        continuous,
+        samples,
+    }
+    .add_to(plot, between);
+    if let Some(ideal_trace) = ideal_trace {
+        plot.add_trace(ideal_trace);
+    }
+    if let Some(nyquist_trace) = nyquist_trace {
+        plot.add_trace(nyquist_trace);
+    }
 }
 
-fn generate_title(params: &SignalParams) -> String {
-    format!(
-        "{} (Nyquist Ratio: {:.2})<br>Signal: {:.1}Hz<br>Sampling: {}Hz<br>Bit Depth: {}-bit",
-        params.name,
-        params.nyquist_ratio,
-        params.signal_freq,
-        params.sampling_rate,
-        params.bit_depth
-    )
+// スペクトルパネル（axis番目の軸）にスペクトルと量子化雑音のトレースを追加し、
+// ピークのラベルを返す
+fn add_spectrum_traces(
+    plot: &mut Plot,
+    data: &SignalData,
+    param: &SignalParams,
+    axis: usize,
+    show_legend: bool,
+    peak_font: Font,
+    options: &Options,
+) -> Vec<Annotation> {
+    let sampling_rate = param.sampling_rate as f64;
+    // --a-weight ではどのスペクトルにも A 特性を掛けてから dB にする（ピークの検出も重み付け後の値で行う）
+    let weighted = |samples: &[f64], rate: f64| {
+        let (freqs, mags) = spectrum(samples, rate);
+        let mags = if options.a_weight {
+            a_weighted(&freqs, &mags)
+        } else {
+            mags
+        };
+        (freqs, mags)
+    };
+    let (freqs, mags) = weighted(&data.sample_y, sampling_rate);
+    let mags_db = magnitude_db(&mags);
+
+    // 連続信号（INTERPOLATION_FACTOR 倍の細かい格子）のスペクトルを「元々あったもの」として下に敷く
+    // ナイキストより上の成分はここにだけ現れ、サンプルのスペクトルでは折り返した位置に移る
+    if options.continuous_spectrum {
+        let fine_rate = sampling_rate * INTERPOLATION_FACTOR as f64;
+        let (fine_freqs, fine_mags) = weighted(&data.continuous_y, fine_rate);
+        let reference = Scatter::new(fine_freqs, magnitude_db(&fine_mags))
+            .name("Continuous Spectrum")
+            .legend_group("continuous-spectrum")
+            .show_legend(show_legend)
+            .mode(Mode::Lines)
+            .line(
+                Line::new()
+                    .color("rgba(127, 127, 127, 0.8)")
+                    .dash(DashType::Dot),
+            )
+            .on_panel(axis);
+        plot.add_trace(reference);
+    }
+
+    let trace = Scatter::new(freqs.clone(), mags_db)
+        .name("Spectrum")
+        .legend_group("spectrum")
+        .show_legend(show_legend)
+        .mode(Mode::LinesMarkers)
+        .line(Line::new().color("rgba(31, 119, 180, 1.0)"))
+        .marker(Marker::new().size(3).color("rgba(31, 119, 180, 1.0)"))
+        .on_panel(axis);
+    plot.add_trace(trace);
+
+    // 量子化雑音（量子化後 - 量子化前）のスペクトルを重ねる
+    // 1ビット減らすごとに雑音フロアが約6dB上がる
+    let (noise_freqs, noise_mags) = weighted(&data.quantization_error(), sampling_rate);
+    let noise = Scatter::new(noise_freqs, magnitude_db(&noise_mags))
+        .name("Quantization Noise")
+        .legend_group("noise")
+        .show_legend(show_legend)
+        .mode(Mode::Lines)
+        .line(Line::new().color("rgba(255, 127, 14, 0.8)"))
+        .on_panel(axis);
+    plot.add_trace(noise);
+
+    // 上位ピークを検出して基本波/エイリアスを色分けしたラベルを付ける
+    let resolution = sampling_rate / data.sample_y.len() as f64;
+    let peaks = find_peaks(&freqs, &mags, PEAK_COUNT, 0.1);
+    let alias = aliased_frequency(param.signal_freq, sampling_rate);
+    classify_peaks(&peaks, param.signal_freq, alias, resolution)
+        .iter()
+        .map(|peak| {
+            let db = magnitude_db(&[peak.magnitude])[0];
+            peak_annotation(peak, db, axis, peak_font.clone())
+        })
+        .collect()
 }
 
-fn main() {
+fn create_spectrum_plot(params: &[SignalParams], theme: &Theme, options: &Options) -> Plot {
     let mut plot = Plot::new();
 
-    // エイリアシングを示すパラメータセット
-    let params = vec![
-        SignalParams::new("Severe Aliasing", 10.0, 8, 16),
-        SignalParams::new("Aliasing", 10.0, 12, 16),
-        SignalParams::new("Near Nyquist", 10.0, 24, 16),
-        SignalParams::new("Hi Resolution", 10.0, 240, 16),
-    ];
+    let subplot_title_font = theme.font(8);
+    let peak_font = theme.font(7);
+
+    let mut layout = theme
+        .apply_background(Layout::new())
+        .margin(
+            Margin::new()
+                .left(0)
+                .right(0)
+                .top(0)
+                .bottom(0)
+                .pad(0)
+                .auto_expand(true),
+        )
+        .show_legend(theme.legend.show)
+        .legend(theme.legend());
+    let mut annotations = Vec::new();
+    let (rows, cols) = grid_dimensions(params.len());
+    let domains = panel_domains(rows, cols, PANEL_GAP);
 
-    // サブプロットの作成
     for (i, param) in params.iter().enumerate() {
-        let (cont_x, cont_y, sample_x, sample_y) = create_sine_wave(param);
+        let data = create_sine_wave(param);
+        annotations.extend(add_spectrum_traces(
+            &mut plot,
+            &data,
+            param,
+            i,
+            i == 0,
+            peak_font.clone(),
+            options,
+        ));
 
-        // 理想的な連続信号（オリジナル）
-        let continuous = Scatter::new(cont_x, cont_y)
-            .name("Original Signal")
-            .mode(Mode::Lines)
-            .line(Line::new().color("rgba(170, 170, 170, 0.5)"))
-            .x_axis(format!("x{}", i + 1))
-            .y_axis(format!("y{}", i + 1));
+        annotations.push(panel_title(
+            generate_title(param, &analyze(param, &data)),
+            i,
+            subplot_title_font.clone(),
+            theme,
+        ));
 
-        // サンプリング点と再構成信号
-        let samples = Scatter::new(sample_x, sample_y)
-            .name("Sampled & Reconstructed")
-            .mode(Mode::LinesMarkers)
-            .line(Line::new().color("rgba(31, 119, 180, 1.0)"))
-            .marker(Marker::new().size(8).color("rgba(255, 0, 0, 0.7)")) // サンプリング点を赤で強調
-            .x_axis(format!("x{}", i + 1))
-            .y_axis(format!("y{}", i + 1));
+        // ナイキスト周波数より上を塗りつぶして折り返し帯域を示す
+        let nyquist = param.sampling_rate as f64 / 2.0;
+        let max_freq = spectrum_max_freq(param);
+        layout.add_shape(nyquist_band(nyquist, max_freq, i));
 
-        plot.add_trace(continuous);
-        plot.add_trace(samples);
+        let (x_domain, y_domain) = domains[i];
+        layout = set_panel_axes(
+            layout,
+            i,
+            theme
+                .styled_axis(AxisKind::Spectrum, "Frequency (Hz)", 7, 6)
+                .domain(&x_domain)
+                .anchor(format!("y{}", i + 1))
+                .range(vec![0.0, max_freq]),
+            theme
+                .styled_axis(
+                    AxisKind::Spectrum,
+                    magnitude_axis_title(options.a_weight),
+                    7,
+                    6,
+                )
+                .domain(&y_domain)
+                .anchor(format!("x{}", i + 1))
+                .range(vec![-160.0, 10.0]),
+        );
     }
 
-    // レイアウト設定
+    for annotation in annotations {
+        layout.add_annotation(annotation);
+    }
+
+    plot.set_layout(layout);
+    plot
+}
 
-    let subplot_title_font = Font::new().size(8).color("#333").family("Fira Code");
-    let axis_font = Font::new().size(7).color("#333").family("Fira Code");
-    let tick_font = Font::new().size(6).color("#333").family("Fira Code");
-    let mut layout = Layout::new()
+// 複素（I/Q）サンプリングのスペクトル。-fs/2 〜 +fs/2 の両側で表示する
+fn create_iq_spectrum_plot(params: &[SignalParams], theme: &Theme) -> Plot {
+    let mut plot = Plot::new();
+
+    let subplot_title_font = theme.font(8);
+    let peak_font = theme.font(7);
+
+    let mut layout = theme
+        .apply_background(Layout::new())
         .margin(
             Margin::new()
                 .left(0)
@@ -195,150 +495,2161 @@ fn main() {
                 .pad(0)
                 .auto_expand(true),
         )
-        .grid(
-            LayoutGrid::new()
-                .rows(2)
-                .columns(2)
-                .pattern(GridPattern::Independent)
-                .sub_plots(vec!["subplot".to_string()]),
+        .show_legend(false);
+    let mut annotations = Vec::new();
+    let (rows, cols) = grid_dimensions(params.len());
+    let domains = panel_domains(rows, cols, PANEL_GAP);
+
+    for (i, param) in params.iter().enumerate() {
+        let iq = create_iq_signal(param);
+        let sampling_rate = param.sampling_rate as f64;
+        let (freqs, mags) = complex_spectrum(&iq.i, &iq.q, sampling_rate);
+        let mags_db = magnitude_db(&mags);
+
+        let trace = Scatter::new(freqs.clone(), mags_db)
+            .name("I/Q Spectrum")
+            .mode(Mode::LinesMarkers)
+            .line(Line::new().color("rgba(148, 103, 189, 1.0)"))
+            .marker(Marker::new().size(3).color("rgba(148, 103, 189, 1.0)"))
+            .on_panel(i);
+        plot.add_trace(trace);
+
+        // 複素サンプリングでは鏡像にならず fs 単位で周回した位置にピークが出る
+        let resolution = sampling_rate / iq.i.len() as f64;
+        let peaks = find_peaks(&freqs, &mags, PEAK_COUNT, 0.1);
+        let alias = wrapped_frequency(param.signal_freq, sampling_rate);
+        for peak in classify_peaks(&peaks, param.signal_freq, alias, resolution) {
+            let db = magnitude_db(&[peak.magnitude])[0];
+            annotations.push(peak_annotation(&peak, db, i, peak_font.clone()));
+        }
+
+        annotations.push(panel_title(
+            generate_title(param, &analyze(param, &create_sine_wave(param))),
+            i,
+            subplot_title_font.clone(),
+            theme,
+        ));
+
+        let nyquist = sampling_rate / 2.0;
+        let (x_domain, y_domain) = domains[i];
+        layout = set_panel_axes(
+            layout,
+            i,
+            theme
+                .styled_axis(AxisKind::Spectrum, "Frequency (Hz)", 7, 6)
+                .domain(&x_domain)
+                .anchor(format!("y{}", i + 1))
+                .range(vec![-nyquist, nyquist]),
+            theme
+                .styled_axis(AxisKind::Spectrum, "Magnitude (dB)", 7, 6)
+                .domain(&y_domain)
+                .anchor(format!("x{}", i + 1))
+                .range(vec![-120.0, 10.0]),
+        );
+    }
+
+    for annotation in annotations {
+        layout.add_annotation(annotation);
+    }
+
+    plot.set_layout(layout);
+    plot
+}
+
+// 再構成フィルタの振幅特性（上）と群遅延（下）。周波数は fs で正規化する
+// 振幅は像（fs の整数倍の周りに現れるコピー）がどれだけ残るかを、群遅延は波形が崩れずに遅れるかを示す
+fn create_response_plot(modes: &[ReconstructionMode], theme: &Theme) -> Plot {
+    let mut plot = Plot::new();
+
+    // 群遅延はナイキストまで。0 と fs/2 ちょうどは避ける
+    let magnitude_freqs: Vec<f64> = (0..=400).map(|i| i as f64 / 200.0).collect();
+    let delay_freqs: Vec<f64> = (1..200).map(|i| i as f64 / 400.0).collect();
+    for (k, mode) in modes.iter().enumerate() {
+        let color = RECONSTRUCTION_COLORS[k % RECONSTRUCTION_COLORS.len()];
+        let magnitude = magnitude_db(&mode.magnitude_response(&magnitude_freqs));
+        plot.add_trace(
+            Scatter::new(magnitude_freqs.clone(), magnitude)
+                .name(mode.label())
+                .legend_group(format!("reconstruction-{}", k))
+                .mode(Mode::Lines)
+                .line(Line::new().color(color))
+                .x_axis("x1")
+                .y_axis("y1"),
+        );
+        plot.add_trace(
+            Scatter::new(delay_freqs.clone(), mode.group_delay(&delay_freqs))
+                .name(mode.label())
+                .legend_group(format!("reconstruction-{}", k))
+                .show_legend(false)
+                .mode(Mode::Lines)
+                .line(Line::new().color(color))
+                .x_axis("x2")
+                .y_axis("y2"),
+        );
+    }
+
+    let domains = panel_domains(2, 1, PANEL_GAP);
+    let mut layout = theme
+        .apply_background(Layout::new())
+        .show_legend(true)
+        .legend(theme.legend());
+    layout = set_panel_axes(
+        layout,
+        0,
+        theme
+            .styled_axis(AxisKind::Spectrum, "Frequency (× Fs)", 10, 9)
+            .domain(&domains[0].0)
+            .anchor("y1"),
+        theme
+            .styled_axis(AxisKind::Spectrum, "Magnitude (dB)", 10, 9)
+            .domain(&domains[0].1)
+            .anchor("x1")
+            .range(vec![-80.0, 5.0]),
+    );
+    layout = set_panel_axes(
+        layout,
+        1,
+        theme
+            .styled_axis(AxisKind::Spectrum, "Frequency (× Fs)", 10, 9)
+            .domain(&domains[1].0)
+            .anchor("y2")
+            .range(vec![0.0, 0.5]),
+        theme
+            .styled_axis(AxisKind::Spectrum, "Group Delay (samples)", 10, 9)
+            .domain(&domains[1].1)
+            .anchor("x2"),
+    );
+    layout.add_shape(nyquist_band(0.5, 2.0, 0));
+    layout.add_annotation(panel_title(
+        "Reconstruction Filter — Magnitude Response".to_string(),
+        0,
+        theme.font(8),
+        theme,
+    ));
+    layout.add_annotation(panel_title(
+        "Group Delay (constant = linear phase)".to_string(),
+        1,
+        theme.font(8),
+        theme,
+    ));
+    plot.set_layout(layout);
+    plot
+}
+
+// 最初の信号の周波数の正弦波について、オーバーサンプリング比ごとの再構成 SNR を --reconstruction の方法ごとに描く
+// 比べるため帯域制限された sinc 補間も必ず加える。有限長のサンプルで打ち切るので、その誤差で頭打ちになる
+fn create_oversampling_plot(
+    params: &[SignalParams],
+    modes: &[ReconstructionMode],
+    theme: &Theme,
+) -> Plot {
+    let mut plot = Plot::new();
+    let frequency = params[0].signal_freq;
+    let ratios: Vec<f64> = (0..=OVERSAMPLING_STEPS)
+        .map(|k| 2f64.powf(k as f64 / OVERSAMPLING_STEPS_PER_OCTAVE))
+        .collect();
+    let modes: Vec<ReconstructionMode> = if modes.contains(&ReconstructionMode::Sinc) {
+        modes.to_vec()
+    } else {
+        modes
+            .iter()
+            .copied()
+            .chain([ReconstructionMode::Sinc])
+            .collect()
+    };
+    for (k, mode) in modes.iter().enumerate() {
+        let snr: Vec<f64> = ratios
+            .iter()
+            .map(|ratio| mode.sine_snr(frequency, 2.0 * ratio * frequency))
+            .collect();
+        let line = Line::new().color(RECONSTRUCTION_COLORS[k % RECONSTRUCTION_COLORS.len()]);
+        let line = if *mode == ReconstructionMode::Sinc {
+            line.dash(DashType::Dash)
+        } else {
+            line
+        };
+        plot.add_trace(
+            Scatter::new(ratios.clone(), snr)
+                .name(mode.label())
+                .mode(Mode::LinesMarkers)
+                .line(line)
+                .marker(Marker::new().size(5))
+                .on_panel(0),
+        );
+    }
+
+    let domains = panel_domains(1, 1, PANEL_GAP);
+    let mut layout = theme
+        .apply_background(Layout::new())
+        .show_legend(true)
+        .legend(theme.legend());
+    layout = set_panel_axes(
+        layout,
+        0,
+        theme
+            .styled_axis(AxisKind::Spectrum, "Oversampling Ratio (Fs / 2f)", 10, 9)
+            .type_(AxisType::Log)
+            .domain(&domains[0].0)
+            .anchor("y1"),
+        theme
+            .styled_axis(AxisKind::Spectrum, "Reconstruction SNR (dB)", 10, 9)
+            .domain(&domains[0].1)
+            .anchor("x1")
+            .range(vec![0.0, 120.0]),
+    );
+    layout.add_annotation(panel_title(
+        format!(
+            "Reconstruction SNR vs Oversampling — {}Hz sine<br>\
+             Linear: about +12 dB per doubling (error ∝ (f/Fs)²)",
+            frequency
+        ),
+        0,
+        theme.font(8),
+        theme,
+    ));
+    plot.set_layout(layout);
+    plot
+}
+
+// 同じ信号を2通りの量子化設定 A/B で量子化して差を並べる（信号ごとに1行）
+// 左はサンプルごとの差 B - A、右は量子化誤差スペクトルの差 (dB)。右が負になる帯域では B の誤差の方が小さい
+fn create_ab_plot(
+    params: &[SignalParams],
+    a: &QuantizerConfig,
+    b: &QuantizerConfig,
+    theme: &Theme,
+) -> Plot {
+    let mut plot = Plot::new();
+    let mut layout = theme
+        .apply_background(Layout::new())
+        .margin(
+            Margin::new()
+                .left(0)
+                .right(0)
+                .top(0)
+                .bottom(0)
+                .pad(0)
+                .auto_expand(true),
         )
-        .show_legend(false)
-        .x_axis(
-            Axis::new()
-                .title(Title::with_text("Time (s)").font(axis_font.clone()))
-                .domain(&[0.05, 0.45])
-                // .range(vec![0.0, 1.0]) // 0-1秒に固定
-                .tick_font(tick_font.clone()),
+        .show_legend(false);
+
+    let domains = panel_domains(params.len().max(1), 2, PANEL_GAP);
+    for (row, param) in params.iter().enumerate() {
+        let (time_index, spectrum_index) = (2 * row, 2 * row + 1);
+        let (param_a, param_b) = (a.apply(param), b.apply(param));
+        let (data_a, data_b) = (create_sine_wave(&param_a), create_sine_wave(&param_b));
+        let sampling_rate = param.sampling_rate as f64;
+
+        let difference: Vec<f64> = data_b
+            .sample_y
+            .iter()
+            .zip(&data_a.sample_y)
+            .map(|(b, a)| b - a)
+            .collect();
+        plot.add_trace(
+            Scatter::new(data_a.sample_x.clone(), difference)
+                .name("B - A")
+                .mode(Mode::Markers)
+                .marker(Marker::new().size(4).color(theme.signal_color(row, param)))
+                .on_panel(time_index),
+        );
+
+        let (freqs, error_a) = spectrum(&data_a.quantization_error(), sampling_rate);
+        let (_, error_b) = spectrum(&data_b.quantization_error(), sampling_rate);
+        let spectrum_difference: Vec<f64> = magnitude_db(&error_b)
+            .iter()
+            .zip(magnitude_db(&error_a))
+            .map(|(b, a)| b - a)
+            .collect();
+        plot.add_trace(
+            Scatter::new(freqs, spectrum_difference)
+                .name("Error Spectrum B - A")
+                .mode(Mode::Lines)
+                .line(Line::new().color("rgba(255, 127, 14, 0.8)"))
+                .on_panel(spectrum_index),
+        );
+
+        layout = set_panel_axes(
+            layout,
+            time_index,
+            theme
+                .styled_axis(AxisKind::Time, "Time (s)", 7, 6)
+                .domain(&domains[time_index].0)
+                .anchor(format!("y{}", time_index + 1)),
+            theme
+                .amplitude_axis(theme.styled_axis(AxisKind::Time, "B - A", 7, 6))
+                .domain(&domains[time_index].1)
+                .anchor(format!("x{}", time_index + 1)),
+        );
+        layout = set_panel_axes(
+            layout,
+            spectrum_index,
+            theme
+                .styled_axis(AxisKind::Spectrum, "Frequency (Hz)", 7, 6)
+                .domain(&domains[spectrum_index].0)
+                .anchor(format!("y{}", spectrum_index + 1))
+                .range(vec![0.0, sampling_rate / 2.0]),
+            theme
+                .styled_axis(AxisKind::Spectrum, "Error Spectrum B - A (dB)", 7, 6)
+                .domain(&domains[spectrum_index].1)
+                .anchor(format!("x{}", spectrum_index + 1)),
+        );
+
+        layout.add_annotation(panel_title(
+            format!(
+                "{}<br>A: {} ({})<br>B: {} ({})",
+                param.name,
+                a.label(param_a.bit_depth),
+                format_metric("SQNR", sqnr_db(&data_a.ideal_y, &data_a.sample_y), " dB"),
+                b.label(param_b.bit_depth),
+                format_metric("SQNR", sqnr_db(&data_b.ideal_y, &data_b.sample_y), " dB")
+            ),
+            time_index,
+            theme.font(8),
+            theme,
+        ));
+        layout.add_annotation(panel_title(
+            format!("{} — Error Spectrum Difference", param.name),
+            spectrum_index,
+            theme.font(8),
+            theme,
+        ));
+    }
+
+    plot.set_layout(layout);
+    plot
+}
+
+// 同じ信号を2つのビット数 A/B で量子化し、スペクトルの差を並べる（信号ごとに1行）
+// 左は両方のスペクトル、右はサンプルの差 B - A のスペクトル。差はビットを落として加わった量子化雑音で、
+// ディザなしなら平らに（白色に）持ち上がり、誤差フィードバックをかけると高域に寄る。破線は帯域の平均
+fn create_bit_depth_diff_plot(params: &[SignalParams], a: u32, b: u32, theme: &Theme) -> Plot {
+    let mut plot = Plot::new();
+    let mut layout = theme
+        .apply_background(Layout::new())
+        .margin(
+            Margin::new()
+                .left(0)
+                .right(0)
+                .top(0)
+                .bottom(0)
+                .pad(0)
+                .auto_expand(true),
         )
-        .y_axis(
-            Axis::new()
-                .title(Title::with_text("Amplitude").font(axis_font.clone()))
-                .domain(&[0.55, 0.95])
-                .tick_font(tick_font.clone())
-                .range(vec![-1.2, 1.2]),
+        .show_legend(false);
+
+    let domains = panel_domains(params.len().max(1), 2, PANEL_GAP);
+    for (row, param) in params.iter().enumerate() {
+        let (spectra_index, difference_index) = (2 * row, 2 * row + 1);
+        let (param_a, param_b) = (
+            param.clone().with_bit_depth(a),
+            param.clone().with_bit_depth(b),
+        );
+        let (data_a, data_b) = (create_sine_wave(&param_a), create_sine_wave(&param_b));
+        let sampling_rate = param.sampling_rate as f64;
+
+        let (freqs, mags_a) = spectrum(&data_a.sample_y, sampling_rate);
+        let (_, mags_b) = spectrum(&data_b.sample_y, sampling_rate);
+        for (label, mags, color) in [
+            (format!("A ({}-bit)", a), &mags_a, "rgba(31, 119, 180, 0.8)"),
+            (format!("B ({}-bit)", b), &mags_b, "rgba(255, 127, 14, 0.8)"),
+        ] {
+            plot.add_trace(
+                Scatter::new(freqs.clone(), magnitude_db(mags))
+                    .name(label)
+                    .mode(Mode::Lines)
+                    .line(Line::new().width(1.0).color(color))
+                    .on_panel(spectra_index),
+            );
+        }
+
+        // スペクトルの差は複素数のまま引く（FFT は線形なので差のスペクトルと同じ）
+        let difference: Vec<f64> = data_b
+            .sample_y
+            .iter()
+            .zip(&data_a.sample_y)
+            .map(|(b, a)| b - a)
+            .collect();
+        let (_, difference_mags) = spectrum(&difference, sampling_rate);
+        let difference_db = magnitude_db(&difference_mags);
+        // 直流を除いた平均（dB）。白色雑音ならスペクトルはこの線の周りに散らばる
+        let mean_db = difference_db.iter().skip(1).sum::<f64>()
+            / difference_db.len().saturating_sub(1).max(1) as f64;
+        plot.add_trace(
+            Scatter::new(freqs.clone(), difference_db)
+                .name("Spectrum of B - A")
+                .mode(Mode::Lines)
+                .line(Line::new().color(theme.signal_color(row, param)))
+                .on_panel(difference_index),
+        );
+        plot.add_trace(
+            Scatter::new(vec![0.0, sampling_rate / 2.0], vec![mean_db, mean_db])
+                .name("Mean Level")
+                .mode(Mode::Lines)
+                .line(Line::new().color("#888").dash(DashType::Dash))
+                .on_panel(difference_index),
+        );
+
+        for (index, title) in [
+            (spectra_index, magnitude_axis_title(false)),
+            (difference_index, "Added Noise (dB)"),
+        ] {
+            layout = set_panel_axes(
+                layout,
+                index,
+                theme
+                    .styled_axis(AxisKind::Spectrum, "Frequency (Hz)", 7, 6)
+                    .domain(&domains[index].0)
+                    .anchor(format!("y{}", index + 1))
+                    .range(vec![0.0, sampling_rate / 2.0]),
+                theme
+                    .styled_axis(AxisKind::Spectrum, title, 7, 6)
+                    .domain(&domains[index].1)
+                    .anchor(format!("x{}", index + 1)),
+            );
+        }
+
+        layout.add_annotation(panel_title(
+            format!(
+                "{}<br>A: {}-bit ({})<br>B: {}-bit ({})",
+                param.name,
+                a,
+                format_metric("SQNR", sqnr_db(&data_a.ideal_y, &data_a.sample_y), " dB"),
+                b,
+                format_metric("SQNR", sqnr_db(&data_b.ideal_y, &data_b.sample_y), " dB")
+            ),
+            spectra_index,
+            theme.font(8),
+            theme,
+        ));
+        layout.add_annotation(panel_title(
+            format!(
+                "{} — Difference Spectrum ({}-bit vs {}-bit)<br>mean {:.1} dB",
+                param.name, b, a, mean_db
+            ),
+            difference_index,
+            theme.font(8),
+            theme,
+        ));
+    }
+
+    plot.set_layout(layout);
+    plot
+}
+
+// 線スペクトルやサンプル列を、点ごとに 0 から値までの縦線（NaN で区切った1本の線）にする
+fn stems(lines: &[(f64, f64)]) -> (Vec<f64>, Vec<f64>) {
+    lines
+        .iter()
+        .flat_map(|&(frequency, amplitude)| {
+            [
+                (frequency, 0.0),
+                (frequency, amplitude),
+                (f64::NAN, f64::NAN),
+            ]
+        })
+        .unzip()
+}
+
+// サンプリングによるスペクトルの周期的な複製。入力の両側スペクトル（ベースバンド）と ±fs, ±2fs のレプリカを並べ、
+// レプリカどうしが重なる帯域を塗る。入力の帯域が fs/2 を超えると重なりが生じ、そこがエイリアスになる
+fn create_replicas_plot(params: &[SignalParams], theme: &Theme) -> Plot {
+    let mut plot = Plot::new();
+
+    let (rows, cols) = grid_dimensions(params.len());
+    let mut layout = theme
+        .apply_background(Layout::new())
+        .margin(
+            Margin::new()
+                .left(0)
+                .right(0)
+                .top(0)
+                .bottom(0)
+                .pad(0)
+                .auto_expand(true),
         )
-        .x_axis2(
-            Axis::new()
-                .title(Title::with_text("Time (s)").font(axis_font.clone()))
-                .domain(&[0.55, 0.95])
-                // .range(vec![0.0, 1.0]) // 0-1秒に固定
-                .tick_font(tick_font.clone()),
+        .show_legend(theme.legend.show)
+        .legend(theme.legend());
+
+    for (i, (param, (x_domain, y_domain))) in params
+        .iter()
+        .zip(panel_domains(rows, cols, PANEL_GAP))
+        .enumerate()
+    {
+        let sampling_rate = param.sampling_rate as f64;
+        let lines = param.line_spectrum(REPLICA_HARMONICS);
+        for k in -REPLICA_ORDER..=REPLICA_ORDER {
+            let (x, y) = stems(&spectral_replica(&lines, sampling_rate, k));
+            let (name, color) = if k == 0 {
+                ("Baseband".to_string(), theme.signal_color(i, param))
+            } else {
+                (
+                    "Replicas".to_string(),
+                    "rgba(127, 127, 127, 0.8)".to_string(),
+                )
+            };
+            plot.add_trace(
+                Scatter::new(x, y)
+                    .mode(Mode::Lines)
+                    .name(&name)
+                    .legend_group(&name)
+                    .show_legend(i == 0 && (k == 0 || k == 1))
+                    .line(Line::new().width(2.0).color(color))
+                    .on_panel(i),
+            );
+        }
+
+        let bandwidth = lines.iter().map(|&(f, _)| f).fold(0.0, f64::max);
+        for (start, end) in replica_overlaps(bandwidth, sampling_rate, REPLICA_ORDER) {
+            layout.add_shape(replica_overlap_band(start, end, i));
+        }
+        for edge in baseband_edges(sampling_rate / 2.0, i) {
+            layout.add_shape(edge);
+        }
+
+        let span = (REPLICA_ORDER as f64 + 0.5) * sampling_rate;
+        layout = set_panel_axes(
+            layout,
+            i,
+            theme
+                .styled_axis(AxisKind::Spectrum, "Frequency (Hz)", 7, 6)
+                .domain(&x_domain)
+                .anchor(format!("y{}", i + 1))
+                .range(vec![-span, span]),
+            theme
+                .styled_axis(AxisKind::Spectrum, "Magnitude", 7, 6)
+                .domain(&y_domain)
+                .anchor(format!("x{}", i + 1)),
+        );
+        layout.add_annotation(panel_title(
+            format!(
+                "{}<br>{}",
+                generate_title(param, &analyze(param, &create_sine_wave(param))),
+                format_metric("Bandwidth", bandwidth, "Hz")
+            ),
+            i,
+            theme.font(8),
+            theme,
+        ));
+    }
+
+    plot.set_layout(layout);
+    plot
+}
+
+// アイパターン。再構成した波形を信号の1周期ごとに切り、同じ時間軸に薄く重ねる
+// 理想的なサンプリングなら周期どうしがぴったり重なり、ジッタやアンダーサンプリングで線がばらけて目が閉じる
+fn create_eye_plot(params: &[SignalParams], theme: &Theme, options: &Options) -> Plot {
+    let mut plot = Plot::new();
+    let mode = options.reconstructions[0];
+
+    let (rows, cols) = grid_dimensions(params.len());
+    let mut layout = theme
+        .apply_background(Layout::new())
+        .margin(
+            Margin::new()
+                .left(0)
+                .right(0)
+                .top(0)
+                .bottom(0)
+                .pad(0)
+                .auto_expand(true),
         )
-        .y_axis2(
-            Axis::new()
-                .title(Title::with_text("Amplitude").font(axis_font.clone()))
-                .domain(&[0.55, 0.95])
-                .tick_font(tick_font.clone())
+        .show_legend(false);
+
+    for (i, (param, (x_domain, y_domain))) in params
+        .iter()
+        .zip(panel_domains(rows, cols, PANEL_GAP))
+        .enumerate()
+    {
+        let data = create_sine_wave(param);
+        let reconstructed = mode.reconstruct(
+            &data.sample_x,
+            &data.sample_y,
+            param.sampling_rate as f64,
+            &data.continuous_x,
+        );
+        let color = theme.signal_color(i, param);
+        for (x, y) in fold_into_windows(&data.continuous_x, &reconstructed, param.period())
+            .into_iter()
+            .take(EYE_MAX_WINDOWS)
+        {
+            plot.add_trace(
+                Scatter::new(x, y)
+                    .mode(Mode::Lines)
+                    .opacity(0.3)
+                    .line(Line::new().width(1.0).color(color.clone()))
+                    .on_panel(i),
+            );
+        }
+
+        layout = set_panel_axes(
+            layout,
+            i,
+            theme
+                .styled_axis(AxisKind::Time, "Time within Period (s)", 7, 6)
+                .domain(&x_domain)
+                .anchor(format!("y{}", i + 1))
+                .range(vec![0.0, param.period()]),
+            theme
+                .amplitude_axis(theme.styled_axis(AxisKind::Time, "Amplitude", 7, 6))
+                .domain(&y_domain)
+                .anchor(format!("x{}", i + 1))
                 .range(vec![-1.2, 1.2]),
-        )
-        .x_axis3(
-            Axis::new()
-                .title(Title::with_text("Time (s)").font(axis_font.clone()))
-                .domain(&[0.05, 0.45])
-                // .range(vec![0.0, 1.0]) // 0-1秒に固定
-                .tick_font(tick_font.clone()),
-        )
-        .y_axis3(
-            Axis::new()
-                .title(Title::with_text("Amplitude").font(axis_font.clone()))
-                .domain(&[0.05, 0.45])
+        );
+        layout.add_annotation(panel_title(
+            format!(
+                "{}<br>Eye: {}",
+                generate_title(param, &analyze(param, &data)),
+                mode.label()
+            ),
+            i,
+            theme.font(8),
+            theme,
+        ));
+    }
+
+    plot.set_layout(layout);
+    plot
+}
+
+// ビット数ごとの実測 SQNR を信号ごとの線で描き、理論値 6.02N + 1.76 dB の直線を重ねる
+// 減衰して振幅が小さくなる信号や、低ビットでフルスケールを使い切れない信号は理論値から外れる
+fn create_sqnr_sweep_plot(params: &[SignalParams], theme: &Theme) -> Plot {
+    let mut plot = Plot::new();
+    let axis_font = theme.font(10);
+    let tick_font = theme.font(9);
+
+    let bit_depths: Vec<u32> = SWEEP_BIT_DEPTHS.collect();
+    let theoretical: Vec<f64> = bit_depths.iter().map(|&n| theoretical_sqnr_db(n)).collect();
+    plot.add_trace(
+        Scatter::new(bit_depths, theoretical)
+            .mode(Mode::Lines)
+            .name("Theoretical (6.02N + 1.76 dB)")
+            .line(Line::new().color("#888").dash(DashType::Dash)),
+    );
+
+    for (i, param) in params.iter().enumerate() {
+        // 量子化誤差が 0 になったビット数（SQNR が無限大）は描かない
+        let (bits, sqnrs): (Vec<u32>, Vec<f64>) = sqnr_sweep(param, SWEEP_BIT_DEPTHS)
+            .into_iter()
+            .filter(|(_, sqnr)| sqnr.is_finite())
+            .unzip();
+        let color = theme.signal_color(i, param);
+        plot.add_trace(
+            Scatter::new(bits, sqnrs)
+                .mode(Mode::LinesMarkers)
+                .name(&param.name)
+                .line(Line::new().color(color.clone()))
+                .marker(Marker::new().size(6).color(color)),
+        );
+    }
+
+    let layout = theme
+        .apply_background(Layout::new())
+        .show_legend(true)
+        .legend(theme.legend())
+        .x_axis(
+            theme
+                .axis()
+                .title(Title::with_text("Bit Depth (bits)").font(axis_font.clone()))
                 .tick_font(tick_font.clone())
-                .range(vec![-1.2, 1.2]),
-        )
-        .x_axis4(
-            Axis::new()
-                .title(Title::with_text("Time (s)").font(axis_font.clone()))
-                .domain(&[0.55, 0.95])
-                // .range(vec![0.0, 1.0]) // 0-1秒に固定
-                .tick_font(tick_font.clone()),
+                .dtick(1.0),
         )
-        .y_axis4(
-            Axis::new()
-                .title(Title::with_text("Amplitude").font(axis_font.clone()))
-                .domain(&[0.05, 0.45])
-                .tick_font(tick_font.clone())
-                .range(vec![-1.2, 1.2]),
+        .y_axis(
+            theme
+                .axis()
+                .title(Title::with_text("SQNR (dB)").font(axis_font))
+                .tick_font(tick_font),
         );
+    plot.set_layout(layout);
+    plot
+}
 
-    // サブプロットのタイトル用のアノテーションを設定
-    let annotations = vec![
-        Annotation::new()
-            .show_arrow(false)
-            .text(generate_title(&params[0]))
-            .font(subplot_title_font.clone())
-            .align(plotly::layout::HAlign::Left)
-            .valign(plotly::layout::VAlign::Top)
-            .x_anchor(plotly::common::Anchor::Right)
-            .y_anchor(plotly::common::Anchor::Top)
-            .x_ref("x")
-            .y_ref("y")
-            .x(1.95)
-            .y(0.95)
-            .border_color("#333")
-            .border_pad(2.0)
-            .background_color("#fff"),
-        Annotation::new()
-            .show_arrow(false)
-            .text(generate_title(&params[1]))
-            .font(subplot_title_font.clone())
-            .align(plotly::layout::HAlign::Left)
-            .valign(plotly::layout::VAlign::Top)
-            .x_anchor(plotly::common::Anchor::Right)
-            .y_anchor(plotly::common::Anchor::Top)
-            .x_ref("x2")
-            .y_ref("y2")
-            .x(1.95)
-            .y(0.95)
-            .border_color("#333")
-            .border_pad(2.0)
-            .background_color("#fff"),
-        Annotation::new()
-            .show_arrow(false)
-            .text(generate_title(&params[2]))
-            .font(subplot_title_font.clone())
-            .align(plotly::layout::HAlign::Left)
-            .valign(plotly::layout::VAlign::Top)
-            .x_anchor(plotly::common::Anchor::Right)
-            .y_anchor(plotly::common::Anchor::Top)
-            .x_ref("x3")
-            .y_ref("y3")
-            .x(1.95)
-            .y(0.95)
-            .border_color("#333")
-            .border_pad(2.0)
-            .background_color("#fff"),
-        Annotation::new()
-            .show_arrow(false)
-            .text(generate_title(&params[3]))
-            .font(subplot_title_font.clone())
-            .align(plotly::layout::HAlign::Left)
-            .valign(plotly::layout::VAlign::Top)
-            .x_anchor(plotly::common::Anchor::Right)
-            .y_anchor(plotly::common::Anchor::Top)
-            .x_ref("x4")
-            .y_ref("y4")
-            .x(1.95)
-            .y(0.95)
-            .border_color("#333")
-            .border_pad(2.0)
-            .background_color("#fff"),
-    ];
+// 一様量子化（破線）と Lloyd-Max 量子化（実線）の SQNR を信号ごとに同じ色で並べる
+// 振幅の分布が一様でない信号（減衰する正弦波など）ほど、同じレベル数での差が大きい
+fn create_lloyd_max_plot(params: &[SignalParams], theme: &Theme) -> Plot {
+    let mut plot = Plot::new();
+    let axis_font = theme.font(10);
+    let tick_font = theme.font(9);
 
-    for annotation in annotations {
-        layout.add_annotation(annotation);
+    for (i, param) in params.iter().enumerate() {
+        let sweep: Vec<(u32, f64, f64)> = lloyd_max_sweep(param, LLOYD_MAX_BIT_DEPTHS)
+            .into_iter()
+            .filter(|(_, uniform, optimized)| uniform.is_finite() && optimized.is_finite())
+            .collect();
+        let bits: Vec<u32> = sweep.iter().map(|t| t.0).collect();
+        let color = theme.signal_color(i, param);
+        plot.add_trace(
+            Scatter::new(bits.clone(), sweep.iter().map(|t| t.1).collect())
+                .mode(Mode::LinesMarkers)
+                .name(format!("{} (uniform)", param.name))
+                .line(Line::new().color(color.clone()).dash(DashType::Dash))
+                .marker(Marker::new().size(5).color(color.clone())),
+        );
+        plot.add_trace(
+            Scatter::new(bits, sweep.iter().map(|t| t.2).collect())
+                .mode(Mode::LinesMarkers)
+                .name(format!("{} (Lloyd-Max)", param.name))
+                .line(Line::new().color(color.clone()))
+                .marker(Marker::new().size(7).color(color)),
+        );
     }
 
-    // layout guides
-    for annotation in create_layout_guides() {
-        layout.add_annotation(annotation);
+    let layout = theme
+        .apply_background(Layout::new())
+        .title(Title::with_text("Uniform vs Lloyd-Max Quantizer").font(theme.font(12)))
+        .show_legend(true)
+        .legend(theme.legend())
+        .x_axis(
+            theme
+                .axis()
+                .title(Title::with_text("Bit Depth (bits, 2^N levels)").font(axis_font.clone()))
+                .tick_font(tick_font.clone())
+                .dtick(1.0),
+        )
+        .y_axis(
+            theme
+                .axis()
+                .title(Title::with_text("SQNR (dB)").font(axis_font))
+                .tick_font(tick_font),
+        );
+    plot.set_layout(layout);
+    plot
+}
+
+// 折り返し図。横軸の真の周波数と縦軸の見かけの周波数を fs で正規化し、fs の違う信号も同じ三角波の上に載せる
+fn create_folding_plot(params: &[SignalParams], theme: &Theme) -> Plot {
+    let mut plot = Plot::new();
+    let axis_font = theme.font(10);
+    let tick_font = theme.font(9);
+
+    let (x, y): (Vec<f64>, Vec<f64>) = folding_diagram(FOLDING_SPAN).into_iter().unzip();
+    plot.add_trace(
+        Scatter::new(x, y)
+            .mode(Mode::Lines)
+            .name("Apparent Frequency")
+            .line(Line::new().color("#888")),
+    );
+    plot.add_trace(
+        Scatter::new(vec![0.0, FOLDING_SPAN], vec![0.5, 0.5])
+            .mode(Mode::Lines)
+            .name("Nyquist (Fs/2)")
+            .line(
+                Line::new()
+                    .color("rgba(214, 39, 40, 0.6)")
+                    .dash(DashType::Dash),
+            ),
+    );
+
+    for (i, param) in params.iter().enumerate() {
+        let sampling_rate = param.sampling_rate as f64;
+        let apparent = aliased_frequency(param.signal_freq, sampling_rate);
+        let color = theme.signal_color(i, param);
+        plot.add_trace(
+            Scatter::new(
+                vec![param.signal_freq / sampling_rate],
+                vec![apparent / sampling_rate],
+            )
+            .mode(Mode::MarkersText)
+            .name(&param.name)
+            .text_array(vec![format!(
+                "{}<br>{}Hz → {:.1}Hz",
+                param.name, param.signal_freq, apparent
+            )])
+            .text_position(Position::TopCenter)
+            .text_font(theme.font(8))
+            .marker(Marker::new().size(10).color(color)),
+        );
     }
 
+    let layout = theme
+        .apply_background(Layout::new())
+        .show_legend(true)
+        .legend(theme.legend())
+        .x_axis(
+            theme
+                .axis()
+                .title(Title::with_text("True Frequency (f / Fs)").font(axis_font.clone()))
+                .tick_font(tick_font.clone())
+                .range(vec![0.0, FOLDING_SPAN])
+                .dtick(0.5),
+        )
+        .y_axis(
+            theme
+                .axis()
+                .title(Title::with_text("Apparent Frequency (f / Fs)").font(axis_font))
+                .tick_font(tick_font)
+                .range(vec![0.0, 0.6])
+                .dtick(0.1),
+        );
     plot.set_layout(layout);
-    plot.write_image(
-        "export/digital_audio_comparison.png",
-        ImageFormat::PNG,
-        1200,
-        800,
-        4.0,
-    );
+    plot
+}
+
+// ワゴンホイール効果の回転図。各信号を、サンプリング周波数をフレームレートとする車輪の回転数とみなす
+// 縦軸は符号つきの見かけの回転数で、負の側に入った信号は逆回転に見える
+fn create_rotation_plot(params: &[SignalParams], theme: &Theme) -> Plot {
+    let mut plot = Plot::new();
+    let axis_font = theme.font(10);
+    let tick_font = theme.font(9);
+
+    let (x, y): (Vec<f64>, Vec<f64>) = rotation_diagram(ROTATION_SPAN).into_iter().unzip();
+    plot.add_trace(
+        Scatter::new(x, y)
+            .mode(Mode::Lines)
+            .name("Apparent Rotation")
+            .line(Line::new().color("#888")),
+    );
+    plot.add_trace(
+        Scatter::new(vec![0.0, ROTATION_SPAN], vec![0.0, 0.0])
+            .mode(Mode::Lines)
+            .name("Standing Still")
+            .line(
+                Line::new()
+                    .color("rgba(214, 39, 40, 0.6)")
+                    .dash(DashType::Dash),
+            ),
+    );
+
+    for (i, param) in params.iter().enumerate() {
+        let frame_rate = param.sampling_rate as f64;
+        let apparent = apparent_rotation(param.signal_freq, frame_rate);
+        let direction = match apparent {
+            a if a > 0.0 => "forward",
+            a if a < 0.0 => "backward",
+            _ => "still",
+        };
+        plot.add_trace(
+            Scatter::new(
+                vec![param.signal_freq / frame_rate],
+                vec![apparent / frame_rate],
+            )
+            .mode(Mode::MarkersText)
+            .name(&param.name)
+            .text_array(vec![format!(
+                "{}<br>{} rev/s → {:.1} rev/s ({})",
+                param.name, param.signal_freq, apparent, direction
+            )])
+            .text_position(Position::TopCenter)
+            .text_font(theme.font(8))
+            .marker(Marker::new().size(10).color(theme.signal_color(i, param))),
+        );
+    }
+
+    let layout = theme
+        .apply_background(Layout::new())
+        .show_legend(true)
+        .legend(theme.legend())
+        .x_axis(
+            theme
+                .axis()
+                .title(
+                    Title::with_text("True Rotation (rev/s / Frame Rate)").font(axis_font.clone()),
+                )
+                .tick_font(tick_font.clone())
+                .range(vec![0.0, ROTATION_SPAN])
+                .dtick(0.5),
+        )
+        .y_axis(
+            theme
+                .axis()
+                .title(Title::with_text("Apparent Rotation (rev/s / Frame Rate)").font(axis_font))
+                .tick_font(tick_font)
+                .range(vec![-0.6, 0.6])
+                .dtick(0.1),
+        );
+    plot.set_layout(layout);
+    plot
+}
+
+// I/Q サンプルのコンスタレーション図。I と Q をそれぞれ同じ量子化器で丸めるので、取りうる点は
+// 各軸の量子化レベルの直積（2^bits × 2^bits の格子の交点）になる。QAM の復調と同じ見方で、
+// 量子化前の点（白抜き）から量子化後の点（塗り）への短い線が、最も近い交点への丸めを示す
+fn create_constellation_plot(params: &[SignalParams], theme: &Theme) -> Plot {
+    let mut plot = Plot::new();
+    let mut layout = theme
+        .apply_background(Layout::new())
+        .show_legend(theme.legend.show)
+        .legend(theme.legend());
+    let (rows, cols) = grid_dimensions(params.len());
+    let domains = panel_domains(rows, cols, PANEL_GAP);
+
+    for (i, param) in params.iter().enumerate() {
+        let range = amplitude_range(param);
+        let levels: Vec<f64> = param
+            .quantizer
+            .levels(param.bit_depth)
+            .iter()
+            .map(|level| level * param.full_scale)
+            .collect();
+        let show_grid = levels.len() <= CONSTELLATION_MAX_LEVELS;
+        if show_grid {
+            // 縦横の格子線を NaN で区切った1本のトレースにする
+            let (mut x, mut y) = (Vec::new(), Vec::new());
+            for &level in &levels {
+                x.extend([level, level, f64::NAN, range[0], range[1], f64::NAN]);
+                y.extend([range[0], range[1], f64::NAN, level, level, f64::NAN]);
+            }
+            plot.add_trace(
+                Scatter::new(x, y)
+                    .name("Quantization Grid")
+                    .legend_group("grid")
+                    .show_legend(i == 0)
+                    .mode(Mode::Lines)
+                    .line(Line::new().width(0.5).color("rgba(127, 127, 127, 0.4)"))
+                    .hover_info(HoverInfo::Skip)
+                    .on_panel(i),
+            );
+        }
+
+        let iq = create_iq_signal(param);
+        let (ideal_i, ideal_q): (Vec<f64>, Vec<f64>) = iq
+            .sample_x
+            .iter()
+            .map(|&t| {
+                let decay = param.amplitude * param.envelope(t);
+                let phase = 2.0 * PI * param.signal_freq * t + param.phase;
+                (decay * phase.cos(), decay * phase.sin())
+            })
+            .unzip();
+        let (mut snap_i, mut snap_q) = (Vec::new(), Vec::new());
+        for k in 0..iq.i.len() {
+            snap_i.extend([ideal_i[k], iq.i[k], f64::NAN]);
+            snap_q.extend([ideal_q[k], iq.q[k], f64::NAN]);
+        }
+        let color = theme.signal_color(i, param);
+        plot.add_trace(
+            Scatter::new(snap_i, snap_q)
+                .name("Rounding")
+                .legend_group("rounding")
+                .show_legend(i == 0)
+                .mode(Mode::Lines)
+                .line(Line::new().width(0.5).color(color.clone()))
+                .hover_info(HoverInfo::Skip)
+                .on_panel(i),
+        );
+        plot.add_trace(
+            Scatter::new(ideal_i, ideal_q)
+                .name("Unquantized I/Q")
+                .legend_group("ideal")
+                .show_legend(i == 0)
+                .mode(Mode::Markers)
+                .marker(
+                    Marker::new()
+                        .size(5)
+                        .symbol(MarkerSymbol::CircleOpen)
+                        .color(color.clone()),
+                )
+                .on_panel(i),
+        );
+        plot.add_trace(
+            Scatter::new(iq.i, iq.q)
+                .name("Quantized I/Q")
+                .legend_group("quantized")
+                .show_legend(i == 0)
+                .mode(Mode::Markers)
+                .marker(Marker::new().size(5).color(color))
+                .on_panel(i),
+        );
+
+        let grid_note = if show_grid {
+            format!("{0} × {0} levels", levels.len())
+        } else {
+            format!(
+                "{0} × {0} levels (grid drawn up to {1})",
+                levels.len(),
+                CONSTELLATION_MAX_LEVELS
+            )
+        };
+        let quantizer = param
+            .quantizer
+            .description()
+            .unwrap_or_else(|| format!("{}-bit linear", param.bit_depth));
+        layout.add_annotation(panel_title(
+            format!("{}<br>{}, {}", param.name, quantizer, grid_note),
+            i,
+            theme.font(8),
+            theme,
+        ));
+        // 格子のセルが正方形になるよう、縦軸の縮尺を横軸に合わせる
+        // 量子化の格子を描くときは、紛らわしいので軸の目盛りのグリッド線を消す
+        let (x_domain, y_domain) = domains[i];
+        layout = set_panel_axes(
+            layout,
+            i,
+            theme
+                .styled_axis(AxisKind::Time, "I", 7, 6)
+                .domain(&x_domain)
+                .anchor(format!("y{}", i + 1))
+                .range(range.clone())
+                .show_grid(!show_grid),
+            theme
+                .styled_axis(AxisKind::Time, "Q", 7, 6)
+                .domain(&y_domain)
+                .anchor(format!("x{}", i + 1))
+                .range(range)
+                .scale_anchor(format!("x{}", i + 1))
+                .show_grid(!show_grid),
+        );
+    }
+
+    plot.set_layout(layout);
+    plot
+}
+
+// 各信号の高調波ごとに、ナイキストを超えて折り返すかどうかの表を表示する
+fn print_harmonics(params: &[SignalParams], n_harmonics: usize) {
+    for param in params {
+        let sampling_rate = param.sampling_rate as f64;
+        println!(
+            "{} ({:?}, Fs = {}Hz)",
+            param.name, param.waveform, param.sampling_rate
+        );
+        println!(
+            "  {:>3}  {:>10}  {:>9}  {:>10}  aliases",
+            "k", "freq (Hz)", "amplitude", "heard at"
+        );
+        let highest = param.highest_synthesized_order();
+        for (k, amplitude) in fourier_coefficients(param.waveform, n_harmonics) {
+            if amplitude == 0.0 {
+                continue;
+            }
+            let frequency = k * param.signal_freq;
+            let aliases = frequency > sampling_rate / 2.0;
+            // 帯域制限した矩形波は synthesized_harmonics にない成分を合成しない
+            let omitted = highest.is_some_and(|highest| k > highest);
+            println!(
+                "  {:>3}  {:>10.1}  {:>9.4}  {:>10.1}  {}",
+                k,
+                frequency,
+                amplitude,
+                aliased_frequency(frequency, sampling_rate),
+                match (aliases, omitted) {
+                    (_, true) => "omitted",
+                    (true, false) => "yes",
+                    (false, _) => "no",
+                }
+            );
+        }
+    }
+}
+
+// 各信号の SQNR と THD を表示する。trials > 1 なら位相をランダムにした平均と標準偏差
+fn print_analysis(params: &[SignalParams], options: &Options) {
+    let (trials, seed) = (options.trials, options.seed);
+    let mut modes = COMPARED_RECONSTRUCTIONS.to_vec();
+    for mode in &options.reconstructions {
+        if !modes.contains(mode) {
+            modes.push(*mode);
+        }
+    }
+    for param in params {
+        if trials > 1 {
+            let summary = monte_carlo(param, trials, seed);
+            println!(
+                "{}: SQNR {:.2} ± {:.2} dB, THD {:.3} ± {:.3} % ({} trials)",
+                param.name,
+                summary.sqnr_db.mean,
+                summary.sqnr_db.std_dev,
+                summary.thd_percent.mean,
+                summary.thd_percent.std_dev,
+                summary.trials
+            );
+        } else {
+            let metrics = analyze(param, &create_sine_wave(param));
+            println!(
+                "{}: SQNR {:.2} dB (ENOB {:.2} bits), THD {:.3} %, RMS {:.3} ({}), crest factor {:.2}",
+                param.name,
+                metrics.sqnr_db,
+                metrics.enob,
+                metrics.thd_percent,
+                metrics.rms,
+                match param.rms_window {
+                    Some(window) => format!("loudest {} s window", window),
+                    None => "whole signal".to_string(),
+                },
+                metrics.crest_factor
+            );
+        }
+        // 再構成の SNR は位相によらずほぼ決まるので、試行回数によらず1回分を示す
+        let snrs = reconstruction_snrs(param, &create_sine_wave(param), &modes);
+        let report: Vec<String> = snrs
+            .iter()
+            .map(|(mode, snr)| format!("{} {:.1} dB", mode.label(), snr))
+            .collect();
+        println!("  reconstruction SNR: {}", report.join(", "));
+    }
+}
+
+// 実験レポートに貼り付けられる Markdown の表を書き出す
+fn write_markdown_report(path: &Path, params: &[SignalParams]) {
+    let metrics: Vec<SignalMetrics> = params
+        .iter()
+        .map(|param| analyze(param, &create_sine_wave(param)))
+        .collect();
+    match std::fs::write(path, markdown_report(params, &metrics)) {
+        Ok(()) => println!("{}: written", path.display()),
+        Err(error) => {
+            eprintln!("error: {}: {}", path.display(), error);
+            std::process::exit(1);
+        }
+    }
+}
+
+// --metrics-json の1要素。信号名と指標を同じ階層に並べる
+#[derive(Serialize)]
+struct NamedMetrics<'a> {
+    name: &'a str,
+    #[serde(flatten)]
+    metrics: SignalMetrics,
+}
+
+fn write_metrics_json(path: &Path, params: &[SignalParams]) {
+    let entries: Vec<NamedMetrics> = params
+        .iter()
+        .map(|param| NamedMetrics {
+            name: &param.name,
+            metrics: analyze(param, &create_sine_wave(param)),
+        })
+        .collect();
+    let json = serde_json::to_string_pretty(&entries).expect("metrics serialize to JSON");
+    match std::fs::write(path, json) {
+        Ok(()) => println!("{}: written", path.display()),
+        Err(error) => {
+            eprintln!("error: {}: {}", path.display(), error);
+            std::process::exit(1);
+        }
+    }
+}
+
+// HTML 出力での操作（モードバー・スクロールズーム・軸の固定）
+fn html_configuration(options: &Options) -> Configuration {
+    let mut configuration = Configuration::new()
+        .scroll_zoom(options.scroll_zoom)
+        .static_plot(options.lock_axes)
+        .display_logo(false);
+    if !options.mode_bar {
+        configuration = configuration.display_mode_bar(DisplayModeBar::False);
+    }
+    if !options.download_button {
+        configuration = configuration.mode_bar_buttons_to_remove(vec![ModeBarButtonName::ToImage]);
+    }
+    configuration
+}
+
+fn create_out_dir(options: &Options) {
+    if let Err(error) = std::fs::create_dir_all(&options.out_dir) {
+        eprintln!(
+            "error: cannot create {}: {}",
+            options.out_dir.display(),
+            error
+        );
+        std::process::exit(1);
+    }
+}
+
+// 画像の書き出しでは、指定のフォントが無いと kaleido が黙って置き換えるので先に知らせる
+// 置き換え先は theme::FALLBACK_FONT_FAMILIES の順（どれも無ければブラウザ既定の等幅フォント）
+fn warn_missing_fonts(theme: &Theme) {
+    let mut families: Vec<&str> = [theme.font_family.as_str()]
+        .into_iter()
+        .chain(theme.time_axis_font.family.as_deref())
+        .chain(theme.spectrum_axis_font.family.as_deref())
+        .map(|family| family.split(',').next().unwrap_or(family).trim())
+        .collect();
+    families.dedup();
+    for family in families {
+        match font_installed(family) {
+            Some(true) => {}
+            Some(false) => eprintln!(
+                "warning: font {:?} is not installed; images use the first available of {}",
+                family, FALLBACK_FONT_FAMILIES
+            ),
+            None => eprintln!(
+                "warning: cannot confirm that font {:?} is installed (fc-list unavailable); \
+                 images may fall back to another font",
+                family
+            ),
+        }
+    }
+}
+
+// <out_dir>/<name>.<拡張子> に書き出す
+// --show では一時ディレクトリに HTML を書いて既定のブラウザで開く（plotly の show と同じ流れ）
+// ファイル名は図ごとに決まっているので、作り直したあとはブラウザで再読み込みすればよい
+// ブラウザを開けない環境でも止まらず、書いたファイルの場所を知らせる
+fn show_in_browser(plot: &Plot, name: &str) {
+    let path = std::env::temp_dir().join(format!("graph-builder_{}.html", name));
+    plot.write_html(&path);
+    let opener = if cfg!(target_os = "macos") {
+        std::process::Command::new("open").arg(&path).status()
+    } else if cfg!(target_os = "windows") {
+        std::process::Command::new("cmd")
+            .args(["/C", "start", ""])
+            .arg(&path)
+            .status()
+    } else {
+        std::process::Command::new("xdg-open").arg(&path).status()
+    };
+    match opener {
+        Ok(status) if status.success() => println!("{}: opened in browser", path.display()),
+        Ok(_) | Err(_) => eprintln!(
+            "warning: cannot open a browser; open {} manually",
+            path.display()
+        ),
+    }
+}
+
+fn write_plot(mut plot: Plot, name: &str, options: &Options) {
+    if options.format != OutputFormat::Png {
+        plot.set_configuration(html_configuration(options));
+    }
+    if options.format == OutputFormat::Browser {
+        show_in_browser(&plot, name);
+        return;
+    }
+    create_out_dir(options);
+    let path = options
+        .out_dir
+        .join(format!("{}.{}", name, options.format.extension()));
+    let _timer = Timer::start(Stage::Write);
+    if options.format == OutputFormat::Png {
+        let size = options.export_size();
+        plot.write_image(path, ImageFormat::PNG, size.width, size.height, size.scale);
+    } else {
+        plot.write_html(path);
+    }
+}
+
+// 信号名をファイル名に使える形にする（"Severe Aliasing" → "severe_aliasing"）
+fn file_stem(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_lowercase()
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+// 各信号の量子化済みサンプルを <out_dir>/<信号名>.wav に書き出す
+// 正規化した場合は掛けたゲインを表示する
+fn write_wavs(params: &[SignalParams], options: &Options) {
+    create_out_dir(options);
+    for param in params {
+        // サンプルは ADC の単位（±full_scale）なので、WAV の ±1 がコンバータのフルスケールになるよう割る
+        let mut samples: Vec<f64> = create_sine_wave(param)
+            .sample_y
+            .iter()
+            .map(|y| y / param.full_scale)
+            .collect();
+        let gain = if options.normalize {
+            normalize_peak(&mut samples, NORMALIZE_PEAK_DBFS)
+        } else {
+            None
+        };
+        let path = options
+            .out_dir
+            .join(format!("{}.wav", file_stem(&param.name)));
+        let result = File::create(&path)
+            .and_then(|file| write_wav(&samples, param.sampling_rate as u32, BufWriter::new(file)));
+        match (result, gain) {
+            (Err(error), _) => {
+                eprintln!("error: {}: {}", path.display(), error);
+                std::process::exit(1);
+            }
+            (Ok(()), Some(gain)) => println!(
+                "{}: normalized to {} dBFS (gain {:+.2} dB)",
+                path.display(),
+                NORMALIZE_PEAK_DBFS,
+                20.0 * gain.log10()
+            ),
+            (Ok(()), None) => println!("{}: written without normalization", path.display()),
+        }
+    }
+}
+
+// 比較図（--layout に従う）
+fn create_figure(params: &[SignalParams], theme: &Theme, options: &Options) -> Plot {
+    match options.layout {
+        FigureLayout::Grid => create_comparison_plot(params, theme, options),
+        FigureLayout::SideBySide => create_side_by_side_plot(params, theme, options),
+    }
+}
+
+// 長い信号を tile 秒ずつの区間に分け、区間ごとに生成して1枚ずつ書き出す
+// 一度に確保するのは1区間分だけなので、数分の 96kHz 信号でも描ける
+fn write_tiles(params: &[SignalParams], theme: &Theme, options: &Options, tile: f64) {
+    for param in params {
+        let per_tile = ((tile * param.sampling_rate as f64).round() as usize).max(1);
+        let count = param.num_samples().div_ceil(per_tile);
+        let stem = file_stem(&param.name);
+        for index in 0..count {
+            let first = index * per_tile;
+            let length = per_tile.min(param.num_samples() - first);
+            let data = create_signal_window(param, first, length);
+            let plot = tile_plot(param, data, index, count, theme);
+            write_plot(plot, &format!("tile_{}_{:04}", stem, index), options);
+        }
+        println!(
+            "{}: {} tiles of {} s (tile_{}_%04d.{})",
+            param.name,
+            count,
+            tile,
+            stem,
+            options.format.extension()
+        );
+    }
+}
+
+// 再生位置の縦線を動かしながら比較図を1コマずつ書き出す
+// コマ k は t = k / fps の位置なので、同じ fps で動画にすれば WAV と同期する
+fn write_playhead_frames(params: &[SignalParams], theme: &Theme, options: &Options, fps: f64) {
+    let plot = create_figure(params, theme, options);
+    let time_axis = |i: usize| match options.layout {
+        FigureLayout::Grid => i,
+        FigureLayout::SideBySide => 2 * i,
+    };
+    let duration = params
+        .iter()
+        .map(|param| param.duration)
+        .fold(0.0, f64::max);
+    let frames = (duration * fps).floor() as usize + 1;
+
+    for frame in 0..frames {
+        let t = frame as f64 / fps;
+        let mut layout = plot.layout().clone();
+        // 表示範囲の短い信号では、範囲を過ぎたら縦線を消す
+        for (i, param) in params.iter().enumerate() {
+            if t <= param.duration {
+                let x = options.time_axis.position(t, param.sampling_rate as f64);
+                layout.add_shape(playhead(x, time_axis(i)));
+            }
+        }
+        let mut frame_plot = plot.clone();
+        frame_plot.set_layout(layout);
+        write_plot(frame_plot, &format!("playhead_{:04}", frame), options);
+    }
+    println!(
+        "{}: {} frames at {} fps (playhead_%04d.{})",
+        options.out_dir.display(),
+        frames,
+        fps,
+        options.format.extension()
+    );
+}
+
+// 周波数の掃引アニメーション。全信号の周波数をナイキスト周波数の ratio 倍にそろえたコマを
+// あらかじめ計算して HTML に埋め込むので、ブラウザは再生するだけで済み、スライダーで任意のコマに戻れる
+fn write_sweep_html(params: &[SignalParams], theme: &Theme, options: &Options, frames: usize) {
+    let (first, last) = SWEEP_NYQUIST_RATIOS;
+    // コマの図は HTML 用に作る（--decimate が効く）
+    let frame_options = Options {
+        format: OutputFormat::Html,
+        ..options.clone()
+    };
+    let frames: Vec<Frame> = (0..frames)
+        .map(|k| {
+            let ratio = first + (last - first) * k as f64 / (frames - 1) as f64;
+            let swept: Vec<SignalParams> = params
+                .iter()
+                .map(|param| {
+                    param
+                        .clone()
+                        .with_signal_freq(ratio * param.sampling_rate as f64 / 2.0)
+                })
+                .collect();
+            let mut plot = create_figure(&swept, theme, &frame_options);
+            plot.set_configuration(html_configuration(options));
+            Frame {
+                name: format!("f = {:.2} × Nyquist", ratio),
+                plot,
+            }
+        })
+        .collect();
+
+    create_out_dir(options);
+    let path = options.out_dir.join("frequency_sweep.html");
+    let _timer = Timer::start(Stage::Write);
+    let html = animated_html(&animated_figure(&frames, SWEEP_FRAME_MS));
+    match std::fs::write(&path, html) {
+        Ok(()) => println!("{}: {} frames", path.display(), frames.len()),
+        Err(error) => {
+            eprintln!("error: {}: {}", path.display(), error);
+            std::process::exit(1);
+        }
+    }
+}
+
+// 図を JPEG に描画してバイト列で返す（kaleido はファイルにしか書き出せないので一時ファイルを経由する）
+fn render_jpeg(plot: &Plot, index: usize, options: &Options) -> std::io::Result<JpegImage> {
+    let path = std::env::temp_dir().join(format!(
+        "graph-builder-page-{}-{}.jpeg",
+        std::process::id(),
+        index
+    ));
+    let size = options.export_size();
+    plot.write_image(
+        &path,
+        ImageFormat::JPEG,
+        size.width,
+        size.height,
+        size.scale,
+    );
+    let data = std::fs::read(&path)?;
+    let _ = std::fs::remove_file(&path);
+    JpegImage::from_bytes(data).ok_or_else(|| {
+        std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            "kaleido did not produce a JPEG",
+        )
+    })
+}
+
+// パラメータセットごとに比較図を1ページにした PDF を書き出す
+fn write_pdf_report(path: &Path, sets: &[Vec<SignalParams>], theme: &Theme, options: &Options) {
+    let result = sets
+        .iter()
+        .enumerate()
+        .map(|(i, params)| render_jpeg(&create_figure(params, theme, options), i, options))
+        .collect::<std::io::Result<Vec<_>>>()
+        .and_then(|pages| write_pdf(&pages, BufWriter::new(File::create(path)?)));
+    match result {
+        Ok(()) => println!("{}: {} pages", path.display(), sets.len()),
+        Err(error) => {
+            eprintln!("error: {}: {}", path.display(), error);
+            std::process::exit(1);
+        }
+    }
+}
+
+// 2x2 グリッドで各信号の波形を比較する
+fn create_comparison_plot(params: &[SignalParams], theme: &Theme, options: &Options) -> Plot {
+    let mut plot = Plot::new();
+
+    // サブプロットの作成。タイトルに載せる指標もここで計算しておく
+    let mut metrics = Vec::new();
+    let mut callout_targets = Vec::new();
+    for (i, param) in params.iter().enumerate() {
+        let data = create_sine_wave(param);
+        metrics.push(analyze(param, &data));
+        callout_targets.push(
+            options
+                .callouts
+                .then(|| callout_target(param, &data, options))
+                .flatten(),
+        );
+        let color = theme.signal_color(i, param);
+        add_time_traces(&mut plot, data, param, i, &color, options, theme);
+    }
+    let nyquist_notes: Vec<Annotation> = params
+        .iter()
+        .enumerate()
+        .filter_map(|(i, param)| nyquist_note(param, i, theme.font(7), theme))
+        .collect();
+
+    // レイアウト設定
+    let scale = options.amplitude_scale;
+
+    let subplot_title_font = theme.font(8);
+    let (rows, cols) = grid_dimensions(params.len());
+    let grid = options.grid.unwrap_or_default();
+    let mut layout = theme
+        .apply_background(Layout::new())
+        .margin(
+            Margin::new()
+                .left(0)
+                .right(0)
+                .top(0)
+                .bottom(0)
+                .pad(0)
+                .auto_expand(true),
+        )
+        .grid(
+            LayoutGrid::new()
+                .rows(rows)
+                .columns(cols)
+                .pattern(grid.pattern())
+                .sub_plots(vec!["subplot".to_string()]),
+        )
+        .show_legend(theme.legend.show)
+        .legend(theme.legend());
+
+    // --aspect では書き出すサイズをもとにパネルの縦横比をそろえる。HTML でも同じ比になるよう図の大きさを固定する
+    let mut domains = panel_domains(rows, cols, PANEL_GAP);
+    if let Some(aspect) = options.aspect {
+        let size = options.export_size();
+        domains = fit_aspect(domains, aspect, size.width, size.height);
+        layout = layout.width(size.width).height(size.height);
+    }
+
+    // サブプロットの軸とタイトル
+    let mut annotations = Vec::new();
+    for (i, (((param, metrics), (x_domain, y_domain)), target)) in params
+        .iter()
+        .zip(&metrics)
+        .zip(domains)
+        .zip(callout_targets)
+        .enumerate()
+    {
+        let mut x_axis = theme
+            .styled_axis(AxisKind::Time, options.time_axis.title(), 7, 6)
+            .domain(&x_domain)
+            .anchor(format!("y{}", i + 1)); // .range(vec![0.0, 1.0]) // 0-1秒に固定
+
+        // 目盛りを周期 1/f の等分点に置き、1周期に何サンプル入るかを数えやすくする
+        if let Some(ticks) = options.ticks_per_period {
+            let period = options
+                .time_axis
+                .position(param.period(), param.sampling_rate as f64);
+            x_axis = x_axis.tick0(0.0).dtick(period / ticks as f64);
+        }
+        let mut y_axis = theme
+            .amplitude_axis(theme.styled_axis(AxisKind::Time, scale.axis_title(), 7, 6))
+            .domain(&y_domain)
+            .anchor(format!("x{}", i + 1))
+            .type_(scale.axis_type())
+            .range(scale.range(param));
+        // Coupled では列・行の先頭パネルの軸に範囲を合わせる（ドメインと anchor はパネルごとのまま）
+        let (x_link, y_link) = grid.linked_axes(i, cols);
+        if let Some(x_link) = x_link {
+            x_axis = x_axis.matches(&x_link);
+        }
+        if let Some(y_link) = y_link {
+            y_axis = y_axis.matches(&y_link);
+        }
+        layout = set_panel_axes(layout, i, x_axis, y_axis);
+        if options.severity_borders {
+            layout.add_shape(severity_border(param, i, theme));
+        }
+        if options.period {
+            annotations.extend(period_annotations(
+                param,
+                i,
+                theme.font(7),
+                options.time_axis,
+                theme,
+            ));
+        }
+        // --metrics-annotation ではタイトルを短くし、指標は右下の注記に分ける
+        let title = if options.metrics_annotation {
+            annotations.push(metrics_annotation(
+                metrics,
+                i,
+                subplot_title_font.clone(),
+                theme,
+            ));
+            short_title(param, metrics)
+        } else {
+            format!(
+                "{}<br>{}",
+                generate_title(param, metrics),
+                format_metric("SQNR", metrics.sqnr_db, " dB")
+            )
+        };
+        annotations.push(match target {
+            Some(target) => panel_callout(title, i, subplot_title_font.clone(), target, theme),
+            None => panel_title(title, i, subplot_title_font.clone(), theme),
+        });
+    }
+
+    for annotation in annotations.into_iter().chain(nyquist_notes) {
+        layout.add_annotation(annotation);
+    }
+
+    // layout guides
+    for annotation in create_layout_guides(theme) {
+        layout.add_annotation(annotation);
+    }
+
+    plot.set_layout(layout);
+    plot
+}
+
+// --callouts でタイトルから指す点。エイリアシングする信号だけで、連続信号と直線補間の再構成の差が
+// 最も大きい時刻の連続信号の値（サンプルが取り逃した山や谷）。座標は --x-axis と振幅の表示に合わせる
+fn callout_target(
+    param: &SignalParams,
+    data: &SignalData,
+    options: &Options,
+) -> Option<(f64, f64)> {
+    if param.signal_freq <= param.sampling_rate as f64 / 2.0 {
+        return None;
+    }
+    let reconstructed = ReconstructionMode::Linear.reconstruct(
+        &data.sample_x,
+        &data.sample_y,
+        param.sampling_rate as f64,
+        &data.continuous_x,
+    );
+    let (t, y) = data
+        .continuous_x
+        .iter()
+        .zip(&data.continuous_y)
+        .zip(&reconstructed)
+        .max_by(|((_, a), ra), ((_, b), rb)| (*a - *ra).abs().total_cmp(&(*b - *rb).abs()))
+        .map(|((&t, &y), _)| (t, y))?;
+    let x = options.time_axis.position(t, param.sampling_rate as f64);
+    Some((x, options.amplitude_scale.apply(&[y], param.full_scale)[0]))
+}
+
+// 信号ごとに1行を使い、左に波形・右にスペクトルを並べる
+fn create_side_by_side_plot(params: &[SignalParams], theme: &Theme, options: &Options) -> Plot {
+    let mut plot = Plot::new();
+    let data: Vec<SignalData> = params.iter().map(create_sine_wave).collect();
+    let metrics: Vec<SignalMetrics> = params
+        .iter()
+        .zip(&data)
+        .map(|(param, data)| analyze(param, data))
+        .collect();
+    let mut layout = side_by_side_layout(
+        params,
+        &metrics,
+        theme,
+        options.amplitude_scale,
+        options.severity_borders,
+        options.a_weight,
+        options.time_axis,
+    );
+
+    for (row, (param, data)) in params.iter().zip(data).enumerate() {
+        let peaks = add_spectrum_traces(
+            &mut plot,
+            &data,
+            param,
+            2 * row + 1,
+            row == 0,
+            theme.font(7),
+            options,
+        );
+        let color = theme.signal_color(row, param);
+        add_time_traces(&mut plot, data, param, 2 * row, &color, options, theme);
+        if options.period {
+            for annotation in
+                period_annotations(param, 2 * row, theme.font(7), options.time_axis, theme)
+            {
+                layout.add_annotation(annotation);
+            }
+        }
+        for annotation in peaks {
+            layout.add_annotation(annotation);
+        }
+    }
+
+    plot.set_layout(layout);
+    plot
+}
+
+// 設定ファイルがあればそちらを使う。プリセット名は parse_args で検証済み
+// 設定ファイルの [axes] はテーマの軸フォントを上書きし、grid は --grid が無いときに使う
+fn load_params(
+    options: &Options,
+    theme: &Theme,
+) -> Result<(Vec<SignalParams>, Theme, Options), ConfigError> {
+    let mut options = options.clone();
+    let (params, theme) = match &options.config {
+        Some(path) => {
+            let config = read_config(path)?;
+            options.grid = options.grid.or(config.grid_mode()?);
+            options.sample_line = options.sample_line.or(config.sample_line()?);
+            if options.outputs.is_empty() {
+                options.outputs = config.outputs()?.unwrap_or_default();
+            }
+            (
+                config.validate_seeded(options.seed)?,
+                config.apply_axis_fonts(theme.clone())?,
+            )
+        }
+        None => (
+            preset_by_name(&options.preset)
+                .expect("preset name is validated by parse_args")
+                .into_iter()
+                .enumerate()
+                .map(|(i, param)| param.with_seed(options.seed.wrapping_add(i as u64)))
+                .collect(),
+            theme.clone(),
+        ),
+    };
+
+    // --freq, --sr, --coherent, --jitter, --input-noise, --dc-offset, --rms-window は全信号に掛ける（乱数のシードは信号ごと）
+    // --coherent は --freq, --sr で変えた後の周波数とサンプル数に合わせる
+    let params = params
+        .into_iter()
+        .map(|param| match options.signal_freq {
+            Some(frequency) => param.with_signal_freq(frequency),
+            None => param,
+        })
+        .map(|param| match options.sample_rate {
+            Some(rate) => param.with_sampling_rate(rate),
+            None => param,
+        })
+        .map(|param| {
+            if !options.coherent {
+                return param;
+            }
+            let freq = coherent_frequency(
+                param.signal_freq,
+                param.num_samples(),
+                param.sampling_rate as f64,
+            );
+            if freq != param.signal_freq {
+                println!(
+                    "{}: {}Hz -> {}Hz (coherent)",
+                    param.name, param.signal_freq, freq
+                );
+            }
+            param.with_signal_freq(freq)
+        })
+        .map(|param| match options.jitter {
+            Some(jitter) => param.with_jitter(jitter),
+            None => param,
+        })
+        .map(|param| match options.input_noise {
+            Some(noise) => param.with_input_noise(noise),
+            None => param,
+        })
+        .map(|param| match options.dc_offset {
+            Some(offset) => param.with_dc_offset(offset),
+            None => param,
+        })
+        .map(|param| match options.rms_window {
+            Some(window) => param.with_rms_window(window),
+            None => param,
+        })
+        .collect::<Vec<_>>();
+
+    // 基準音は --sr などの変更を受けない
+    let params = if options.reference {
+        if params.len() >= MAX_SIGNALS {
+            return Err(ConfigError::Invalid {
+                field: "signals".to_string(),
+                message: format!(
+                    "--reference needs a free panel (at most {} signals)",
+                    MAX_SIGNALS - 1
+                ),
+            });
+        }
+        params.into_iter().chain([reference_tone()]).collect()
+    } else {
+        params
+    };
+    Ok((params, theme, options))
+}
+
+// 設定ファイルの保存を待って描き直す。保存直後に続けて届くイベントは1回にまとめる
+// 読み込みに失敗しても終了せず、次の保存を待つ
+fn watch_config(path: &Path, theme: &Theme, options: &Options) {
+    let (sender, receiver) = mpsc::channel();
+    let watch = notify::recommended_watcher(sender).and_then(|mut watcher| {
+        // エディタによっては保存時にファイルを置き換えるので、親ディレクトリごと監視する
+        let directory = match path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => Path::new("."),
+        };
+        watcher.watch(directory, RecursiveMode::NonRecursive)?;
+        Ok(watcher)
+    });
+    let _watcher = watch.unwrap_or_else(|error| {
+        eprintln!("error: cannot watch {}: {}", path.display(), error);
+        std::process::exit(1);
+    });
+
+    let touches_config = |event: &notify::Result<Event>| match event {
+        Ok(event) => {
+            !event.kind.is_access()
+                && event
+                    .paths
+                    .iter()
+                    .any(|changed| changed.file_name() == path.file_name())
+        }
+        Err(_) => false,
+    };
+    println!("watching {} (Ctrl-C to stop)", path.display());
+    while let Ok(event) = receiver.recv() {
+        if !touches_config(&event) {
+            continue;
+        }
+        while receiver.recv_timeout(WATCH_DEBOUNCE).is_ok() {}
+        match load_params(options, theme) {
+            Ok((params, theme, options)) => {
+                run(&params, &theme, &options);
+                println!("{}: re-rendered", path.display());
+            }
+            Err(error) => eprintln!("error: {}: {}", path.display(), error),
+        }
+    }
+}
+
+// ディレクトリ内の .toml を1つずつ --config として読み、<out_dir>/<ファイル名>/ に書き出す
+// 設定ごとに独立しているので rayon で並列に描く。読み込めない設定は知らせて飛ばし、失敗した数を返す
+// --profile では段階ごとの集計が全スレッドの合計になって全体の時間を超えるので、1つずつ描く
+fn render_config_dir(dir: &Path, theme: &Theme, options: &Options) -> usize {
+    let entries = std::fs::read_dir(dir).unwrap_or_else(|error| {
+        eprintln!("error: {}: {}", dir.display(), error);
+        std::process::exit(1);
+    });
+    let mut paths: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.is_file() && path.extension().is_some_and(|ext| ext == "toml"))
+        .collect();
+    paths.sort();
+    if paths.is_empty() {
+        eprintln!("error: no .toml files in {}", dir.display());
+        std::process::exit(1);
+    }
+
+    let render = |path: &PathBuf| {
+        let stem = path.file_stem().unwrap_or_default();
+        let mut config_options = options.clone();
+        config_options.config = Some(path.to_path_buf());
+        config_options.out_dir = options.out_dir.join(stem);
+        match load_params(&config_options, theme) {
+            Ok((params, theme, config_options)) => {
+                run(&params, &theme, &config_options);
+                println!(
+                    "{}: rendered to {}",
+                    path.display(),
+                    config_options.out_dir.display()
+                );
+                false
+            }
+            Err(error) => {
+                eprintln!("error: {}: {}", path.display(), error);
+                true
+            }
+        }
+    };
+    if options.profile {
+        paths.iter().filter(|path| render(path)).count()
+    } else {
+        paths.par_iter().filter(|path| render(path)).count()
+    }
+}
+
+// 読み込んだ信号について、オプションで選んだ出力を1回分行う
+fn run(params: &[SignalParams], theme: &Theme, options: &Options) {
+    // 画像（PNG・PDF）を書き出すときだけフォントを確かめる
+    let text_only = options.harmonics.is_some()
+        || options.analyze
+        || options.metrics_json.is_some()
+        || options.report_md.is_some()
+        || options.dump_json.is_some()
+        || options.sweep_html.is_some()
+        || !options.outputs().iter().any(|output| output.is_figure());
+    if options.pdf_report.is_some() || (options.format == OutputFormat::Png && !text_only) {
+        warn_missing_fonts(theme);
+    }
+    // ナイキスト周波数ちょうどの信号は位相次第で全サンプルがゼロになるので知らせておく
+    for param in params.iter().filter(|param| param.is_at_nyquist()) {
+        eprintln!(
+            "warning: {} is sampled at exactly twice its frequency; \
+             the captured amplitude depends on the starting phase",
+            param.name
+        );
+    }
+
+    if options.layout == FigureLayout::SideBySide && params.len() > 4 {
+        eprintln!(
+            "error: --layout side-by-side supports up to 4 signals (got {})",
+            params.len()
+        );
+        std::process::exit(2);
+    }
+
+    if let Some(n_harmonics) = options.harmonics {
+        print_harmonics(params, n_harmonics);
+        return;
+    }
+
+    if options.response {
+        write_plot(
+            create_response_plot(&options.reconstructions, theme),
+            "reconstruction_response",
+            options,
+        );
+        return;
+    }
+
+    if options.oversampling {
+        write_plot(
+            create_oversampling_plot(params, &options.reconstructions, theme),
+            "reconstruction_oversampling",
+            options,
+        );
+        return;
+    }
+
+    if let Some((a, b)) = &options.ab {
+        if params.len() > 4 {
+            eprintln!(
+                "error: --ab supports up to 4 signals (got {})",
+                params.len()
+            );
+            std::process::exit(2);
+        }
+        write_plot(create_ab_plot(params, a, b, theme), "quantizer_ab", options);
+        return;
+    }
+
+    if let Some((a, b)) = options.bit_diff {
+        if params.len() > 4 {
+            eprintln!(
+                "error: --bit-diff supports up to 4 signals (got {})",
+                params.len()
+            );
+            std::process::exit(2);
+        }
+        write_plot(
+            create_bit_depth_diff_plot(params, a, b, theme),
+            "bit_depth_difference",
+            options,
+        );
+        return;
+    }
+
+    if options.replicas {
+        write_plot(
+            create_replicas_plot(params, theme),
+            "spectrum_replicas",
+            options,
+        );
+        return;
+    }
+
+    if options.eye {
+        write_plot(
+            create_eye_plot(params, theme, options),
+            "eye_diagram",
+            options,
+        );
+        return;
+    }
+
+    if options.folding {
+        write_plot(
+            create_folding_plot(params, theme),
+            "folding_diagram",
+            options,
+        );
+        return;
+    }
+
+    if options.wagon_wheel {
+        write_plot(create_rotation_plot(params, theme), "wagon_wheel", options);
+        return;
+    }
+
+    if options.sqnr_sweep {
+        write_plot(create_sqnr_sweep_plot(params, theme), "sqnr_sweep", options);
+        return;
+    }
+
+    if options.lloyd_max {
+        write_plot(create_lloyd_max_plot(params, theme), "lloyd_max", options);
+        return;
+    }
+
+    if options.constellation {
+        write_plot(
+            create_constellation_plot(params, theme),
+            "iq_constellation",
+            options,
+        );
+        return;
+    }
+
+    if options.analyze {
+        print_analysis(params, options);
+        return;
+    }
+
+    // 設定ファイルがなければ全プリセットを1ページずつ並べる
+    if let Some(path) = &options.pdf_report {
+        let sets: Vec<Vec<SignalParams>> = match options.config {
+            Some(_) => vec![params.to_vec()],
+            None => PRESETS
+                .iter()
+                .filter_map(|(name, _)| preset_by_name(name))
+                .collect(),
+        };
+        write_pdf_report(path, &sets, theme, options);
+        return;
+    }
+
+    if let Some(path) = &options.metrics_json {
+        write_metrics_json(path, params);
+        return;
+    }
+
+    if let Some(path) = &options.report_md {
+        write_markdown_report(path, params);
+        return;
+    }
+
+    // 図の定義（data / layout）だけを書き出し、描画は plotly.js や plotly.py に任せる
+    if let Some(path) = &options.dump_json {
+        let plot = create_figure(params, theme, options);
+        match std::fs::write(path, plot.to_json()) {
+            Ok(()) => println!("{}: written", path.display()),
+            Err(error) => {
+                eprintln!("error: {}: {}", path.display(), error);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    if options.wav {
+        write_wavs(params, options);
+    }
+
+    if let Some(fps) = options.playhead {
+        write_playhead_frames(params, theme, options, fps);
+        return;
+    }
+
+    if let Some(frames) = options.sweep_html {
+        write_sweep_html(params, theme, options, frames);
+        return;
+    }
+
+    if let Some(tile) = options.tile {
+        write_tiles(params, theme, options, tile);
+        return;
+    }
+
+    // 信号ごとに1枚、グリッドを使わない単独の図を書き出す
+    if options.staircase {
+        for param in params {
+            let name = format!("staircase_{}", file_stem(&param.name));
+            write_plot(staircase_plot(param, theme), &name, options);
+        }
+        return;
+    }
+
+    if options.single {
+        for param in params {
+            let name = format!("single_{}", file_stem(&param.name));
+            write_plot(single_plot(param, theme), &name, options);
+        }
+        return;
+    }
+
+    // --output（設定ファイルの outputs）で選んだものを順に書き出す
+    for output in options.outputs() {
+        match output {
+            Output::Comparison => {
+                let plot = create_figure(params, theme, options);
+                write_plot(plot, "digital_audio_comparison", options);
+            }
+            // 各信号のスペクトル（ピーク検出付き）
+            Output::Spectrum => {
+                let spectrum_plot = create_spectrum_plot(params, theme, options);
+                write_plot(spectrum_plot, "digital_audio_spectrum", options);
+            }
+            // 同じパラメータを複素（I/Q）でサンプリングした場合のスペクトル
+            Output::Iq => {
+                let iq_plot = create_iq_spectrum_plot(params, theme);
+                write_plot(iq_plot, "iq_spectrum", options);
+            }
+            Output::Metrics => {
+                create_out_dir(options);
+                write_metrics_json(&options.out_dir.join("metrics.json"), params);
+            }
+            Output::Report => {
+                create_out_dir(options);
+                write_markdown_report(&options.out_dir.join("report.md"), params);
+            }
+        }
+    }
+}
+
+fn main() {
+    let started = Instant::now();
+    let options = match parse_args(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("error: {}\n\n{}", message, USAGE);
+            std::process::exit(2);
+        }
+    };
+    if options.help {
+        println!("{}", USAGE);
+        return;
+    }
+    if options.list_presets {
+        for (name, description) in PRESETS {
+            println!("{:<12}{}", name, description);
+        }
+        return;
+    }
+
+    let theme = match &options.theme_file {
+        Some(path) => load_theme(path).unwrap_or_else(|error| {
+            eprintln!("error: {}: {}", path.display(), error);
+            std::process::exit(1);
+        }),
+        None => Theme::default(),
+    };
+    let theme = if options.transparent {
+        theme.with_transparent_background()
+    } else {
+        theme
+    };
+
+    if let Some(path) = &options.validate_config {
+        match load_config(path) {
+            Ok(params) => println!("{}: ok ({} signals)", path.display(), params.len()),
+            Err(error) => {
+                eprintln!("error: {}: {}", path.display(), error);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    if let Some(dir) = &options.config_dir {
+        let failures = render_config_dir(dir, &theme, &options);
+        if options.profile {
+            println!("{}", profile::report(started.elapsed()));
+        }
+        if failures > 0 {
+            eprintln!(
+                "error: {} config file(s) in {} failed to load",
+                failures,
+                dir.display()
+            );
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    let (params, config_theme, config_options) =
+        load_params(&options, &theme).unwrap_or_else(|error| {
+            let path = options
+                .config
+                .as_deref()
+                .expect("only config files fail to load");
+            eprintln!("error: {}: {}", path.display(), error);
+            std::process::exit(1);
+        });
+    // run のうち生成・FFT・書き出しを除いた時間を図の組み立てとして数える
+    {
+        let _timer = Timer::start(Stage::Figure);
+        run(&params, &config_theme, &config_options);
+    }
+    if options.profile {
+        println!("{}", profile::report(started.elapsed()));
+    }
+
+    if options.watch {
+        let path = options
+            .config
+            .as_deref()
+            .expect("--watch requires --config");
+        watch_config(path, &theme, &options);
+    }
 }
@@ -0,0 +1,391 @@
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde::Serialize;
+use std::f64::consts::PI;
+
+use crate::reconstruct::ReconstructionMode;
+use crate::signal::{create_sine_wave, lloyd_max, Quantizer, SignalData, SignalParams, Waveform};
+use crate::spectrum::{aliased_frequency, spectrum};
+
+// THD の計算に含める高調波の次数（2次〜この次数まで）
+const THD_HARMONICS: usize = 5;
+
+// 信号対量子化雑音比 (dB)。ideal が量子化前、quantized が量子化後のサンプル
+pub fn sqnr_db(ideal: &[f64], quantized: &[f64]) -> f64 {
+    let signal_power: f64 = ideal.iter().map(|x| x * x).sum();
+    let noise_power: f64 = ideal
+        .iter()
+        .zip(quantized)
+        .map(|(x, q)| (q - x).powi(2))
+        .sum();
+    if noise_power == 0.0 {
+        return f64::INFINITY;
+    }
+    10.0 * (signal_power / noise_power).log10()
+}
+
+// 再構成の信号対誤差比 (dB)。continuous が元の連続信号、reconstructed が同じ時刻で再構成した値
+// sqnr_db と同じく信号の電力と誤差の電力の比で、元の信号に近いほど大きい
+pub fn reconstruction_snr_db(continuous: &[f64], reconstructed: &[f64]) -> f64 {
+    sqnr_db(continuous, reconstructed)
+}
+
+// --analyze で比べる再構成方法。--reconstruction で指定した方法はこの後ろに加える
+pub const COMPARED_RECONSTRUCTIONS: [ReconstructionMode; 3] = [
+    ReconstructionMode::Sinc,
+    ReconstructionMode::Linear,
+    ReconstructionMode::ZeroOrderHold,
+];
+
+// 方法ごとにサンプルから連続信号を再構成し、元の信号との SNR を並べる
+pub fn reconstruction_snrs(
+    params: &SignalParams,
+    data: &SignalData,
+    modes: &[ReconstructionMode],
+) -> Vec<(ReconstructionMode, f64)> {
+    modes
+        .iter()
+        .map(|&mode| {
+            let reconstructed = mode.reconstruct(
+                &data.sample_x,
+                &data.sample_y,
+                params.sampling_rate as f64,
+                &data.continuous_x,
+            );
+            (
+                mode,
+                reconstruction_snr_db(&data.continuous_y, &reconstructed),
+            )
+        })
+        .collect()
+}
+
+// 量子化ビット数と SQNR の関係を調べる範囲
+pub const SWEEP_BIT_DEPTHS: std::ops::RangeInclusive<u32> = 1..=16;
+
+// 一様量子化と Lloyd-Max 量子化を比べるビット数の範囲（Lloyd-Max は 2^N レベル）
+pub const LLOYD_MAX_BIT_DEPTHS: std::ops::RangeInclusive<u32> = 1..=8;
+
+// ビット数ごとに、一様（Linear）量子化と、信号の振幅分布に合わせた Lloyd-Max 量子化の SQNR
+// 戻り値は（ビット数, 一様の SQNR, Lloyd-Max の SQNR）。Lloyd-Max のレベルは 2^N 個
+// 学習には細かい時刻の連続信号を使う。周期的なサンプル列そのものに合わせると、値の種類が少ないときに
+// 各値にレベルが1つずつ載って誤差がほぼ 0 になり、量子化器の比較にならない
+pub fn lloyd_max_sweep(
+    params: &SignalParams,
+    bit_depths: std::ops::RangeInclusive<u32>,
+) -> Vec<(u32, f64, f64)> {
+    let training: Vec<f64> = create_sine_wave(params)
+        .continuous_y
+        .iter()
+        .map(|y| y / params.full_scale)
+        .collect();
+    bit_depths
+        .map(|bit_depth| {
+            let uniform = create_sine_wave(
+                &params
+                    .clone()
+                    .with_quantizer(Quantizer::Linear)
+                    .with_bit_depth(bit_depth),
+            );
+            let levels = lloyd_max(&training, 2usize.pow(bit_depth));
+            let optimized =
+                create_sine_wave(&params.clone().with_quantizer(Quantizer::Custom(levels)));
+            (
+                bit_depth,
+                sqnr_db(&uniform.ideal_y, &uniform.sample_y),
+                sqnr_db(&optimized.ideal_y, &optimized.sample_y),
+            )
+        })
+        .collect()
+}
+
+// フルスケールの正弦波に対する理論上の SQNR (dB)
+pub fn theoretical_sqnr_db(bit_depth: u32) -> f64 {
+    6.02 * bit_depth as f64 + 1.76
+}
+
+// 有効ビット数（ENOB）。実測 SQNR を満たす理想的な量子化器のビット数
+pub fn enob(sqnr_db: f64) -> f64 {
+    (sqnr_db - 1.76) / 6.02
+}
+
+// 1信号分の指標。文字列にせず値のまま返すので、テストや JSON 出力でそのまま使える
+// 量子化誤差が 0 のとき sqnr_db と enob は無限大（JSON では null）
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub struct SignalMetrics {
+    pub nyquist_ratio: f64,
+    pub samples_per_cycle: f64,
+    pub aliased_frequency: f64, // サンプリング後に観測される周波数 (Hz)
+    pub aliasing_severity: f64,
+    pub sqnr_db: f64,
+    pub enob: f64,
+    pub thd_percent: f64,
+    pub rms: f64, // 量子化後のサンプルの RMS（rms_window があれば最大の区間の値）
+    pub crest_factor: f64, // ピーク / RMS。正弦波で √2
+}
+
+// 生成した信号から指標をまとめて求める
+pub fn analyze(params: &SignalParams, data: &SignalData) -> SignalMetrics {
+    let sampling_rate = params.sampling_rate as f64;
+    let sqnr = sqnr_db(&data.ideal_y, &data.sample_y);
+    let level = level_window(params, &data.sample_y);
+    SignalMetrics {
+        nyquist_ratio: params.nyquist_ratio,
+        samples_per_cycle: params.samples_per_cycle(),
+        aliased_frequency: aliased_frequency(params.signal_freq, sampling_rate),
+        aliasing_severity: aliasing_severity(params),
+        sqnr_db: sqnr,
+        enob: enob(sqnr),
+        thd_percent: thd_percent(&data.sample_y, sampling_rate, params.signal_freq),
+        rms: rms(level),
+        crest_factor: crest_factor(level),
+    }
+}
+
+// 二乗平均平方根。空なら 0
+pub fn rms(samples: &[f64]) -> f64 {
+    if samples.is_empty() {
+        return 0.0;
+    }
+    (samples.iter().map(|y| y * y).sum::<f64>() / samples.len() as f64).sqrt()
+}
+
+// クレストファクタ（ピーク / RMS）。無音なら 0
+pub fn crest_factor(samples: &[f64]) -> f64 {
+    let rms = rms(samples);
+    if rms == 0.0 {
+        return 0.0;
+    }
+    samples.iter().fold(0.0, |peak: f64, y| peak.max(y.abs())) / rms
+}
+
+// RMS・クレストファクタを測る区間。rms_window が無ければ信号全体、
+// あればその長さずつに区切った（最後は短くてもよい）うち RMS が最大の区間
+fn level_window<'a>(params: &SignalParams, samples: &'a [f64]) -> &'a [f64] {
+    let Some(window) = params.rms_window else {
+        return samples;
+    };
+    let length = ((window * params.sampling_rate as f64).round() as usize).max(1);
+    samples
+        .chunks(length)
+        .max_by(|a, b| rms(a).total_cmp(&rms(b)))
+        .unwrap_or(samples)
+}
+
+// ビット数だけを変えて同じ信号を量子化し、実測 SQNR を並べる
+pub fn sqnr_sweep(
+    params: &SignalParams,
+    bit_depths: std::ops::RangeInclusive<u32>,
+) -> Vec<(u32, f64)> {
+    bit_depths
+        .map(|bit_depth| {
+            let swept = params.clone().with_bit_depth(bit_depth);
+            let data = create_sine_wave(&swept);
+            (bit_depth, sqnr_db(&data.ideal_y, &data.sample_y))
+        })
+        .collect()
+}
+
+// エイリアシングの深刻度（0〜1）
+// 成分ごとに、ナイキストを超えていれば 0.5、そこから fs まで離れるにつれて 1.0 まで上がる重みを付け、
+// 折り返すエネルギーの割合で重み付き平均する
+pub fn aliasing_severity(params: &SignalParams) -> f64 {
+    let sampling_rate = params.sampling_rate as f64;
+    let weight = |freq: f64| {
+        if freq <= sampling_rate / 2.0 {
+            0.0
+        } else {
+            0.5 + 0.5 * (2.0 * freq / sampling_rate - 1.0).min(1.0)
+        }
+    };
+
+    match params.waveform {
+        Waveform::Sine => weight(params.signal_freq),
+        // 合成した項のエネルギーで重み付けする。既定ではナイキスト未満の項だけなので 0 になる
+        // 基本波からナイキスト以上だと1項も合成せず無音になるので、これも 0
+        Waveform::BandLimitedSquare => {
+            let harmonics = params.synthesized_harmonics();
+            let total: f64 = harmonics.iter().map(|(_, a)| a * a).sum();
+            if total == 0.0 {
+                return 0.0;
+            }
+            harmonics
+                .iter()
+                .map(|(k, a)| a * a * weight(k * params.signal_freq))
+                .sum::<f64>()
+                / total
+        }
+        // 振幅 4/(πk) の奇数次高調波。エネルギーの割合は 8/(πk)^2 で、総和は 1
+        Waveform::Square => {
+            let (below_fs, severity) = (1..)
+                .step_by(2)
+                .map(|k| k as f64)
+                .take_while(|k| k * params.signal_freq < sampling_rate)
+                .fold((0.0, 0.0), |(below_fs, severity), k| {
+                    let energy = 8.0 / (PI * k).powi(2);
+                    (
+                        below_fs + energy,
+                        severity + energy * weight(k * params.signal_freq),
+                    )
+                });
+            // fs 以上の成分は重み 1.0
+            severity + (1.0 - below_fs).max(0.0)
+        }
+    }
+}
+
+// 全高調波歪み (%)。サンプル列のスペクトルから基本波と高調波の振幅を読み取る
+// 高調波は折り返した後の周波数で探し、基本波と同じ位置に落ちるものは除く
+pub fn thd_percent(samples: &[f64], sampling_rate: f64, fundamental: f64) -> f64 {
+    // 減衰信号は両端で打ち切られるため、ハン窓を掛けて漏れが高調波のビンに混ざるのを抑える
+    let n = samples.len();
+    let windowed: Vec<f64> = samples
+        .iter()
+        .enumerate()
+        .map(|(i, s)| s * 0.5 * (1.0 - (2.0 * PI * i as f64 / n as f64).cos()))
+        .collect();
+    let (freqs, mags) = spectrum(&windowed, sampling_rate);
+    if mags.is_empty() {
+        return 0.0;
+    }
+    let resolution = sampling_rate / samples.len() as f64;
+
+    // 指定周波数の前後1ビンの最大振幅
+    let amplitude_at = |frequency: f64| -> f64 {
+        freqs
+            .iter()
+            .zip(&mags)
+            .filter(|(f, _)| (*f - frequency).abs() <= resolution)
+            .map(|(_, m)| *m)
+            .fold(0.0, f64::max)
+    };
+
+    let fundamental_alias = aliased_frequency(fundamental, sampling_rate);
+    let fundamental_amplitude = amplitude_at(fundamental_alias);
+    if fundamental_amplitude == 0.0 {
+        return 0.0;
+    }
+
+    let harmonic_power: f64 = (2..=THD_HARMONICS)
+        .map(|k| aliased_frequency(k as f64 * fundamental, sampling_rate))
+        .filter(|f| (f - fundamental_alias).abs() > resolution)
+        .map(|f| amplitude_at(f).powi(2))
+        .sum();
+
+    100.0 * harmonic_power.sqrt() / fundamental_amplitude
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Stats {
+    pub mean: f64,
+    pub std_dev: f64,
+}
+
+impl Stats {
+    pub fn from_values(values: &[f64]) -> Self {
+        let n = values.len().max(1) as f64;
+        let mean = values.iter().sum::<f64>() / n;
+        let variance = values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / n;
+        Self {
+            mean,
+            std_dev: variance.sqrt(),
+        }
+    }
+}
+
+pub struct TrialSummary {
+    pub trials: usize,
+    pub sqnr_db: Stats,
+    pub thd_percent: Stats,
+}
+
+// 初期位相をランダムに変えて trials 回生成し、SQNR と THD の平均と標準偏差を求める
+// 1回の生成では位相によって指標が変わるため、平均を取って位相依存をならす
+pub fn monte_carlo(params: &SignalParams, trials: usize, seed: u64) -> TrialSummary {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let (sqnr, thd): (Vec<f64>, Vec<f64>) = (0..trials)
+        .map(|_| {
+            let trial = params.clone().with_phase(rng.gen_range(0.0..2.0 * PI));
+            let data = create_sine_wave(&trial);
+            (
+                sqnr_db(&data.ideal_y, &data.sample_y),
+                thd_percent(
+                    &data.sample_y,
+                    trial.sampling_rate as f64,
+                    trial.signal_freq,
+                ),
+            )
+        })
+        .unzip();
+
+    TrialSummary {
+        trials,
+        sqnr_db: Stats::from_values(&sqnr),
+        thd_percent: Stats::from_values(&thd),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f64::consts::{FRAC_1_SQRT_2, SQRT_2};
+
+    #[test]
+    fn analyze_reports_aliasing_and_resolution() {
+        let params = SignalParams::new("Aliasing", 10.0, 12, 16);
+        let metrics = analyze(&params, &create_sine_wave(&params));
+
+        assert_eq!(metrics.aliased_frequency, 2.0);
+        assert!((metrics.samples_per_cycle - 1.2).abs() < 1e-12);
+        assert!(metrics.aliasing_severity > 0.5);
+        assert!((metrics.enob - enob(metrics.sqnr_db)).abs() < 1e-12);
+        // 16-bit の減衰信号なので、ENOB は 16 ビットから大きくは外れない
+        assert!((13.0..17.0).contains(&metrics.enob), "{}", metrics.enob);
+    }
+
+    #[test]
+    fn sinc_reconstruction_beats_linear_and_hold() {
+        let params = SignalParams::new("Oversampled", 10.0, 64, 16);
+        let snrs = reconstruction_snrs(
+            &params,
+            &create_sine_wave(&params),
+            &COMPARED_RECONSTRUCTIONS,
+        );
+        let snr = |mode| snrs.iter().find(|(m, _)| *m == mode).unwrap().1;
+
+        assert!(snr(ReconstructionMode::Sinc) > snr(ReconstructionMode::Linear));
+        assert!(snr(ReconstructionMode::Linear) > snr(ReconstructionMode::ZeroOrderHold));
+        assert_eq!(
+            reconstruction_snr_db(&[1.0, -1.0], &[1.0, -1.0]),
+            f64::INFINITY
+        );
+    }
+
+    // 一定振幅の正弦波は RMS 1/√2、クレストファクタ √2。減衰させると全体の RMS は最大区間より小さい
+    #[test]
+    fn rms_and_crest_factor_of_a_sine() {
+        let params = SignalParams::new("Level", 10.0, 1000, 16).with_decay_rate(0.0);
+        let metrics = analyze(&params, &create_sine_wave(&params));
+        assert!(
+            (metrics.rms - FRAC_1_SQRT_2).abs() < 1e-3,
+            "{}",
+            metrics.rms
+        );
+        assert!((metrics.crest_factor - SQRT_2).abs() < 1e-3);
+
+        let decaying = SignalParams::new("Level", 10.0, 1000, 16).with_decay_rate(2.0);
+        let data = create_sine_wave(&decaying);
+        let whole = analyze(&decaying, &data);
+        let windowed = analyze(&decaying.clone().with_rms_window(0.1), &data);
+        assert!(windowed.rms > whole.rms);
+        assert!(windowed.crest_factor < whole.crest_factor);
+    }
+
+    // 基本波がナイキストを超える band-limited square は何も合成しないので、NaN ではなく 0
+    #[test]
+    fn silent_band_limited_square_has_no_aliasing() {
+        let params =
+            SignalParams::new("Silent", 10.0, 12, 16).with_waveform(Waveform::BandLimitedSquare);
+        assert!(params.synthesized_harmonics().is_empty());
+        assert_eq!(aliasing_severity(&params), 0.0);
+    }
+}
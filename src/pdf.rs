@@ -0,0 +1,217 @@
+use std::io::{self, Write};
+
+// PDF のページ幅 (pt)。A4 横置きの幅で、高さは画像の縦横比に合わせる
+const PAGE_WIDTH: f64 = 842.0;
+
+// PDF に貼り込む JPEG 画像。データは再エンコードせず DCTDecode のまま埋め込む
+pub struct JpegImage {
+    pub data: Vec<u8>,
+    pub width: u32,
+    pub height: u32,
+    pub components: u8, // 1: グレー, 3: RGB, 4: CMYK
+}
+
+impl JpegImage {
+    // SOF マーカーから画像サイズと色成分数を読み取る。JPEG として読めなければ None
+    pub fn from_bytes(data: Vec<u8>) -> Option<Self> {
+        if data.get(..2)? != [0xFF, 0xD8] {
+            return None;
+        }
+        let mut pos = 2;
+        while pos + 4 <= data.len() {
+            if data[pos] != 0xFF {
+                return None;
+            }
+            let marker = data[pos + 1];
+            let length = u16::from_be_bytes([data[pos + 2], data[pos + 3]]) as usize;
+            // SOF0〜SOF15 のうち DHT（C4）・JPG（C8）・DAC（CC）以外がフレームヘッダ
+            if (0xC0..=0xCF).contains(&marker) && ![0xC4, 0xC8, 0xCC].contains(&marker) {
+                let header = data.get(pos + 4..pos + 10)?;
+                let height = u16::from_be_bytes([header[1], header[2]]) as u32;
+                let width = u16::from_be_bytes([header[3], header[4]]) as u32;
+                let components = header[5];
+                return Some(Self {
+                    data,
+                    width,
+                    height,
+                    components,
+                });
+            }
+            pos += 2 + length;
+        }
+        None
+    }
+
+    fn color_space(&self) -> &'static str {
+        match self.components {
+            1 => "/DeviceGray",
+            4 => "/DeviceCMYK",
+            _ => "/DeviceRGB",
+        }
+    }
+}
+
+// 書き込んだバイト数を数えて、xref 用に各オブジェクトの位置を記録する
+struct PdfWriter<W: Write> {
+    inner: W,
+    position: usize,
+    offsets: Vec<usize>,
+}
+
+impl<W: Write> PdfWriter<W> {
+    fn write(&mut self, bytes: &[u8]) -> io::Result<()> {
+        self.inner.write_all(bytes)?;
+        self.position += bytes.len();
+        Ok(())
+    }
+
+    // オブジェクト番号は 1 から順に振る前提
+    fn object(&mut self, body: &str) -> io::Result<()> {
+        self.offsets.push(self.position);
+        let number = self.offsets.len();
+        self.write(format!("{} 0 obj\n{}\nendobj\n", number, body).as_bytes())
+    }
+
+    fn stream(&mut self, dictionary: &str, data: &[u8]) -> io::Result<()> {
+        self.offsets.push(self.position);
+        let number = self.offsets.len();
+        let dictionary = format!("{} /Length {}", dictionary, data.len());
+        self.write(
+            format!(
+                "{} 0 obj\n<< {} >>\nstream\n",
+                number,
+                dictionary.trim_start()
+            )
+            .as_bytes(),
+        )?;
+        self.write(data)?;
+        self.write(b"\nendstream\nendobj\n")
+    }
+}
+
+// 1ページに1枚ずつ画像を全面に貼った PDF を書き出す
+// オブジェクト番号: 1 = Catalog, 2 = Pages, 以降ページごとに Page, 内容, 画像の3つ
+pub fn write_pdf<W: Write>(pages: &[JpegImage], writer: W) -> io::Result<()> {
+    let mut pdf = PdfWriter {
+        inner: writer,
+        position: 0,
+        offsets: Vec::new(),
+    };
+    pdf.write(b"%PDF-1.4\n%\xE2\xE3\xCF\xD3\n")?;
+
+    let page_object = |i: usize| 3 + 3 * i;
+    let kids: Vec<String> = (0..pages.len())
+        .map(|i| format!("{} 0 R", page_object(i)))
+        .collect();
+    pdf.object("<< /Type /Catalog /Pages 2 0 R >>")?;
+    pdf.object(&format!(
+        "<< /Type /Pages /Kids [{}] /Count {} >>",
+        kids.join(" "),
+        pages.len()
+    ))?;
+
+    for (i, image) in pages.iter().enumerate() {
+        let height = PAGE_WIDTH * image.height as f64 / image.width.max(1) as f64;
+        let (content, xobject) = (page_object(i) + 1, page_object(i) + 2);
+        pdf.object(&format!(
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {:.2} {:.2}] \
+             /Resources << /XObject << /Im0 {} 0 R >> >> /Contents {} 0 R >>",
+            PAGE_WIDTH, height, xobject, content
+        ))?;
+        let drawing = format!("q {:.2} 0 0 {:.2} 0 0 cm /Im0 Do Q", PAGE_WIDTH, height);
+        pdf.stream("", drawing.as_bytes())?;
+        pdf.stream(
+            &format!(
+                "/Type /XObject /Subtype /Image /Width {} /Height {} /ColorSpace {} \
+                 /BitsPerComponent 8 /Filter /DCTDecode",
+                image.width,
+                image.height,
+                image.color_space()
+            ),
+            &image.data,
+        )?;
+    }
+
+    let xref = pdf.position;
+    let mut table = format!("xref\n0 {}\n0000000000 65535 f \n", pdf.offsets.len() + 1);
+    for offset in &pdf.offsets {
+        table.push_str(&format!("{:010} 00000 n \n", offset));
+    }
+    table.push_str(&format!(
+        "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+        pdf.offsets.len() + 1,
+        xref
+    ));
+    pdf.write(table.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // SOI, APP0（中身は読み飛ばす）, SOF0（高さ 2, 幅 3, 3成分）だけの JPEG ヘッダ
+    fn minimal_jpeg() -> Vec<u8> {
+        let mut data = vec![0xFF, 0xD8, 0xFF, 0xE0, 0x00, 0x04, 0x00, 0x00];
+        data.extend([0xFF, 0xC0, 0x00, 0x11, 0x08, 0x00, 0x02, 0x00, 0x03, 0x03]);
+        data.extend([0x00; 9]);
+        data
+    }
+
+    fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+        haystack
+            .windows(needle.len())
+            .position(|window| window == needle)
+    }
+
+    #[test]
+    fn jpeg_size_is_read_from_the_frame_header() {
+        let image = JpegImage::from_bytes(minimal_jpeg()).unwrap();
+        assert_eq!((image.width, image.height, image.components), (3, 2, 3));
+        assert_eq!(image.color_space(), "/DeviceRGB");
+
+        assert!(JpegImage::from_bytes(b"\x89PNG\r\n\x1a\n".to_vec()).is_none());
+        assert!(JpegImage::from_bytes(Vec::new()).is_none());
+        // SOF の前で途切れたもの
+        assert!(JpegImage::from_bytes(minimal_jpeg()[..12].to_vec()).is_none());
+    }
+
+    // xref の各項目が "N 0 obj" の先頭を指し、startxref が xref 表の位置を指す
+    #[test]
+    fn xref_offsets_point_at_each_object() {
+        let pages: Vec<JpegImage> = (0..2)
+            .map(|_| JpegImage::from_bytes(minimal_jpeg()).unwrap())
+            .collect();
+        let mut pdf = Vec::new();
+        write_pdf(&pages, &mut pdf).unwrap();
+        assert!(pdf.starts_with(b"%PDF-1.4\n"));
+        assert!(find(&pdf, b"/Count 2 >>").is_some());
+
+        let trailer = String::from_utf8_lossy(&pdf[find(&pdf, b"trailer").unwrap()..]).to_string();
+        let startxref: usize = trailer
+            .lines()
+            .skip_while(|line| *line != "startxref")
+            .nth(1)
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert!(pdf[startxref..].starts_with(b"xref\n"));
+
+        let table = String::from_utf8_lossy(&pdf[startxref..]).to_string();
+        let mut lines = table.lines().skip(1);
+        assert_eq!(lines.next(), Some("0 9")); // 空き項目 + Catalog, Pages, 2ページ × 3
+        assert_eq!(lines.next(), Some("0000000000 65535 f "));
+        for number in 1..=8 {
+            let entry = lines.next().unwrap();
+            assert!(entry.ends_with(" 00000 n "), "{}", entry);
+            let offset: usize = entry[..10].parse().unwrap();
+            let header = format!("{} 0 obj\n", number);
+            assert!(
+                pdf[offset..].starts_with(header.as_bytes()),
+                "object {} is not at {}",
+                number,
+                offset
+            );
+        }
+        assert!(pdf.ends_with(b"%%EOF\n"));
+    }
+}
@@ -0,0 +1,311 @@
+use std::f64::consts::PI;
+
+use crate::signal::{Quantizer, SignalParams, Waveform};
+
+// 名前で選べるパラメータセット（名前, 説明）
+pub const PRESETS: &[(&str, &str)] = &[
+    (
+        "aliasing",
+        "10Hz tone at 8/12/24/240Hz sampling rates, 16-bit (default)",
+    ),
+    (
+        "bit-depth",
+        "10Hz tone at 240Hz sampling rate, 2/4/8/16-bit",
+    ),
+    (
+        "square",
+        "70Hz naive vs band-limited square wave at 1000Hz, 16-bit",
+    ),
+    (
+        "audio-rates",
+        "15kHz tone at 8k/22.05k/44.1k/96k sampling rates, 16-bit, 2ms",
+    ),
+    (
+        "fixed-point",
+        "Band-limited square (overshoots ±1) as 16-bit linear, Q1.15 and Q2.14",
+    ),
+    (
+        "critical",
+        "10Hz tone sampled at exactly 20Hz (2x) from phases 0/45/90/135 degrees, 16-bit",
+    ),
+    (
+        "wagon-wheel",
+        "Wheel turning 6/11/13/22 rev/s filmed at 24fps (temporal aliasing), 1s",
+    ),
+];
+
+// よく使われるオーディオのサンプリング周波数（名前, Hz）
+pub const SAMPLE_RATES: &[(&str, i64)] = &[
+    ("telephone", 8_000),
+    ("wideband", 16_000),
+    ("half-cd", 22_050),
+    ("cd", 44_100),
+    ("dvd", 48_000),
+    ("hi-res", 96_000),
+    ("hi-res-192", 192_000),
+];
+
+// "44.1k" や "48000" "48000Hz"、SAMPLE_RATES の名前をサンプリング周波数 (Hz) にする
+// 整数の Hz にならない値や 0 以下は None
+pub fn parse_sample_rate(text: &str) -> Option<i64> {
+    if let Some((_, rate)) = SAMPLE_RATES.iter().find(|(name, _)| *name == text) {
+        return Some(*rate);
+    }
+    let number = text
+        .strip_suffix("Hz")
+        .or_else(|| text.strip_suffix("hz"))
+        .unwrap_or(text);
+    let (number, multiplier) = match number.strip_suffix(['k', 'K']) {
+        Some(number) => (number, 1000.0),
+        None => (number, 1.0),
+    };
+    let rate = number.parse::<f64>().ok()? * multiplier;
+    let rounded = rate.round();
+    ((rate - rounded).abs() < 1e-6 && rounded > 0.0).then_some(rounded as i64)
+}
+
+// 平均律の基準 A4 (Hz)
+pub const CONCERT_A: f64 = 440.0;
+
+// "A4" "C#5" "Bb3" のような音名（と "+25c" "-10cents" のようなセント単位のずれ）を平均律で Hz にする
+// 音名は大文字小文字を問わず、# / b（♯ / ♭）を重ねてもよい。オクターブは科学的音高表記（C4 が中央のド）
+pub fn note_frequency(text: &str) -> Option<f64> {
+    let mut chars = text.chars().peekable();
+    let semitone = match chars.next()?.to_ascii_uppercase() {
+        'C' => 0,
+        'D' => 2,
+        'E' => 4,
+        'F' => 5,
+        'G' => 7,
+        'A' => 9,
+        'B' => 11,
+        _ => return None,
+    };
+    let mut accidental = 0;
+    while let Some(&c) = chars.peek() {
+        match c {
+            '#' | '♯' => accidental += 1,
+            'b' | '♭' => accidental -= 1,
+            _ => break,
+        }
+        chars.next();
+    }
+    let rest: String = chars.collect();
+    // オクターブは負でもよい（C-1 が MIDI の 0 番）。その後ろがセントのずれ
+    let split = rest
+        .char_indices()
+        .skip(1)
+        .find(|&(_, c)| c == '+' || c == '-')
+        .map_or(rest.len(), |(i, _)| i);
+    let (octave, cents) = rest.split_at(split);
+    let octave: i32 = octave.parse().ok()?;
+    let cents: f64 = if cents.is_empty() {
+        0.0
+    } else {
+        let number = ["cents", "cent", "c"]
+            .iter()
+            .find_map(|suffix| cents.strip_suffix(suffix))?;
+        number.trim_start_matches('+').parse().ok()?
+    };
+    let midi = 12 * (octave + 1) + semitone + accidental;
+    let frequency = CONCERT_A * 2f64.powf((midi - 69) as f64 / 12.0 + cents / 1200.0);
+    frequency.is_finite().then_some(frequency)
+}
+
+// 信号の周波数。"440" "1.5k" "440Hz" のような Hz の値か、note_frequency の音名。正の有限値のみ
+pub fn parse_frequency(text: &str) -> Option<f64> {
+    let text = text.trim();
+    if let Some(frequency) = note_frequency(text) {
+        return Some(frequency);
+    }
+    let number = text
+        .strip_suffix("Hz")
+        .or_else(|| text.strip_suffix("hz"))
+        .unwrap_or(text);
+    let (number, multiplier) = match number.strip_suffix(['k', 'K']) {
+        Some(number) => (number, 1000.0),
+        None => (number, 1.0),
+    };
+    let frequency = number.parse::<f64>().ok()? * multiplier;
+    (frequency.is_finite() && frequency > 0.0).then_some(frequency)
+}
+
+// エイリアシングを示す標準のパラメータセット
+pub fn aliasing_preset() -> Vec<SignalParams> {
+    vec![
+        SignalParams::new("Severe Aliasing", 10.0, 8, 16),
+        SignalParams::new("Aliasing", 10.0, 12, 16),
+        SignalParams::new("Near Nyquist", 10.0, 24, 16),
+        SignalParams::new("Hi Resolution", 10.0, 240, 16),
+    ]
+}
+
+// サンプリング条件を固定し、量子化ビット数だけを変える
+pub fn bit_depth_preset() -> Vec<SignalParams> {
+    [2, 4, 8, 16]
+        .into_iter()
+        .map(|bits| SignalParams::new(&format!("{}-bit", bits), 10.0, 240, bits))
+        .collect()
+}
+
+// 素朴な矩形波と帯域制限した矩形波の比較
+// 630Hz 以上の高調波が 370Hz, 230Hz, 90Hz... に折り返すのがスペクトルで見える
+pub fn square_preset() -> Vec<SignalParams> {
+    vec![
+        SignalParams::new("Naive Square", 70.0, 1000, 16).with_waveform(Waveform::Square),
+        SignalParams::new("Band-Limited Square", 70.0, 1000, 16)
+            .with_waveform(Waveform::BandLimitedSquare),
+    ]
+}
+
+// 聞き慣れたサンプリング周波数で 15kHz の音を録る
+// 8k では 1kHz、22.05k では 7.05kHz に折り返し、44.1k 以上でそのまま残る
+// 高い周波数なので 2ms（30周期）だけを描く
+pub fn audio_rates_preset() -> Vec<SignalParams> {
+    [
+        ("Telephone 8k", 8_000),
+        ("22.05k", 22_050),
+        ("CD 44.1k", 44_100),
+        ("Hi-Res 96k", 96_000),
+    ]
+    .into_iter()
+    .map(|(name, rate)| SignalParams::new(name, 15_000.0, rate, 16).with_duration(0.002))
+    .collect()
+}
+
+// 固定小数点の整数部（ヘッドルーム）の役割
+// 帯域制限した矩形波はギブス現象で ±1 を約 9% 超えるので、Q1.15 では飽和するか回り込む
+// Q2.14 は ±2 まで表せる代わりにステップが2倍（約 6dB 分）粗くなる
+pub fn fixed_point_preset() -> Vec<SignalParams> {
+    let square = |name: &str| {
+        SignalParams::new(name, 70.0, 1000, 16).with_waveform(Waveform::BandLimitedSquare)
+    };
+    let fixed = |name: &str, format: &str, saturate: bool| {
+        square(name).with_quantizer(
+            Quantizer::from_name(format, saturate).expect("preset formats are valid"),
+        )
+    };
+    vec![
+        square("16-bit Linear"),
+        fixed("Q1.15 Saturating", "Q1.15", true),
+        fixed("Q1.15 Wrapping", "Q1.15", false),
+        fixed("Q2.14 Saturating", "Q2.14", true),
+    ]
+}
+
+// ちょうど 2 倍のサンプリング（臨界サンプリング）。サンプルは毎回半周期ずつ進んだ同じ位相を拾うので、
+// 取り込める振幅は初期位相 φ だけで決まり |sin φ| 倍になる（0° で全サンプルがゼロ、90° でピークそのもの）
+// 「2 倍」は余裕ではなく限界であることを示す。振幅を読み取りやすいよう減衰させない
+pub fn critical_preset() -> Vec<SignalParams> {
+    [0, 45, 90, 135]
+        .into_iter()
+        .map(|degrees| {
+            SignalParams::new(&format!("Phase {}°", degrees), 10.0, 20, 16)
+                .with_phase(degrees as f64 * PI / 180.0)
+                .with_decay_rate(0.0)
+        })
+        .collect()
+}
+
+// ワゴンホイール効果。車輪上の1点を 24fps で撮ると、回転数がフレームレートの半分を超えたところで逆回転に見える
+// 13 回転/秒は 11 回転/秒の逆回転、22 回転/秒は 2 回転/秒の逆回転になる。定常回転なので減衰させない
+pub fn wagon_wheel_preset() -> Vec<SignalParams> {
+    [
+        ("6 rev/s", 6.0),
+        ("11 rev/s", 11.0),
+        ("13 rev/s (Reversed)", 13.0),
+        ("22 rev/s (Reversed)", 22.0),
+    ]
+    .into_iter()
+    .map(|(name, rotation)| {
+        SignalParams::new(name, rotation, 24, 16)
+            .with_decay_rate(0.0)
+            .with_duration(1.0)
+    })
+    .collect()
+}
+
+// 校正用の 1kHz 基準音（0 dBFS、減衰なし、48kHz/16-bit）。--reference で信号の後ろに加える
+// 一定振幅の既知の信号なので、その SQNR とスペクトルを他のパネルの基準にできる
+pub fn reference_tone() -> SignalParams {
+    SignalParams::new("1kHz Reference", 1000.0, 48_000, 16)
+        .with_decay_rate(0.0)
+        .with_duration(0.005)
+        .with_color("rgba(127, 127, 127, 1.0)")
+}
+
+pub fn preset_by_name(name: &str) -> Option<Vec<SignalParams>> {
+    match name {
+        "aliasing" => Some(aliasing_preset()),
+        "bit-depth" => Some(bit_depth_preset()),
+        "square" => Some(square_preset()),
+        "audio-rates" => Some(audio_rates_preset()),
+        "fixed-point" => Some(fixed_point_preset()),
+        "critical" => Some(critical_preset()),
+        "wagon-wheel" => Some(wagon_wheel_preset()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signal::create_sine_wave;
+
+    #[test]
+    fn parse_sample_rate_expands_shorthand() {
+        assert_eq!(parse_sample_rate("44.1k"), Some(44_100));
+        assert_eq!(parse_sample_rate("22.05k"), Some(22_050));
+        assert_eq!(parse_sample_rate("48000"), Some(48_000));
+        assert_eq!(parse_sample_rate("96kHz"), Some(96_000));
+        assert_eq!(parse_sample_rate("cd"), Some(44_100));
+        assert_eq!(parse_sample_rate("44.1005k"), None);
+        assert_eq!(parse_sample_rate("0"), None);
+        assert_eq!(parse_sample_rate("fast"), None);
+    }
+
+    #[test]
+    fn note_names_follow_equal_temperament() {
+        let close = |text: &str, expected: f64| {
+            let frequency = parse_frequency(text).unwrap();
+            assert!(
+                (frequency - expected).abs() < 0.01,
+                "{}: {}",
+                text,
+                frequency
+            );
+        };
+        close("A4", 440.0);
+        close("a5", 880.0);
+        close("C4", 261.63);
+        close("C#5", 554.37);
+        close("Db5", 554.37);
+        close("Bb3", 233.08);
+        close("C-1", 8.18);
+        close("A4+100c", 466.16); // 100 セントは半音
+        close("A4-1200cents", 220.0);
+        close("1.5k", 1500.0);
+        close("440Hz", 440.0);
+        assert_eq!(parse_frequency("H4"), None);
+        assert_eq!(parse_frequency("A"), None);
+        assert_eq!(parse_frequency("A4+25"), None);
+        assert_eq!(parse_frequency("-440"), None);
+    }
+
+    #[test]
+    fn critical_sampling_captures_the_sine_of_the_phase() {
+        for param in critical_preset() {
+            assert!(param.is_at_nyquist());
+            let captured = create_sine_wave(&param)
+                .sample_y
+                .iter()
+                .fold(0.0, |peak: f64, y| peak.max(y.abs()));
+            assert!(
+                (captured - param.phase.sin().abs()).abs() < 1e-3,
+                "{}: {}",
+                param.name,
+                captured
+            );
+        }
+    }
+}
@@ -0,0 +1,140 @@
+use std::cell::RefCell;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+// --profile で集計する処理の段階
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Stage {
+    Generation, // 信号の生成と量子化
+    Spectrum,   // FFT
+    Figure,     // 図（トレースとレイアウト）の組み立て。指標の計算なども含む
+    Write,      // PNG（kaleido）・HTML の書き出し
+}
+
+impl Stage {
+    pub const ALL: [Stage; 4] = [
+        Stage::Generation,
+        Stage::Spectrum,
+        Stage::Figure,
+        Stage::Write,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Generation => "signal generation",
+            Self::Spectrum => "spectrum (FFT)",
+            Self::Figure => "figure build",
+            Self::Write => "image write",
+        }
+    }
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+// 段階ごとの（所要時間, 回数）。時間は入れ子になった段階の分を除いた正味
+static TOTALS: Mutex<[(Duration, usize); 4]> = Mutex::new([(Duration::ZERO, 0); 4]);
+
+thread_local! {
+    // 計測中の段階ごとに、内側の段階が使った時間を積む
+    static NESTED: RefCell<Vec<Duration>> = const { RefCell::new(Vec::new()) };
+}
+
+// スコープを抜けるまでの時間を stage に足す。図の組み立ての中で信号を生成したときなどは、
+// 内側の時間を外側から引くので、各段階の合計が全体の時間を超えない
+pub struct Timer {
+    stage: Stage,
+    start: Instant,
+}
+
+impl Timer {
+    pub fn start(stage: Stage) -> Self {
+        NESTED.with(|nested| nested.borrow_mut().push(Duration::ZERO));
+        Self {
+            stage,
+            start: Instant::now(),
+        }
+    }
+}
+
+impl Drop for Timer {
+    fn drop(&mut self) {
+        let elapsed = self.start.elapsed();
+        let inner = NESTED.with(|nested| {
+            let mut nested = nested.borrow_mut();
+            let inner = nested.pop().unwrap_or_default();
+            if let Some(outer) = nested.last_mut() {
+                *outer += elapsed;
+            }
+            inner
+        });
+        if let Ok(mut totals) = TOTALS.lock() {
+            let total = &mut totals[self.stage.index()];
+            total.0 += elapsed.saturating_sub(inner);
+            total.1 += 1;
+        }
+    }
+}
+
+// これまでの集計（段階, 正味の時間, 回数）
+pub fn totals() -> Vec<(Stage, Duration, usize)> {
+    let totals = TOTALS.lock().map(|totals| *totals).unwrap_or_default();
+    Stage::ALL
+        .iter()
+        .map(|&stage| (stage, totals[stage.index()].0, totals[stage.index()].1))
+        .collect()
+}
+
+// 全体の時間 wall に対する内訳。どの段階にも入らない時間（設定の読み込みなど）は other とする
+pub fn report(wall: Duration) -> String {
+    let totals = totals();
+    let measured: Duration = totals.iter().map(|(_, time, _)| *time).sum();
+    let percent = |time: Duration| match wall.as_secs_f64() {
+        0.0 => 0.0,
+        wall => 100.0 * time.as_secs_f64() / wall,
+    };
+    let mut lines = vec!["profile:".to_string()];
+    for (stage, time, calls) in totals {
+        lines.push(format!(
+            "  {:<18} {:>9.1} ms {:>5.1} % ({}x)",
+            stage.label(),
+            time.as_secs_f64() * 1000.0,
+            percent(time),
+            calls
+        ));
+    }
+    let other = wall.saturating_sub(measured);
+    lines.push(format!(
+        "  {:<18} {:>9.1} ms {:>5.1} %",
+        "other",
+        other.as_secs_f64() * 1000.0,
+        percent(other)
+    ));
+    lines.push(format!(
+        "  {:<18} {:>9.1} ms",
+        "total",
+        wall.as_secs_f64() * 1000.0
+    ));
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 内側の段階の時間は外側から引かれる（ほかのテストも生成を数えるので、図の段階だけを見る）
+    #[test]
+    fn nested_stages_are_not_counted_twice() {
+        let figure = |totals: &[(Stage, Duration, usize)]| totals[Stage::Figure.index()];
+        let before = figure(&totals());
+        {
+            let _figure = Timer::start(Stage::Figure);
+            let _generation = Timer::start(Stage::Generation);
+            std::thread::sleep(Duration::from_millis(20));
+        }
+        let after = figure(&totals());
+        assert_eq!(after.2 - before.2, 1);
+        assert!(after.1 - before.1 < Duration::from_millis(10));
+    }
+}
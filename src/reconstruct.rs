@@ -0,0 +1,392 @@
+use std::f64::consts::PI;
+
+// サンプル列から任意の時刻の値を直線補間で再構成する
+// 最初と最後のサンプルの外側は端の値を保持する
+pub fn reconstruct_linear(sample_x: &[f64], sample_y: &[f64], x: &[f64]) -> Vec<f64> {
+    x.iter()
+        .map(|&t| {
+            let upper = sample_x.partition_point(|&s| s <= t);
+            if upper == 0 {
+                return sample_y.first().copied().unwrap_or(0.0);
+            }
+            if upper >= sample_x.len() {
+                return sample_y.last().copied().unwrap_or(0.0);
+            }
+            let (x0, x1) = (sample_x[upper - 1], sample_x[upper]);
+            let (y0, y1) = (sample_y[upper - 1], sample_y[upper]);
+            y0 + (y1 - y0) * (t - x0) / (x1 - x0)
+        })
+        .collect()
+}
+
+// サンプル値を次のサンプルまで保持する（0次ホールド、DAC の階段出力）
+pub fn reconstruct_zero_order_hold(sample_x: &[f64], sample_y: &[f64], x: &[f64]) -> Vec<f64> {
+    x.iter()
+        .map(|&t| {
+            let upper = sample_x.partition_point(|&s| s <= t);
+            sample_y
+                .get(upper.saturating_sub(1))
+                .copied()
+                .unwrap_or(0.0)
+        })
+        .collect()
+}
+
+// 正規化 sinc 関数 sin(πx)/(πx)
+fn sinc(x: f64) -> f64 {
+    if x.abs() < 1e-12 {
+        1.0
+    } else {
+        (PI * x).sin() / (PI * x)
+    }
+}
+
+// 理想的なローパス（sinc 補間）による再構成。サンプルは 0 秒から 1/fs 間隔で並んでいるとする
+// 全サンプルを足し合わせるので、有限長の信号では端で打ち切りの誤差が出る
+pub fn reconstruct_sinc(samples: &[f64], sampling_rate: f64, x: &[f64]) -> Vec<f64> {
+    x.iter()
+        .map(|&t| {
+            let position = t * sampling_rate;
+            samples
+                .iter()
+                .enumerate()
+                .map(|(n, &y)| y * sinc(position - n as f64))
+                .sum()
+        })
+        .collect()
+}
+
+// 有限長 sinc カーネルに掛ける窓関数
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Window {
+    Rectangular, // 単純な打ち切り。リップルが大きい
+    #[default]
+    Hann,
+    Blackman, // 阻止域の減衰が大きい代わりにメインローブが広い
+}
+
+impl Window {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "rectangular" => Some(Self::Rectangular),
+            "hann" => Some(Self::Hann),
+            "blackman" => Some(Self::Blackman),
+            _ => None,
+        }
+    }
+
+    // u は -1.0〜1.0（カーネルの端が ±1）
+    fn value(self, u: f64) -> f64 {
+        match self {
+            Self::Rectangular => 1.0,
+            Self::Hann => 0.5 * (1.0 + (PI * u).cos()),
+            Self::Blackman => 0.42 + 0.5 * (PI * u).cos() + 0.08 * (2.0 * PI * u).cos(),
+        }
+    }
+}
+
+// 時刻 t での窓付き sinc 補間の値。t の前後 taps/2 サンプルだけを使う
+fn windowed_sinc_at(
+    samples: &[f64],
+    sampling_rate: f64,
+    t: f64,
+    taps: usize,
+    window: Window,
+) -> f64 {
+    let position = t * sampling_rate;
+    let half = taps as f64 / 2.0;
+    let first = (position - half).ceil().max(0.0) as usize;
+    let last = ((position + half).floor().max(0.0) as usize).min(samples.len().saturating_sub(1));
+    (first..=last)
+        .filter(|&n| n < samples.len())
+        .map(|n| {
+            let offset = position - n as f64;
+            samples[n] * sinc(offset) * window.value(offset / half)
+        })
+        .sum()
+}
+
+// 窓付き sinc カーネル（taps サンプル分）による再構成。fs の oversample 倍のレートで出力する
+// 戻り値は（時刻, 値）。taps を増やすほど理想的な sinc 再構成に近づく
+pub fn reconstruct_windowed_sinc(
+    samples: &[f64],
+    sampling_rate: f64,
+    oversample: usize,
+    taps: usize,
+    window: Window,
+) -> (Vec<f64>, Vec<f64>) {
+    let output_rate = sampling_rate * oversample as f64;
+    (0..samples.len() * oversample)
+        .map(|m| {
+            let t = m as f64 / output_rate;
+            (t, windowed_sinc_at(samples, sampling_rate, t, taps, window))
+        })
+        .unzip()
+}
+
+// 周波数特性を数値積分するときの、1サンプル間あたりの分割数
+const RESPONSE_STEPS_PER_SAMPLE: f64 = 64.0;
+
+// 理想的な sinc カーネルの周波数特性を求めるときの打ち切り幅（片側のサンプル数）
+const SINC_RESPONSE_HALF_WIDTH: f64 = 64.0;
+
+// 波形パネルで使う再構成方法
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum ReconstructionMode {
+    #[default]
+    Linear, // サンプル間を直線で結ぶ
+    ZeroOrderHold, // 次のサンプルまで値を保持する
+    Sinc,          // 全サンプルを使う理想的な sinc 補間
+    WindowedSinc {
+        taps: usize,
+        window: Window,
+    }, // 有限長の窓付き sinc 補間
+}
+
+impl ReconstructionMode {
+    // windowed-sinc の taps と window は別のオプションで指定する
+    pub fn from_name(name: &str, taps: usize, window: Window) -> Option<Self> {
+        match name {
+            "linear" => Some(Self::Linear),
+            "zoh" => Some(Self::ZeroOrderHold),
+            "sinc" => Some(Self::Sinc),
+            "windowed-sinc" => Some(Self::WindowedSinc { taps, window }),
+            _ => None,
+        }
+    }
+
+    pub fn label(self) -> String {
+        match self {
+            Self::Linear => "Linear".to_string(),
+            Self::ZeroOrderHold => "Zero-Order Hold".to_string(),
+            Self::Sinc => "Sinc".to_string(),
+            Self::WindowedSinc { taps, window } => {
+                format!("Windowed Sinc ({} taps, {:?})", taps, window)
+            }
+        }
+    }
+
+    // 補間カーネル h(u)。u はサンプル間隔で正規化した時刻で、y(t) = Σ x[n] h(t·fs - n)
+    fn kernel(self, u: f64) -> f64 {
+        match self {
+            Self::Linear => (1.0 - u.abs()).max(0.0),
+            Self::ZeroOrderHold => {
+                if (0.0..1.0).contains(&u) {
+                    1.0
+                } else {
+                    0.0
+                }
+            }
+            Self::Sinc => sinc(u),
+            Self::WindowedSinc { taps, window } => {
+                let half = taps as f64 / 2.0;
+                if u.abs() > half {
+                    0.0
+                } else {
+                    sinc(u) * window.value(u / half)
+                }
+            }
+        }
+    }
+
+    // カーネルが 0 でない区間（サンプル単位）。理想的な sinc は無限に続くので打ち切る
+    fn kernel_support(self) -> (f64, f64) {
+        match self {
+            Self::Linear => (-1.0, 1.0),
+            Self::ZeroOrderHold => (0.0, 1.0),
+            Self::Sinc => (-SINC_RESPONSE_HALF_WIDTH, SINC_RESPONSE_HALF_WIDTH),
+            Self::WindowedSinc { taps, .. } => (-(taps as f64) / 2.0, taps as f64 / 2.0),
+        }
+    }
+
+    // カーネルのフーリエ変換 H(f) と、t·h(t) のフーリエ変換を数値積分で求める
+    // f は fs で正規化した周波数（サイクル/サンプル）
+    fn transforms(self, f: f64) -> ((f64, f64), (f64, f64)) {
+        let (start, end) = self.kernel_support();
+        let steps = ((end - start) * RESPONSE_STEPS_PER_SAMPLE) as usize;
+        let du = (end - start) / steps as f64;
+        (0..steps)
+            .map(|i| start + (i as f64 + 0.5) * du) // 中点則
+            .fold(((0.0, 0.0), (0.0, 0.0)), |((re, im), (t_re, t_im)), u| {
+                let h = self.kernel(u) * du;
+                let (sin, cos) = (2.0 * PI * f * u).sin_cos();
+                (
+                    (re + h * cos, im - h * sin),
+                    (t_re + u * h * cos, t_im - u * h * sin),
+                )
+            })
+    }
+
+    // 振幅特性 |H(f)|（直流で 1 に正規化）
+    pub fn magnitude_response(self, frequencies: &[f64]) -> Vec<f64> {
+        let dc = self.transforms(0.0).0 .0;
+        frequencies
+            .iter()
+            .map(|&f| {
+                let ((re, im), _) = self.transforms(f);
+                re.hypot(im) / dc
+            })
+            .collect()
+    }
+
+    // 群遅延（サンプル数）。τ = Re[ F{t·h(t)} / F{h(t)} ] で、位相のアンラップは要らない
+    // |H(f)| が 0 になる周波数では定義できないので NaN を返す
+    pub fn group_delay(self, frequencies: &[f64]) -> Vec<f64> {
+        frequencies
+            .iter()
+            .map(|&f| {
+                let ((re, im), (t_re, t_im)) = self.transforms(f);
+                let power = re * re + im * im;
+                if power < 1e-12 {
+                    f64::NAN
+                } else {
+                    (t_re * re + t_im * im) / power
+                }
+            })
+            .collect()
+    }
+
+    // 一様間隔（0 秒始まり）のサンプルから、時刻 x の値を再構成する
+    pub fn reconstruct(
+        self,
+        sample_x: &[f64],
+        sample_y: &[f64],
+        sampling_rate: f64,
+        x: &[f64],
+    ) -> Vec<f64> {
+        match self {
+            Self::Linear => reconstruct_linear(sample_x, sample_y, x),
+            Self::ZeroOrderHold => reconstruct_zero_order_hold(sample_x, sample_y, x),
+            Self::Sinc => reconstruct_sinc(sample_y, sampling_rate, x),
+            Self::WindowedSinc { taps, window } => x
+                .iter()
+                .map(|&t| windowed_sinc_at(sample_y, sampling_rate, t, taps, window))
+                .collect(),
+        }
+    }
+}
+
+// sine_snr で使うサンプル数と、両端から除くサンプル数。中央の区間だけで比べて打ち切りの誤差を避ける
+const SNR_SAMPLES: usize = 512;
+const SNR_MARGIN: usize = 128;
+
+// 比べる区間での、1サンプル間あたりの評価点数
+const SNR_STEPS_PER_SAMPLE: usize = 16;
+
+impl ReconstructionMode {
+    // 振幅 1 の正弦波（frequency）を sampling_rate でサンプリングし、この方法で再構成したときの SNR (dB)
+    // 量子化はしないので、再構成（補間）そのものの誤差だけを測る
+    pub fn sine_snr(self, frequency: f64, sampling_rate: f64) -> f64 {
+        let phase = PI / 4.0; // fs = 2f ちょうどでもサンプルが全部 0 にならないように
+        let sine = |t: f64| (2.0 * PI * frequency * t + phase).sin();
+        let sample_x: Vec<f64> = (0..SNR_SAMPLES).map(|n| n as f64 / sampling_rate).collect();
+        let sample_y: Vec<f64> = sample_x.iter().map(|&t| sine(t)).collect();
+        let x: Vec<f64> = (SNR_MARGIN * SNR_STEPS_PER_SAMPLE
+            ..(SNR_SAMPLES - SNR_MARGIN) * SNR_STEPS_PER_SAMPLE)
+            .map(|m| m as f64 / (sampling_rate * SNR_STEPS_PER_SAMPLE as f64))
+            .collect();
+        let reconstructed = self.reconstruct(&sample_x, &sample_y, sampling_rate, &x);
+        let (signal, error) =
+            x.iter()
+                .zip(&reconstructed)
+                .fold((0.0, 0.0), |(signal, error), (&t, &y)| {
+                    let expected = sine(t);
+                    (signal + expected * expected, error + (y - expected).powi(2))
+                });
+        10.0 * (signal / error.max(f64::MIN_POSITIVE)).log10()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 対称なカーネルは位相が直線的で群遅延は一定（中心に置いた実装では 0）
+    // 0次ホールドは [0, T) を保持するので、半サンプル遅れる
+    #[test]
+    fn group_delay_of_symmetric_and_hold_kernels() {
+        let frequencies = [0.05, 0.2, 0.45];
+        let modes = [
+            ReconstructionMode::Linear,
+            ReconstructionMode::WindowedSinc {
+                taps: 16,
+                window: Window::Hann,
+            },
+        ];
+        for mode in modes {
+            for delay in mode.group_delay(&frequencies) {
+                assert!(delay.abs() < 1e-6, "{:?}: {}", mode, delay);
+            }
+        }
+        for delay in ReconstructionMode::ZeroOrderHold.group_delay(&frequencies) {
+            assert!((delay - 0.5).abs() < 1e-6, "{}", delay);
+        }
+
+        // 0次ホールドの振幅特性は |sinc(f)|
+        let droop = ReconstructionMode::ZeroOrderHold.magnitude_response(&[0.5])[0];
+        assert!((droop - 2.0 / PI).abs() < 1e-3, "{}", droop);
+    }
+
+    // 直線補間の誤差は (f/fs)^2 に比例するので、オーバーサンプリング比を 2 倍にすると SNR は約 12 dB 上がる
+    // 帯域制限された sinc 補間は、オーバーサンプリングの小さいうちは直線補間よりずっと誤差が小さい
+    #[test]
+    fn linear_interpolation_improves_with_oversampling() {
+        let snr = |mode: ReconstructionMode, ratio: f64| mode.sine_snr(1.0, 2.0 * ratio);
+        let (coarse, fine) = (
+            snr(ReconstructionMode::Linear, 4.0),
+            snr(ReconstructionMode::Linear, 8.0),
+        );
+        assert!((fine - coarse - 12.0).abs() < 0.5, "{} -> {}", coarse, fine);
+        assert!(snr(ReconstructionMode::Sinc, 4.0) > fine);
+    }
+
+    // 有限長の窓付き sinc は taps を増やすほど誤差が減り、16 taps でサンプル列全体を使う sinc に並ぶ
+    // （こちらも有限長のサンプル列で打ち切られるので、窓で滑らかに打ち切る方が先に誤差が下がる）
+    #[test]
+    fn windowed_sinc_approaches_sinc_with_more_taps() {
+        let snr = |taps| {
+            ReconstructionMode::WindowedSinc {
+                taps,
+                window: Window::Hann,
+            }
+            .sine_snr(1.0, 8.0)
+        };
+        let snrs: Vec<f64> = [4, 8, 16, 32].into_iter().map(snr).collect();
+        assert!(
+            snrs.windows(2).all(|pair| pair[1] > pair[0] + 10.0),
+            "{:?}",
+            snrs
+        );
+        let sinc = ReconstructionMode::Sinc.sine_snr(1.0, 8.0);
+        assert!(snrs[2] > sinc - 3.0, "{:?} vs {}", snrs, sinc);
+    }
+
+    // taps を増やすほど、サンプル列全体を使う sinc 補間との差が小さくなる
+    // 両端は打ち切りの誤差が大きいので中央の区間で比べる。32 taps あたりで sinc 自身の打ち切り誤差に並ぶ
+    #[test]
+    fn windowed_sinc_converges_to_sinc() {
+        let (sampling_rate, oversample) = (8.0, 4);
+        let samples: Vec<f64> = (0..256)
+            .map(|n| (2.0 * PI * n as f64 / sampling_rate + 0.3).sin())
+            .collect();
+        let rms_difference = |taps| {
+            let (t, y) =
+                reconstruct_windowed_sinc(&samples, sampling_rate, oversample, taps, Window::Hann);
+            assert_eq!(t.len(), samples.len() * oversample);
+            assert_eq!(t[oversample], 1.0 / sampling_rate);
+            let ideal = reconstruct_sinc(&samples, sampling_rate, &t);
+            let middle = t.len() / 4..3 * t.len() / 4;
+            let power: f64 = middle.clone().map(|i| (y[i] - ideal[i]).powi(2)).sum();
+            (power / middle.len() as f64).sqrt()
+        };
+        let differences: Vec<f64> = [4, 8, 16, 32].into_iter().map(rms_difference).collect();
+        assert!(
+            differences[..3]
+                .windows(2)
+                .all(|pair| pair[1] < pair[0] / 3.0),
+            "{:?}",
+            differences
+        );
+        assert!(differences[3] <= differences[2], "{:?}", differences);
+    }
+}
@@ -0,0 +1,1376 @@
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use std::f64::consts::PI;
+
+use crate::config::MAX_BIT_DEPTH;
+use crate::profile::{Stage, Timer};
+
+// 減衰エンベロープを量子化の前に掛けるか、表示だけに使うか
+//
+// - Quantized: 減衰した信号をそのまま量子化する（実際のADCと同じ）。
+//   振幅が小さくなる後半ほど使える量子化レベルが減り、SQNRは時間とともに悪化する。
+// - DisplayOnly: 減衰していない定常な正弦波を量子化し、その結果にエンベロープを掛けて表示する。
+//   全区間でフルスケールのレベルを使うので、SQNRは定常信号のもの（約 6.02N + 1.76 dB）になる。
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum EnvelopeMode {
+    #[default]
+    Quantized,
+    DisplayOnly,
+}
+
+impl EnvelopeMode {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "quantized" => Some(Self::Quantized),
+            "display-only" => Some(Self::DisplayOnly),
+            _ => None,
+        }
+    }
+}
+
+// 入力信号の波形
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Waveform {
+    #[default]
+    Sine,
+    Square,            // 素朴な矩形波。帯域が無限なのでナイキストを超えた高調波が折り返す
+    BandLimitedSquare, // ナイキスト未満（または harmonics 項）の奇数次高調波だけをフーリエ合成した矩形波
+}
+
+impl Waveform {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "sine" => Some(Self::Sine),
+            "square" => Some(Self::Square),
+            "band-limited-square" => Some(Self::BandLimitedSquare),
+            _ => None,
+        }
+    }
+}
+
+// サンプル値の量子化方式
+//
+// - Linear: ±1 をフルスケールとして bit_depth ビットで丸める。+1.0 もそのまま表せる理想的な量子化器。
+// - FixedPoint: 2の補数の Qm.n 固定小数点（m は符号ビットを含む整数部, n は小数部のビット数）。
+//   表せる範囲は -2^(m-1) 〜 2^(m-1) - 2^-n で、Q1.15 では +1.0 がちょうど範囲外になる。
+//   範囲を超えた値は saturate なら端の値に張り付き、そうでなければ反対側に回り込む。
+// - Custom: 昇順に並べた任意の量子化レベル（±1 がフルスケール）。各サンプルを最も近いレベルに丸める。
+//   非一様な量子化器（Lloyd-Max など）を Linear と同じ SQNR で比べるためのもの。
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum Quantizer {
+    #[default]
+    Linear,
+    FixedPoint {
+        int_bits: u32,
+        frac_bits: u32,
+        saturate: bool,
+    },
+    Custom(Vec<f64>),
+}
+
+impl Quantizer {
+    // "linear" または "Q1.15" のような Qm.n 表記（大文字小文字は問わない）
+    // 語長 m + n は MAX_BIT_DEPTH まで（それより長いと 2^n などが溢れる）
+    pub fn from_name(name: &str, saturate: bool) -> Option<Self> {
+        if name == "linear" {
+            return Some(Self::Linear);
+        }
+        let format = name.strip_prefix(['Q', 'q'])?;
+        let (int_bits, frac_bits) = format.split_once('.')?;
+        let int_bits: u32 = int_bits.parse().ok()?;
+        let frac_bits: u32 = frac_bits.parse().ok()?;
+        if int_bits == 0 {
+            return None; // 符号ビットの分が必要
+        }
+        if int_bits.checked_add(frac_bits)? > MAX_BIT_DEPTH {
+            return None;
+        }
+        Some(Self::FixedPoint {
+            int_bits,
+            frac_bits,
+            saturate,
+        })
+    }
+
+    // タイトルに添える説明。Linear ではビット数だけで十分なので None
+    pub fn description(&self) -> Option<String> {
+        match self {
+            Self::Linear => None,
+            Self::Custom(levels) => Some(format!("custom, {} levels", levels.len())),
+            Self::FixedPoint {
+                int_bits,
+                frac_bits,
+                saturate,
+            } => Some(format!(
+                "Q{}.{}, {}",
+                int_bits,
+                frac_bits,
+                if *saturate { "saturating" } else { "wrapping" }
+            )),
+        }
+    }
+
+    // 量子化ステップ（1 LSB）の大きさ。Custom では段の幅がそろわないので平均の幅
+    pub fn lsb(&self, bit_depth: u32) -> f64 {
+        match self {
+            Self::Linear => 2.0 * half_lsb(bit_depth),
+            Self::FixedPoint { frac_bits, .. } => 1.0 / 2u64.pow(*frac_bits) as f64,
+            Self::Custom(levels) => match (levels.first(), levels.last()) {
+                (Some(low), Some(high)) if levels.len() > 1 => {
+                    (high - low) / (levels.len() - 1) as f64
+                }
+                _ => 0.0,
+            },
+        }
+    }
+
+    pub fn quantize(&self, value: f64, bit_depth: u32) -> f64 {
+        match self {
+            Self::Linear => quantize(value, bit_depth),
+            Self::Custom(levels) => levels.get(nearest_level(levels, value)).map_or(0.0, |&l| l),
+            Self::FixedPoint {
+                int_bits,
+                frac_bits,
+                saturate,
+            } => {
+                let scale = 2u64.pow(*frac_bits) as f64;
+                let word = 2i64.pow(int_bits + frac_bits);
+                let (min, max) = (-word / 2, word / 2 - 1);
+                let level = (value * scale).round() as i64;
+                let level = if *saturate {
+                    level.clamp(min, max)
+                } else {
+                    (level - min).rem_euclid(word) + min
+                };
+                level as f64 / scale
+            }
+        }
+    }
+
+    // 量子化後に取りうる値（±1 をフルスケールとして昇順）
+    // Linear は ±1 の両端を含む 2^bit_depth + 1 個、Qm.n は語長 m+n ビットで表せるすべての値
+    pub fn levels(&self, bit_depth: u32) -> Vec<f64> {
+        match self {
+            Self::Linear => {
+                let half = 2i64.pow(bit_depth - 1);
+                (-half..=half).map(|k| k as f64 / half as f64).collect()
+            }
+            Self::FixedPoint {
+                int_bits,
+                frac_bits,
+                ..
+            } => {
+                let scale = 2u64.pow(*frac_bits) as f64;
+                let word = 2i64.pow(int_bits + frac_bits);
+                (-word / 2..word / 2).map(|l| l as f64 / scale).collect()
+            }
+            Self::Custom(levels) => levels.clone(),
+        }
+    }
+
+    // 量子化後の値が何番目の量子化レベルか（0 が無音）
+    // Custom では 0 に最も近いレベルを 0 番として数える
+    pub fn level(&self, value: f64, bit_depth: u32) -> i64 {
+        match self {
+            Self::Linear => quantization_level(value, bit_depth),
+            Self::FixedPoint { .. } => (value / self.lsb(bit_depth)).round() as i64,
+            Self::Custom(levels) => {
+                nearest_level(levels, value) as i64 - nearest_level(levels, 0.0) as i64
+            }
+        }
+    }
+}
+
+// count 個のレベルの番号を表せる最小のビット数（1 以上）
+pub fn code_bits(count: usize) -> u32 {
+    count.next_power_of_two().trailing_zeros().max(1)
+}
+
+// Lloyd-Max 法で、samples の振幅分布に対して平均二乗誤差が最小になる levels 個の量子化レベルを求める
+// 判定しきい値を隣り合うレベルの中点に置き、各レベルを自分に丸められるサンプルの平均に移す、を収束するまで繰り返す
+// 初期値はサンプルの最小値〜最大値の等間隔。サンプルが1つも入らないレベルはそのまま残す
+pub fn lloyd_max(samples: &[f64], levels: usize) -> Vec<f64> {
+    let (low, high) = samples
+        .iter()
+        .fold((f64::INFINITY, f64::NEG_INFINITY), |(low, high), &s| {
+            (low.min(s), high.max(s))
+        });
+    if levels == 0 || samples.is_empty() {
+        return Vec::new();
+    }
+    let mut table: Vec<f64> = (0..levels)
+        .map(|k| match levels {
+            1 => (low + high) / 2.0,
+            _ => low + (high - low) * k as f64 / (levels - 1) as f64,
+        })
+        .collect();
+    for _ in 0..LLOYD_MAX_ITERATIONS {
+        let mut sums = vec![(0.0, 0usize); levels];
+        for &sample in samples {
+            let cell = &mut sums[nearest_level(&table, sample)];
+            cell.0 += sample;
+            cell.1 += 1;
+        }
+        let next: Vec<f64> = table
+            .iter()
+            .zip(&sums)
+            .map(|(&level, &(sum, count))| match count {
+                0 => level,
+                _ => sum / count as f64,
+            })
+            .collect();
+        let moved = next
+            .iter()
+            .zip(&table)
+            .fold(0.0, |moved: f64, (a, b)| moved.max((a - b).abs()));
+        table = next;
+        if moved < 1e-12 {
+            break;
+        }
+    }
+    table
+}
+
+// lloyd_max の反復回数の上限
+const LLOYD_MAX_ITERATIONS: usize = 500;
+
+// 昇順のレベルのうち value に最も近いものの番号（空なら 0）
+fn nearest_level(levels: &[f64], value: f64) -> usize {
+    let upper = levels.partition_point(|&level| level < value);
+    match (upper.checked_sub(1), levels.get(upper)) {
+        (Some(lower), Some(&above)) if value - levels[lower] <= above - value => lower,
+        (Some(lower), None) => lower,
+        _ => upper.min(levels.len().saturating_sub(1)),
+    }
+}
+
+// 量子化の直前に加えるディザ
+// 誤差を信号と無相関にして、高調波状の量子化歪みを平坦な雑音に変える
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Dither {
+    #[default]
+    None,
+    Rectangular, // ±½ LSB の一様分布（RPDF）
+    Triangular,  // 一様分布2つの和で ±1 LSB の三角分布（TPDF）。誤差の分散が信号によらなくなる
+}
+
+impl Dither {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "none" => Some(Self::None),
+            "rpdf" => Some(Self::Rectangular),
+            "tpdf" => Some(Self::Triangular),
+            _ => None,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::None => "no dither",
+            Self::Rectangular => "RPDF dither",
+            Self::Triangular => "TPDF dither",
+        }
+    }
+
+    // 1サンプル分のディザ。lsb は量子化ステップの大きさ
+    pub fn sample<R: Rng>(self, rng: &mut R, lsb: f64) -> f64 {
+        match self {
+            Self::None => 0.0,
+            Self::Rectangular => lsb * (rng.gen::<f64>() - 0.5),
+            Self::Triangular => lsb * (rng.gen::<f64>() - rng.gen::<f64>()),
+        }
+    }
+}
+
+// 量子化器とディザ（と誤差フィードバック）の組み合わせ。A/B 比較で "linear+tpdf" や "Q1.15+shaped" のように指定する
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct QuantizerConfig {
+    pub quantizer: Quantizer,
+    pub dither: Dither,
+    pub noise_shaping: bool,
+}
+
+impl QuantizerConfig {
+    // "<量子化器>[+<ディザ>][+shaped]"。固定小数点は飽和させる
+    pub fn from_name(name: &str) -> Option<Self> {
+        let mut parts = name.split('+');
+        let quantizer = Quantizer::from_name(parts.next()?, true)?;
+        let (mut dither, mut noise_shaping) = (Dither::None, false);
+        for part in parts {
+            match part {
+                "shaped" if !noise_shaping => noise_shaping = true,
+                _ if dither == Dither::None && !noise_shaping => dither = Dither::from_name(part)?,
+                _ => return None,
+            }
+        }
+        Some(Self {
+            quantizer,
+            dither,
+            noise_shaping,
+        })
+    }
+
+    pub fn label(&self, bit_depth: u32) -> String {
+        let quantizer = self
+            .quantizer
+            .description()
+            .unwrap_or_else(|| format!("{}-bit linear", bit_depth));
+        let label = format!("{}, {}", quantizer, self.dither.label());
+        if self.noise_shaping {
+            format!("{}, error feedback", label)
+        } else {
+            label
+        }
+    }
+
+    // 信号の量子化器・ディザ・誤差フィードバックを置き換える。Linear のときは元のビット数を保つ
+    pub fn apply(&self, params: &SignalParams) -> SignalParams {
+        params
+            .clone()
+            .with_quantizer(self.quantizer.clone())
+            .with_dither(self.dither)
+            .with_noise_shaping(self.noise_shaping)
+    }
+}
+
+// 波形のフーリエ級数（sin 項）の係数を 1〜n_harmonics 次まで返す
+// 戻り値は（周波数, 振幅）で、周波数は基本波に対する倍率（実際の周波数は signal_freq を掛ける）
+// 帯域制限した矩形波は元の矩形波と同じ係数で、どこで打ち切るかは fs で決まる
+pub fn fourier_coefficients(waveform: Waveform, n_harmonics: usize) -> Vec<(f64, f64)> {
+    (1..=n_harmonics)
+        .map(|k| {
+            let amplitude = match waveform {
+                Waveform::Sine => {
+                    if k == 1 {
+                        1.0
+                    } else {
+                        0.0
+                    }
+                }
+                // 奇数次のみ 4/(πk)
+                Waveform::Square | Waveform::BandLimitedSquare => {
+                    if k % 2 == 1 {
+                        4.0 / (PI * k as f64)
+                    } else {
+                        0.0
+                    }
+                }
+            };
+            (k as f64, amplitude)
+        })
+        .collect()
+}
+
+#[derive(Clone)]
+pub struct SignalParams {
+    pub name: String,
+    pub signal_freq: f64,            // 入力信号の周波数 (Hz)
+    pub sampling_rate: i64,          // サンプリング周波数 (Hz)
+    pub bit_depth: u32,              // 量子化ビット数
+    pub nyquist_ratio: f64,          // ナイキスト周波数との比率
+    pub envelope_mode: EnvelopeMode, // 減衰エンベロープの適用方法
+    pub phase: f64,                  // 初期位相 (rad)
+    pub waveform: Waveform,          // 入力信号の波形
+    pub color: Option<String>,       // サンプル点の色（None ならテーマのパレットから選ぶ）
+    pub quantizer: Quantizer,        // 量子化方式
+    pub dither: Dither,              // 量子化前に加えるディザ
+    pub noise_shaping: bool,         // 量子化誤差を次のサンプルに持ち越す（誤差拡散）
+    pub jitter: f64,                 // クロックジッタの標準偏差 (s)。0 なら理想的なクロック
+    pub input_noise: f64,            // サンプリング前に加える雑音の標準偏差（フルスケール比）
+    pub seed: u64,                   // ジッタ・ディザの乱数シード
+    pub duration: f64,               // 表示・サンプリングする時間の長さ (s)
+    pub decay_rate: f64,             // エンベロープの減衰係数 (1/s)。0 なら一定振幅
+    pub amplitude: f64,              // 信号のピーク振幅（エンベロープを掛ける前）
+    pub full_scale: f64,             // ADC のフルスケール。量子化レベルは ±full_scale に並ぶ
+    pub harmonics: Option<usize>,    // 帯域制限波形で合成する高調波の数（None で自動）
+    pub rms_window: Option<f64>,     // RMS を測る窓の長さ (s)。None なら信号全体
+    pub dc_offset: f64,              // 全サンプルに足す直流成分
+}
+
+impl SignalParams {
+    pub fn new(name: &str, signal_freq: f64, sampling_rate: i64, bit_depth: u32) -> Self {
+        let nyquist_ratio = (2.0 * signal_freq) / (sampling_rate as f64);
+        Self {
+            name: name.to_string(),
+            signal_freq,
+            sampling_rate,
+            bit_depth,
+            nyquist_ratio,
+            envelope_mode: EnvelopeMode::default(),
+            phase: 0.0,
+            waveform: Waveform::default(),
+            color: None,
+            quantizer: Quantizer::default(),
+            dither: Dither::default(),
+            noise_shaping: false,
+            jitter: 0.0,
+            input_noise: 0.0,
+            seed: 0,
+            duration: TIME_RANGE,
+            decay_rate: DECAY_RATE,
+            amplitude: 1.0,
+            full_scale: 1.0,
+            harmonics: None,
+            rms_window: None,
+            dc_offset: 0.0,
+        }
+    }
+
+    // 信号周波数がちょうどナイキスト周波数（fs/2）か
+    // このときサンプルは半周期ごとに同じ位相を拾うため、結果は初期位相だけで決まる
+    pub fn is_at_nyquist(&self) -> bool {
+        (self.nyquist_ratio - 1.0).abs() < 1e-9
+    }
+
+    // 連続信号の周期 (s)
+    pub fn period(&self) -> f64 {
+        1.0 / self.signal_freq
+    }
+
+    // 入力信号の線スペクトル（周波数 Hz, 振幅）。振幅 0 の次数は含めない
+    // 帯域制限した矩形波は合成する高調波（synthesized_harmonics）だけを含める
+    pub fn line_spectrum(&self, n_harmonics: usize) -> Vec<(f64, f64)> {
+        let highest = self.highest_synthesized_order();
+        fourier_coefficients(self.waveform, n_harmonics)
+            .into_iter()
+            .filter(|&(k, amplitude)| {
+                amplitude != 0.0 && highest.is_none_or(|highest| k <= highest)
+            })
+            .map(|(k, amplitude)| (k * self.signal_freq, amplitude))
+            .collect()
+    }
+
+    // 帯域制限した矩形波で合成する最高次数（それ以外の波形は None で、すべての次数を含む）
+    // synthesized_harmonics は奇数次を先頭から連続して取るので、次数の上限だけで判定できる
+    pub fn highest_synthesized_order(&self) -> Option<f64> {
+        match self.waveform {
+            Waveform::BandLimitedSquare => {
+                Some(self.synthesized_harmonics().last().map_or(0.0, |&(k, _)| k))
+            }
+            Waveform::Sine | Waveform::Square => None,
+        }
+    }
+
+    // 帯域制限した矩形波で足し合わせる項（次数, 振幅）。振幅 0 の偶数次は含めない
+    // harmonics が None ならナイキスト未満のすべての奇数次、Some(n) ならナイキストによらず先頭の n 項
+    // 項数を減らすと不連続点の前後でリンギング（ギブス現象）が目立つ
+    pub fn synthesized_harmonics(&self) -> Vec<(f64, f64)> {
+        let nyquist = self.sampling_rate as f64 / 2.0;
+        let odd = |count: usize| -> Vec<(f64, f64)> {
+            fourier_coefficients(Waveform::Square, 2 * count)
+                .into_iter()
+                .filter(|&(_, amplitude)| amplitude != 0.0)
+                .collect()
+        };
+        match self.harmonics {
+            Some(count) => odd(count),
+            None => odd((nyquist / self.signal_freq).ceil() as usize)
+                .into_iter()
+                .take_while(|(k, _)| k * self.signal_freq < nyquist)
+                .collect(),
+        }
+    }
+
+    // 1周期に入るサンプル数（sampling_rate / signal_freq）。2 を下回るとナイキスト条件を満たさない
+    // ナイキスト比の逆数の2倍だが、「1周期に何点取れているか」の方が直感的に分かりやすい
+    pub fn samples_per_cycle(&self) -> f64 {
+        self.sampling_rate as f64 * self.period()
+    }
+
+    // サンプル列が何点ごとに繰り返すか（f/fs を既約分数 p/q にしたときの q）
+    // 周波数は 1mHz 単位の有理数として扱い、それで表せなければ None
+    // 繰り返すサンプル列はエイリアスが静止して見える原因になる
+    pub fn sample_period(&self) -> Option<u64> {
+        const RESOLUTION: f64 = 1000.0;
+        let scaled = self.signal_freq.abs() * RESOLUTION;
+        if (scaled - scaled.round()).abs() > 1e-6 || self.sampling_rate <= 0 {
+            return None;
+        }
+        let numerator = scaled.round() as u64;
+        let denominator = self.sampling_rate as u64 * RESOLUTION as u64;
+        Some(denominator / gcd(numerator, denominator))
+    }
+
+    // 信号周波数を変える（ナイキスト比も計算し直す）
+    pub fn with_signal_freq(mut self, signal_freq: f64) -> Self {
+        self.signal_freq = signal_freq;
+        self.nyquist_ratio = (2.0 * signal_freq) / (self.sampling_rate as f64);
+        self
+    }
+
+    // サンプリング周波数を変える（ナイキスト比も計算し直す）
+    pub fn with_sampling_rate(mut self, sampling_rate: i64) -> Self {
+        self.sampling_rate = sampling_rate;
+        self.nyquist_ratio = (2.0 * self.signal_freq) / (sampling_rate as f64);
+        self
+    }
+
+    // 高い周波数の信号では既定の TIME_RANGE だと点が多すぎるので短くする
+    pub fn with_duration(mut self, duration: f64) -> Self {
+        self.duration = duration;
+        self
+    }
+
+    // 校正用の基準音など、減衰させない信号では 0 にする
+    pub fn with_decay_rate(mut self, decay_rate: f64) -> Self {
+        self.decay_rate = decay_rate;
+        self
+    }
+
+    // 信号のピーク振幅。full_scale より小さいと使える量子化レベルが減り、SQNR はその分下がる
+    pub fn with_amplitude(mut self, amplitude: f64) -> Self {
+        self.amplitude = amplitude;
+        self
+    }
+
+    // RMS・クレストファクタを信号全体ではなく window 秒ごとの区間で測り、最も大きい区間の値を使う
+    // 減衰する信号では RMS が時間とともに下がるので、レベルメーターの最大値に近い読みになる
+    pub fn with_rms_window(mut self, window: f64) -> Self {
+        self.rms_window = Some(window);
+        self
+    }
+
+    // 全サンプルに足す直流成分（信号と同じ単位）。エンベロープでは減衰せず、量子化の前に足す
+    // 直流は折り返さずにスペクトルの 0 Hz のビンに現れ、使われる量子化レベル（コード）がその分ずれる
+    pub fn with_dc_offset(mut self, dc_offset: f64) -> Self {
+        self.dc_offset = dc_offset;
+        self
+    }
+
+    // ADC のフルスケール（±full_scale を 2^bit_depth 段に分ける）。信号の振幅とは独立に決める
+    pub fn with_full_scale(mut self, full_scale: f64) -> Self {
+        self.full_scale = full_scale;
+        self
+    }
+
+    // 量子化ステップ（1 LSB）の大きさ。信号と同じ単位で、フルスケールに比例する
+    pub fn lsb(&self) -> f64 {
+        self.quantizer.lsb(self.bit_depth) * self.full_scale
+    }
+
+    // 信号の振幅の dBFS（20*log10(amplitude / full_scale)）。0 dBFS でフルスケールを使い切る
+    pub fn level_dbfs(&self) -> f64 {
+        20.0 * (self.amplitude / self.full_scale).log10()
+    }
+
+    // 時刻 t における指数減衰エンベロープ
+    pub fn envelope(&self, t: f64) -> f64 {
+        (-self.decay_rate * t).exp()
+    }
+
+    // duration の間に取るサンプル数
+    pub fn num_samples(&self) -> usize {
+        (self.duration * self.sampling_rate as f64) as usize
+    }
+
+    pub fn with_phase(mut self, phase: f64) -> Self {
+        self.phase = phase;
+        self
+    }
+
+    // 帯域制限波形で合成する高調波の数を固定する（基本波を1項目として数える）
+    pub fn with_harmonics(mut self, harmonics: usize) -> Self {
+        self.harmonics = Some(harmonics);
+        self
+    }
+
+    pub fn with_waveform(mut self, waveform: Waveform) -> Self {
+        self.waveform = waveform;
+        self
+    }
+
+    pub fn with_dither(mut self, dither: Dither) -> Self {
+        self.dither = dither;
+        self
+    }
+
+    // 量子化誤差を次のサンプルに持ち越して丸める（誤差拡散）
+    // 出力の誤差は e[n] - e[n-1] になり、オーバーサンプリングなしで雑音を高域へ押しやる
+    pub fn with_noise_shaping(mut self, noise_shaping: bool) -> Self {
+        self.noise_shaping = noise_shaping;
+        self
+    }
+
+    // 各サンプル時刻を標準偏差 std_dev 秒のガウス雑音でずらす（クロックジッタ）
+    pub fn with_jitter(mut self, std_dev: f64) -> Self {
+        self.jitter = std_dev;
+        self
+    }
+
+    // サンプリング前のアナログ信号に標準偏差 std_dev のガウス雑音を加える（雑音のあるフロントエンド）
+    // 量子化器の直前で加えるディザと違い、量子化前のサンプル値そのものが揺らぐ
+    pub fn with_input_noise(mut self, std_dev: f64) -> Self {
+        self.input_noise = std_dev;
+        self
+    }
+
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    pub fn with_color(mut self, color: &str) -> Self {
+        self.color = Some(color.to_string());
+        self
+    }
+
+    // 固定小数点では語長（整数部 + 小数部）を bit_depth にそろえる
+    // Custom ではレベルの番号を表せるだけのビット数（コードの幅）にする
+    pub fn with_quantizer(mut self, quantizer: Quantizer) -> Self {
+        match &quantizer {
+            Quantizer::Linear => {}
+            Quantizer::FixedPoint {
+                int_bits,
+                frac_bits,
+                ..
+            } => self.bit_depth = int_bits + frac_bits,
+            Quantizer::Custom(levels) => self.bit_depth = code_bits(levels.len()),
+        }
+        self.quantizer = quantizer;
+        self
+    }
+
+    // 量子化ビット数を変える。固定小数点では整数部を保ったまま小数部のビット数で調整する
+    // Custom はレベルの表でビット数が決まるので変えない
+    pub fn with_bit_depth(mut self, bit_depth: u32) -> Self {
+        match &mut self.quantizer {
+            Quantizer::Linear => self.bit_depth = bit_depth,
+            Quantizer::Custom(_) => {}
+            Quantizer::FixedPoint {
+                int_bits,
+                frac_bits,
+                ..
+            } => {
+                *frac_bits = bit_depth.saturating_sub(*int_bits);
+                self.bit_depth = *int_bits + *frac_bits;
+            }
+        }
+        self
+    }
+
+    pub fn with_envelope_mode(mut self, envelope_mode: EnvelopeMode) -> Self {
+        self.envelope_mode = envelope_mode;
+        self
+    }
+}
+
+fn gcd(a: u64, b: u64) -> u64 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+// ±1 をフルスケールとして bit_depth ビットで量子化
+pub fn quantize(value: f64, bit_depth: u32) -> f64 {
+    let amplitude_levels = 2u32.pow(bit_depth) as f64;
+    (value * amplitude_levels / 2.0).round() / (amplitude_levels / 2.0)
+}
+
+// 量子化誤差の上限（±½ LSB）。フルスケール ±1 を 2^bit_depth 段に分けた1段の半分
+pub fn half_lsb(bit_depth: u32) -> f64 {
+    1.0 / 2u32.pow(bit_depth) as f64
+}
+
+// 量子化後の値が何番目の量子化レベルか（0 が無音、±2^(bit_depth-1) がフルスケール）
+pub fn quantization_level(value: f64, bit_depth: u32) -> i64 {
+    let amplitude_levels = 2u32.pow(bit_depth) as f64;
+    (value * amplitude_levels / 2.0).round() as i64
+}
+
+// 生成した信号一式
+pub struct SignalData {
+    pub continuous_x: Vec<f64>, // 連続信号の時刻
+    pub continuous_y: Vec<f64>, // 連続信号の値
+    pub sample_x: Vec<f64>,     // サンプリング時刻
+    pub sample_y: Vec<f64>,     // 量子化後のサンプル値
+    pub ideal_y: Vec<f64>, // 量子化前のサンプル値（ジッタがあればずれた時刻での値、入力雑音込み）
+}
+
+impl SignalData {
+    // 量子化誤差（量子化後 - 量子化前）
+    pub fn quantization_error(&self) -> Vec<f64> {
+        self.sample_y
+            .iter()
+            .zip(&self.ideal_y)
+            .map(|(q, ideal)| q - ideal)
+            .collect()
+    }
+
+    // 各サンプルの ADC 出力コード（オフセットバイナリ、0〜2^bit_depth-1）
+    // 量子化レベル 0（無音）がコード 2^(bit_depth-1) になり、+フルスケールは最大コードに丸める
+    pub fn quantization_codes(&self, params: &SignalParams) -> Vec<u32> {
+        let offset = 2i64.pow(params.bit_depth - 1);
+        let max_code = 2i64.pow(params.bit_depth) - 1;
+        self.quantization_levels(params)
+            .iter()
+            .map(|level| (level + offset).clamp(0, max_code) as u32)
+            .collect()
+    }
+
+    // 各サンプルの量子化誤差の上限（±½ LSB）
+    // DisplayOnly では量子化後にエンベロープを掛けるので、上限も同じだけ縮む
+    pub fn quantization_bounds(&self, params: &SignalParams) -> Vec<f64> {
+        let bound = params.lsb() / 2.0;
+        self.sample_x
+            .iter()
+            .map(|&t| match params.envelope_mode {
+                EnvelopeMode::Quantized => bound,
+                EnvelopeMode::DisplayOnly => bound * params.envelope(t),
+            })
+            .collect()
+    }
+
+    // 各サンプルの量子化レベル番号
+    // DisplayOnly ではエンベロープを掛ける前の定常信号を量子化しているので、その値から求める
+    pub fn quantization_levels(&self, params: &SignalParams) -> Vec<i64> {
+        self.sample_x
+            .iter()
+            .zip(&self.sample_y)
+            .map(|(&t, &y)| {
+                let value = match params.envelope_mode {
+                    EnvelopeMode::Quantized => y,
+                    EnvelopeMode::DisplayOnly => {
+                        (y - params.dc_offset) / params.envelope(t) + params.dc_offset
+                    }
+                };
+                params
+                    .quantizer
+                    .level(value / params.full_scale, params.bit_depth)
+            })
+            .collect()
+    }
+}
+
+// 点列を window 秒ごとの区間に切り分け、各区間の時刻を区間の先頭からの時刻にする（アイパターン用）
+// 区間の境目で線が途切れないよう、各区間の末尾には次の区間の最初の点も含める
+pub fn fold_into_windows(x: &[f64], y: &[f64], window: f64) -> Vec<(Vec<f64>, Vec<f64>)> {
+    let mut windows: Vec<(Vec<f64>, Vec<f64>)> = Vec::new();
+    let mut current = None;
+    for (&t, &value) in x.iter().zip(y) {
+        let index = (t / window + 1e-9).floor();
+        if current != Some(index) {
+            if let Some((last_x, last_y)) = windows.last_mut() {
+                last_x.push(t - (index - 1.0) * window);
+                last_y.push(value);
+            }
+            windows.push((Vec::new(), Vec::new()));
+            current = Some(index);
+        }
+        let (window_x, window_y) = windows.last_mut().expect("a window was just pushed");
+        window_x.push(t - index * window);
+        window_y.push(value);
+    }
+    windows
+}
+
+// 点列を高々 max_points 点に間引く（最小値・最大値による包絡線の間引き）
+// 区間ごとに最小値と最大値の点だけを時刻順に残すので、ピークは欠けない
+pub fn min_max_decimate(x: &[f64], y: &[f64], max_points: usize) -> (Vec<f64>, Vec<f64>) {
+    if y.len() <= max_points {
+        return (x.to_vec(), y.to_vec());
+    }
+    let buckets = (max_points / 2).max(1);
+    let bucket_size = y.len().div_ceil(buckets);
+    let mut indices = Vec::with_capacity(2 * buckets);
+    for start in (0..y.len()).step_by(bucket_size) {
+        let end = (start + bucket_size).min(y.len());
+        let by_value = |a: &usize, b: &usize| y[*a].total_cmp(&y[*b]);
+        let min = (start..end).min_by(by_value).expect("bucket is not empty");
+        let max = (start..end).max_by(by_value).expect("bucket is not empty");
+        indices.push(min.min(max));
+        if min != max {
+            indices.push(min.max(max));
+        }
+    }
+    indices.iter().map(|&i| (x[i], y[i])).unzip()
+}
+
+// 表示する時間の長さの既定値 (s)
+pub const TIME_RANGE: f64 = 2.0;
+
+// 連続信号の表示用パラメータ
+pub const INTERPOLATION_FACTOR: i64 = 20; // 補間の細かさ（1サンプル間を何分割するか）オーバーサンプリング率
+
+// 減衰係数（時定数）
+pub const DECAY_RATE: f64 = 0.5; // 減衰の速さを調整（大きいほど速く減衰）
+
+// 既定の減衰係数での指数減衰エンベロープ
+pub fn envelope(t: f64) -> f64 {
+    (-DECAY_RATE * t).exp()
+}
+
+// 標準正規分布に従う乱数（Box-Muller 法）
+pub fn gaussian<R: Rng>(rng: &mut R) -> f64 {
+    let u1 = 1.0 - rng.gen::<f64>(); // ln(0) を避けるため (0, 1] にする
+    let u2 = rng.gen::<f64>();
+    (-2.0 * u1.ln()).sqrt() * (2.0 * PI * u2).cos()
+}
+
+// 実際に信号を拾う時刻。ジッタがあれば公称のサンプリング時刻からずれる
+// サンプルは公称の時刻に記録されるので、ずれはそのまま振幅の誤差になる
+fn sampling_instants(params: &SignalParams, sample_x: &[f64], rng: &mut StdRng) -> Vec<f64> {
+    if params.jitter == 0.0 {
+        return sample_x.to_vec();
+    }
+    sample_x
+        .iter()
+        .map(|&t| t + params.jitter * gaussian(rng))
+        .collect()
+}
+
+// サンプリング前に加わる入力雑音（標準偏差はフルスケール比）。雑音がなければ乱数を消費しない
+fn input_noise(params: &SignalParams, rng: &mut StdRng) -> f64 {
+    if params.input_noise == 0.0 {
+        return 0.0;
+    }
+    params.input_noise * params.full_scale * gaussian(rng)
+}
+
+// ディザを加えてから量子化する
+// 量子化器は ±1 をフルスケールとして扱うので、full_scale で割ってから丸めて戻す
+fn quantize_dithered(params: &SignalParams, value: f64, rng: &mut StdRng) -> f64 {
+    let dither = params.dither.sample(rng, params.lsb());
+    params
+        .quantizer
+        .quantize((value + dither) / params.full_scale, params.bit_depth)
+        * params.full_scale
+}
+
+// 誤差フィードバック付きの量子化。feedback は直前のサンプルの量子化誤差で、呼ぶたびに更新する
+// 飽和で誤差が大きくなっても発散しないよう、持ち越す誤差は ±1 LSB に抑える
+fn quantize_shaped(params: &SignalParams, value: f64, feedback: &mut f64, rng: &mut StdRng) -> f64 {
+    if !params.noise_shaping {
+        return quantize_dithered(params, value, rng);
+    }
+    let target = value - *feedback;
+    let quantized = quantize_dithered(params, target, rng);
+    let lsb = params.lsb();
+    *feedback = (quantized - target).clamp(-lsb, lsb);
+    quantized
+}
+
+// 量子化前の信号モデル。連続信号の表示とサンプリングの両方がこれを評価する
+// 多くの時刻で評価するときは IdealSignal を作って高調波の表を使い回す
+pub fn ideal_signal(params: &SignalParams, t: f64) -> f64 {
+    IdealSignal::new(params).value(t)
+}
+
+// ideal_signal の評価に使う、信号ごとに一度だけ作る高調波の表
+struct IdealSignal<'a> {
+    params: &'a SignalParams,
+    harmonics: Vec<(f64, f64)>, // band-limited square で足す項（synthesized_harmonics）
+}
+
+impl<'a> IdealSignal<'a> {
+    fn new(params: &'a SignalParams) -> Self {
+        let harmonics = match params.waveform {
+            Waveform::BandLimitedSquare => params.synthesized_harmonics(),
+            Waveform::Sine | Waveform::Square => Vec::new(),
+        };
+        Self { params, harmonics }
+    }
+
+    fn value(&self, t: f64) -> f64 {
+        let params = self.params;
+        let theta = 2.0 * PI * params.signal_freq * t + params.phase;
+        let value = match params.waveform {
+            Waveform::Sine => theta.sin(),
+            Waveform::Square => {
+                if theta.sin() >= 0.0 {
+                    1.0
+                } else {
+                    -1.0
+                }
+            }
+            // 矩形波のフーリエ級数のうち synthesized_harmonics の項だけを足す
+            Waveform::BandLimitedSquare => self
+                .harmonics
+                .iter()
+                .map(|(k, amplitude)| amplitude * (k * theta).sin())
+                .sum(),
+        };
+        params.amplitude * params.envelope(t) * value + params.dc_offset
+    }
+}
+
+// 連続信号をナイキスト周波数で理想低域通過したもの。サンプリングで残りうるのはこの成分だけ
+// 波形の高調波のうち fs/2 未満のものを足す（エンベロープは搬送波に比べてゆっくり変わるとみなす）
+// 帯域内の信号では元の信号と重なり、元の信号との差が折り返して別の周波数に化ける成分になる
+pub fn nyquist_limited_signal(params: &SignalParams, t: &[f64]) -> Vec<f64> {
+    let nyquist = params.sampling_rate as f64 / 2.0;
+    let harmonics = match params.waveform {
+        Waveform::BandLimitedSquare => params.synthesized_harmonics(),
+        waveform => fourier_coefficients(waveform, (nyquist / params.signal_freq).ceil() as usize),
+    };
+    let passband: Vec<(f64, f64)> = harmonics
+        .into_iter()
+        .filter(|&(k, amplitude)| amplitude != 0.0 && k * params.signal_freq < nyquist)
+        .collect();
+    t.iter()
+        .map(|&t| {
+            let theta = 2.0 * PI * params.signal_freq * t + params.phase;
+            let value: f64 = passband
+                .iter()
+                .map(|(k, amplitude)| amplitude * (k * theta).sin())
+                .sum();
+            params.amplitude * params.envelope(t) * value + params.dc_offset
+        })
+        .collect()
+}
+
+pub fn create_sine_wave(params: &SignalParams) -> SignalData {
+    create_signal_window(params, 0, params.num_samples())
+}
+
+// first 番目から count 個のサンプル（と同じ区間の連続信号）だけを生成する
+// 長い信号を区間ごとに描くときに、全体を一度に確保しないで済む
+// 乱数は区間の先頭サンプル番号で種をずらすので、first = 0 なら create_sine_wave と同じ列になる
+pub fn create_signal_window(params: &SignalParams, first: usize, count: usize) -> SignalData {
+    let _timer = Timer::start(Stage::Generation);
+    let dt = 1.0 / params.sampling_rate as f64; // サンプリング間隔
+    let (first, end) = (first as i64, (first + count) as i64);
+
+    // 理想的な連続信号（高解像度でプロット）
+    let continuous_x: Vec<f64> = (first * INTERPOLATION_FACTOR..end * INTERPOLATION_FACTOR) // 表示用のポイント
+        .map(|i| i as f64 * dt / INTERPOLATION_FACTOR as f64) // より細かい時間間隔
+        .take_while(|&t| t <= params.duration)
+        .collect();
+
+    let signal = IdealSignal::new(params);
+    let continuous_y: Vec<f64> = continuous_x.iter().map(|&t| signal.value(t)).collect();
+
+    // サンプリングと量子化
+    let sample_x: Vec<f64> = (first..end).map(|i| i as f64 * dt).collect();
+
+    // ジッタ、入力雑音、ディザの順に同じ乱数列から取り出す
+    // 誤差フィードバックは区間の先頭で 0 から始める
+    let mut rng = StdRng::seed_from_u64(params.seed.wrapping_add(first as u64));
+    let mut feedback = 0.0;
+    let (sample_y, ideal_y): (Vec<f64>, Vec<f64>) = sampling_instants(params, &sample_x, &mut rng)
+        .into_iter()
+        .map(|t| {
+            let ideal = signal.value(t) + input_noise(params, &mut rng);
+            let quantized = match params.envelope_mode {
+                EnvelopeMode::Quantized => quantize_shaped(params, ideal, &mut feedback, &mut rng),
+                // 減衰前の定常信号を量子化してからエンベロープを掛ける（直流成分は減衰させない）
+                EnvelopeMode::DisplayOnly => {
+                    let (dc, envelope) = (params.dc_offset, params.envelope(t));
+                    let steady = (ideal - dc) / envelope + dc;
+                    envelope * (quantize_shaped(params, steady, &mut feedback, &mut rng) - dc) + dc
+                }
+            };
+            (quantized, ideal)
+        })
+        .unzip();
+
+    SignalData {
+        continuous_x,
+        continuous_y,
+        sample_x,
+        sample_y,
+        ideal_y,
+    }
+}
+
+// 複素ベースバンド信号（I/Q）。I と Q をそれぞれ量子化して保持する
+pub struct IqSignal {
+    pub sample_x: Vec<f64>,
+    pub i: Vec<f64>, // 実部（同相成分）
+    pub q: Vec<f64>, // 虚部（直交成分）
+}
+
+// 減衰する複素指数 e^{j2πft} をサンプリング・量子化する
+// 実信号と違い負の周波数と区別できるので、スペクトルは折り返さずに周回する
+pub fn create_iq_signal(params: &SignalParams) -> IqSignal {
+    let _timer = Timer::start(Stage::Generation);
+    let dt = 1.0 / params.sampling_rate as f64;
+    let num_samples = params.num_samples() as i64;
+
+    let sample_x: Vec<f64> = (0..num_samples).map(|i| i as f64 * dt).collect();
+    let mut rng = StdRng::seed_from_u64(params.seed);
+    let (mut feedback_i, mut feedback_q) = (0.0, 0.0);
+    let (i, q) = sampling_instants(params, &sample_x, &mut rng)
+        .into_iter()
+        .map(|t| {
+            let decay = params.amplitude * params.envelope(t);
+            let phase = 2.0 * PI * params.signal_freq * t + params.phase;
+            let i = decay * phase.cos() + input_noise(params, &mut rng);
+            let i = quantize_shaped(params, i, &mut feedback_i, &mut rng);
+            let q = decay * phase.sin() + input_noise(params, &mut rng);
+            (i, quantize_shaped(params, q, &mut feedback_q, &mut rng))
+        })
+        .unzip();
+
+    IqSignal { sample_x, i, q }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 連続信号とサンプル値は同じモデルから生成されるので、
+    // 量子化前のサンプル値はサンプリング時刻での連続信号と一致するはず
+    #[test]
+    fn sampled_points_lie_on_continuous_curve() {
+        let cases = [
+            SignalParams::new("Severe Aliasing", 10.0, 8, 16),
+            SignalParams::new("Aliasing", 10.0, 12, 16),
+            SignalParams::new("Near Nyquist", 10.0, 24, 16),
+            SignalParams::new("Hi Resolution", 10.0, 240, 16),
+            SignalParams::new("Display Only", 10.0, 24, 4)
+                .with_envelope_mode(EnvelopeMode::DisplayOnly),
+        ];
+
+        for params in &cases {
+            let data = create_sine_wave(params);
+            assert_eq!(data.sample_x.len(), data.ideal_y.len());
+
+            for (i, (&t, &ideal)) in data.sample_x.iter().zip(&data.ideal_y).enumerate() {
+                assert!((ideal - ideal_signal(params, t)).abs() < 1e-12);
+
+                // 連続信号側の同じ時刻の点とも一致する
+                let k = i * INTERPOLATION_FACTOR as usize;
+                assert!((data.continuous_x[k] - t).abs() < 1e-12);
+                assert!(
+                    (data.continuous_y[k] - ideal).abs() < 1e-9,
+                    "{}: sample {} drifted from continuous curve",
+                    params.name,
+                    i
+                );
+            }
+        }
+    }
+
+    // ちょうどナイキストでは、位相0だと全サンプルがゼロ交差に乗って消え、
+    // 位相90°だと ±エンベロープを交互に拾う
+    #[test]
+    fn nyquist_sampling_depends_on_phase() {
+        let params = SignalParams::new("At Nyquist", 10.0, 20, 16);
+        assert!(params.is_at_nyquist());
+        assert!(!SignalParams::new("Near Nyquist", 10.0, 24, 16).is_at_nyquist());
+
+        let data = create_sine_wave(&params);
+        assert!(data.sample_y.iter().all(|y| y.abs() < 1e-3));
+
+        let data = create_sine_wave(&params.clone().with_phase(PI / 2.0));
+        for (i, (&t, &y)) in data.sample_x.iter().zip(&data.sample_y).enumerate() {
+            let expected = if i % 2 == 0 {
+                envelope(t)
+            } else {
+                -envelope(t)
+            };
+            assert!((y - expected).abs() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn sample_period_follows_reduced_ratio() {
+        // 10/8 = 5/4: 4点ごとに同じサンプル列になる
+        assert_eq!(SignalParams::new("a", 10.0, 8, 16).sample_period(), Some(4));
+        assert_eq!(
+            SignalParams::new("b", 10.0, 12, 16).sample_period(),
+            Some(6)
+        );
+        assert_eq!(
+            SignalParams::new("c", 10.0, 240, 16).sample_period(),
+            Some(24)
+        );
+        assert_eq!(SignalParams::new("d", 0.5, 8, 16).sample_period(), Some(16));
+        assert_eq!(
+            SignalParams::new("e", 2.0_f64.sqrt(), 8, 16).sample_period(),
+            None
+        );
+    }
+
+    #[test]
+    fn fixed_point_saturates_or_wraps_on_overflow() {
+        let saturating = Quantizer::from_name("Q1.15", true).unwrap();
+        let wrapping = Quantizer::from_name("q1.15", false).unwrap();
+        let max = 1.0 - 1.0 / 32768.0;
+        // +1.0 は Q1.15 の範囲外
+        assert_eq!(saturating.quantize(1.0, 16), max);
+        assert_eq!(wrapping.quantize(1.0, 16), -1.0);
+        assert_eq!(saturating.quantize(-1.0, 16), -1.0);
+        // 範囲内なら 16bit の Linear と同じステップで丸める
+        assert_eq!(
+            saturating.quantize(0.3, 16),
+            Quantizer::Linear.quantize(0.3, 16)
+        );
+        assert_eq!(Quantizer::from_name("Q0.15", true), None);
+        // 語長が MAX_BIT_DEPTH を超えるもの・u32 で溢れるものは受け付けない
+        assert!(Quantizer::from_name("Q1.30", true).is_some());
+        assert_eq!(Quantizer::from_name("Q1.70", true), None);
+        assert_eq!(Quantizer::from_name("Q4294967295.1", true), None);
+    }
+
+    #[test]
+    fn jitter_is_reproducible_per_seed() {
+        let params = SignalParams::new("Jitter", 10.0, 240, 16).with_jitter(1e-4);
+        let first = create_sine_wave(&params);
+        let again = create_sine_wave(&params);
+        let other = create_sine_wave(&params.clone().with_seed(1));
+        let ideal = create_sine_wave(&SignalParams::new("Ideal", 10.0, 240, 16));
+
+        assert_eq!(first.ideal_y, again.ideal_y);
+        assert_ne!(first.ideal_y, other.ideal_y);
+        assert_ne!(first.ideal_y, ideal.ideal_y);
+        // 公称のサンプリング時刻は変わらない
+        assert_eq!(first.sample_x, ideal.sample_x);
+    }
+
+    // 入力雑音は量子化前の値を揺らすので、量子化誤差は ±½ LSB のまま
+    #[test]
+    fn input_noise_is_added_before_quantization() {
+        let clean = SignalParams::new("Clean", 10.0, 240, 16);
+        let noisy = clean.clone().with_input_noise(0.01);
+        let (clean_data, noisy_data) = (create_sine_wave(&clean), create_sine_wave(&noisy));
+
+        let deviation: Vec<f64> = noisy_data
+            .ideal_y
+            .iter()
+            .zip(&clean_data.ideal_y)
+            .map(|(a, b)| a - b)
+            .collect();
+        let rms = (deviation.iter().map(|d| d * d).sum::<f64>() / deviation.len() as f64).sqrt();
+        assert!((rms - 0.01).abs() < 0.002, "rms {}", rms);
+
+        let half_lsb = noisy.quantizer.lsb(noisy.bit_depth) / 2.0;
+        assert!(noisy_data
+            .quantization_error()
+            .iter()
+            .all(|e| e.abs() <= half_lsb + 1e-12));
+    }
+
+    // 誤差フィードバックをかけると、量子化誤差のスペクトルが高域ほど大きくなる
+    #[test]
+    fn error_feedback_pushes_quantization_noise_upward() {
+        let band_ratio = |params: &SignalParams| {
+            let data = create_sine_wave(params);
+            let (_, mags) = crate::spectrum::spectrum(&data.quantization_error(), 480.0);
+            let power = |band: &[f64]| band.iter().map(|m| m * m).sum::<f64>();
+            let quarter = mags.len() / 4;
+            power(&mags[mags.len() - quarter..]) / power(&mags[..quarter])
+        };
+        let params = SignalParams::new("Shaped", 10.0, 480, 8).with_dither(Dither::Triangular);
+
+        assert!(band_ratio(&params) < 2.0);
+        assert!(band_ratio(&params.clone().with_noise_shaping(true)) > 10.0);
+    }
+
+    // 区間ごとに生成した信号をつなげると、全体を一度に生成したものと同じになる（乱数を使わない場合）
+    #[test]
+    fn signal_windows_concatenate_to_the_full_signal() {
+        let params = SignalParams::new("Tiled", 10.0, 240, 8);
+        let full = create_sine_wave(&params);
+        let (mut sample_y, mut continuous_y) = (Vec::new(), Vec::new());
+        for first in (0..params.num_samples()).step_by(100) {
+            let count = 100.min(params.num_samples() - first);
+            let window = create_signal_window(&params, first, count);
+            sample_y.extend(window.sample_y);
+            continuous_y.extend(window.continuous_y);
+        }
+        assert_eq!(sample_y, full.sample_y);
+        assert_eq!(continuous_y, full.continuous_y);
+    }
+
+    // フルスケールの半分の信号は半分のレベルしか使わず、SQNR が約 6dB 下がる
+    #[test]
+    fn full_scale_is_independent_of_signal_amplitude() {
+        let full = SignalParams::new("Full", 10.0, 240, 8).with_decay_rate(0.0);
+        let half = full.clone().with_amplitude(0.5);
+        let max_level = |params: &SignalParams| {
+            let data = create_sine_wave(params);
+            let levels = data.quantization_levels(params);
+            levels.iter().map(|level| level.abs()).max().unwrap()
+        };
+        assert_eq!(max_level(&full), 128);
+        assert_eq!(max_level(&half), 64);
+        assert_eq!(max_level(&half.clone().with_full_scale(0.5)), 128);
+        assert!((half.level_dbfs() + 6.02).abs() < 0.01);
+
+        let sqnr = |params: &SignalParams| {
+            let data = create_sine_wave(params);
+            crate::metrics::sqnr_db(&data.ideal_y, &data.sample_y)
+        };
+        let loss = sqnr(&full) - sqnr(&half);
+        assert!((loss - 6.0).abs() < 1.5, "loss {}", loss);
+    }
+
+    #[test]
+    fn dither_spec_parses_quantizer_and_dither() {
+        let spec = QuantizerConfig::from_name("Q1.15+tpdf").unwrap();
+        assert_eq!(spec.dither, Dither::Triangular);
+        assert_eq!(
+            spec.apply(&SignalParams::new("a", 10.0, 240, 8)).bit_depth,
+            16
+        );
+        assert_eq!(
+            QuantizerConfig::from_name("linear").unwrap().dither,
+            Dither::None
+        );
+        assert_eq!(QuantizerConfig::from_name("linear+white"), None);
+        let shaped = QuantizerConfig::from_name("linear+tpdf+shaped").unwrap();
+        assert!(shaped.noise_shaping && shaped.dither == Dither::Triangular);
+        assert_eq!(QuantizerConfig::from_name("linear+shaped+tpdf"), None);
+
+        // TPDF ディザは ±1 LSB に収まる
+        let mut rng = StdRng::seed_from_u64(0);
+        assert!((0..1000).all(|_| Dither::Triangular.sample(&mut rng, 0.5).abs() <= 0.5));
+    }
+
+    #[test]
+    fn min_max_decimate_keeps_peaks() {
+        let params = SignalParams::new("Dense", 10.0, 240, 16);
+        let data = create_sine_wave(&params);
+        let (x, y) = min_max_decimate(&data.continuous_x, &data.continuous_y, 500);
+
+        assert!(y.len() <= 500);
+        assert!(x.windows(2).all(|pair| pair[0] < pair[1]));
+        let peak = |values: &[f64]| values.iter().cloned().fold(f64::MIN, f64::max);
+        assert_eq!(peak(&y), peak(&data.continuous_y));
+    }
+
+    #[test]
+    fn fold_into_windows_overlays_periods() {
+        let x: Vec<f64> = (0..10).map(|i| i as f64 * 0.25).collect();
+        let y: Vec<f64> = (0..10).map(|i| i as f64).collect();
+        let windows = fold_into_windows(&x, &y, 1.0);
+
+        assert_eq!(windows.len(), 3);
+        // 各区間は 0 秒から始まり、次の区間の先頭の点で終わる
+        assert_eq!(windows[0].0, vec![0.0, 0.25, 0.5, 0.75, 1.0]);
+        assert_eq!(windows[0].1, vec![0.0, 1.0, 2.0, 3.0, 4.0]);
+        assert_eq!(windows[2].0, vec![0.0, 0.25]);
+        assert_eq!(windows[2].1, vec![8.0, 9.0]);
+    }
+
+    // 項数を固定すると先頭の奇数次だけを足し、少ない項では縁の行き過ぎ（ギブス現象）が残る
+    #[test]
+    fn fixed_harmonics_use_the_first_odd_terms() {
+        let params = SignalParams::new("Square", 10.0, 1000, 16)
+            .with_waveform(Waveform::BandLimitedSquare)
+            .with_harmonics(3);
+        let orders: Vec<f64> = params.synthesized_harmonics().iter().map(|t| t.0).collect();
+        assert_eq!(orders, vec![1.0, 3.0, 5.0]);
+        let peak = (0..100)
+            .map(|i| ideal_signal(&params, i as f64 / 1000.0))
+            .fold(f64::MIN, f64::max);
+        assert!(peak > 1.0, "peak {}", peak);
+
+        // 省略時はナイキスト (500 Hz) 未満の 25 項
+        let automatic = SignalParams {
+            harmonics: None,
+            ..params
+        };
+        assert_eq!(automatic.synthesized_harmonics().len(), 25);
+    }
+
+    // どの値も量子化すると levels のどれかになる
+    #[test]
+    fn quantized_values_are_among_the_levels() {
+        for quantizer in [
+            Quantizer::Linear,
+            Quantizer::from_name("Q1.3", true).unwrap(),
+            Quantizer::Custom(vec![-1.0, -0.2, 0.1, 0.7]),
+        ] {
+            let levels = quantizer.levels(3);
+            assert!(levels.windows(2).all(|pair| pair[0] < pair[1]));
+            for i in -100..=100 {
+                let quantized = quantizer.quantize(i as f64 / 100.0, 3);
+                assert!(
+                    levels.iter().any(|level| (level - quantized).abs() < 1e-12),
+                    "{:?}: {}",
+                    quantizer,
+                    quantized
+                );
+            }
+        }
+        assert_eq!(Quantizer::Linear.levels(3).len(), 9);
+    }
+
+    // 等間隔の表は同じ段の Linear と同じ値になり、非一様な表では最も近いレベルに丸める
+    #[test]
+    fn custom_levels_round_to_the_nearest_level() {
+        let uniform: Vec<f64> = (-4..=4).map(|k| k as f64 / 4.0).collect();
+        let linear = SignalParams::new("Linear", 10.0, 240, 3);
+        let custom = linear
+            .clone()
+            .with_quantizer(Quantizer::Custom(uniform.clone()));
+        assert_eq!(custom.bit_depth, 4); // 9 レベルの番号には 4 ビット要る
+        assert_eq!(
+            create_sine_wave(&custom).sample_y,
+            create_sine_wave(&linear).sample_y
+        );
+
+        let table = Quantizer::Custom(vec![-1.0, -0.25, 0.0, 0.25, 1.0]);
+        assert_eq!(table.quantize(0.6, 3), 0.25);
+        assert_eq!(table.quantize(0.7, 3), 1.0);
+        assert_eq!(table.quantize(-2.0, 3), -1.0);
+        assert_eq!(table.level(-0.3, 3), -1);
+    }
+
+    // 一様分布には一様なレベルが最適で、2 レベルならそれぞれの半分の区間の中央に収束する
+    #[test]
+    fn lloyd_max_fits_the_amplitude_distribution() {
+        let uniform: Vec<f64> = (0..10_000)
+            .map(|k| -1.0 + 2.0 * k as f64 / 9_999.0)
+            .collect();
+        let levels = lloyd_max(&uniform, 2);
+        assert!((levels[0] + 0.5).abs() < 1e-3 && (levels[1] - 0.5).abs() < 1e-3);
+
+        // 0 の近くに集まった分布では、内側のレベルの間隔が外側より狭くなる
+        let peaked: Vec<f64> = uniform.iter().map(|x| x * x * x).collect();
+        let levels = lloyd_max(&peaked, 8);
+        assert!(levels.windows(2).all(|pair| pair[0] < pair[1]));
+        assert!(levels[4] - levels[3] < levels[7] - levels[6]);
+    }
+
+    // 直流成分は量子化の前に足されて使うレベルをずらし、スペクトルの 0 Hz のビンに現れる
+    #[test]
+    fn dc_offset_shifts_the_levels_and_the_zero_bin() {
+        let base = SignalParams::new("DC", 10.0, 240, 8).with_decay_rate(0.0);
+        let shifted = base.clone().with_dc_offset(0.25);
+        let (plain, offset) = (create_sine_wave(&base), create_sine_wave(&shifted));
+        let steps = (0.25 / base.lsb()).round() as i64;
+        for (a, b) in plain
+            .quantization_levels(&base)
+            .iter()
+            .zip(offset.quantization_levels(&shifted))
+        {
+            // +フルスケール付近は飽和するので、届く範囲だけ比べる
+            if b < 127 {
+                assert_eq!(a + steps, b);
+            }
+        }
+        let (_, magnitudes) = crate::spectrum::spectrum(&offset.sample_y, 240.0);
+        assert!((magnitudes[0] - 0.25).abs() < 0.01, "{}", magnitudes[0]);
+        assert!(crate::spectrum::spectrum(&plain.sample_y, 240.0).1[0] < 0.01);
+    }
+
+    // 帯域内の正弦波はそのまま残り、帯域外の正弦波は消える。矩形波は fs/2 未満の奇数次だけになる
+    #[test]
+    fn nyquist_limit_keeps_only_the_in_band_harmonics() {
+        let t: Vec<f64> = (0..200).map(|i| i as f64 / 2000.0).collect();
+        let in_band = SignalParams::new("In Band", 10.0, 24, 16);
+        let limited = nyquist_limited_signal(&in_band, &t);
+        for (&t, limited) in t.iter().zip(&limited) {
+            assert!((ideal_signal(&in_band, t) - limited).abs() < 1e-12);
+        }
+        let above = SignalParams::new("Above", 10.0, 12, 16).with_signal_freq(8.0);
+        assert!(nyquist_limited_signal(&above, &t)
+            .iter()
+            .all(|value| value.abs() < 1e-12));
+
+        // 100 Hz の矩形波を 1 kHz でサンプリングすると 1・3 次だけが残る
+        let square = SignalParams::new("Square", 100.0, 1000, 16).with_waveform(Waveform::Square);
+        let reference = SignalParams {
+            harmonics: Some(2),
+            ..square.clone().with_waveform(Waveform::BandLimitedSquare)
+        };
+        let limited = nyquist_limited_signal(&square, &t);
+        for (&t, limited) in t.iter().zip(&limited) {
+            assert!((ideal_signal(&reference, t) - limited).abs() < 1e-9);
+        }
+    }
+}
@@ -0,0 +1,272 @@
+use plotly::{
+    common::{Fill, Line, LineShape, Marker, Mode, Title},
+    ImageFormat, Layout, Plot, Scatter,
+};
+
+use crate::cli::ExportSize;
+use crate::layout::{format_metric, generate_title, panel_title, PanelTrace};
+use crate::metrics::analyze;
+use crate::reconstruct::ReconstructionMode;
+use crate::signal::{create_sine_wave, SignalData, SignalParams};
+use crate::theme::{AxisKind, Theme};
+
+// 1つの信号だけの図。グリッドを組まず、軸1組に連続信号・サンプル点と指標入りのタイトルを描く
+pub fn single_plot(params: &SignalParams, theme: &Theme) -> Plot {
+    let data = create_sine_wave(params);
+    let metrics = analyze(params, &data);
+    let title = format!(
+        "{}<br>{}",
+        generate_title(params, &metrics),
+        format_metric("SQNR", metrics.sqnr_db, " dB")
+    );
+    panel_plot(params, data, params.name.as_str(), title, theme)
+}
+
+// 縦軸の範囲。信号（直流成分込み）とフルスケールの大きい方に余白を付け、どの区間でも同じにする
+pub fn amplitude_range(params: &SignalParams) -> Vec<f64> {
+    let peak = 1.2 * (params.amplitude + params.dc_offset.abs()).max(params.full_scale);
+    vec![-peak, peak]
+}
+
+// 長い信号を区間（タイル）に分けたうちの index 番目（0始まり、全 count 枚）の図
+// 縦軸はタイルによらず amplitude_range なので、並べて見ても振幅を比べられる
+pub fn tile_plot(
+    params: &SignalParams,
+    data: SignalData,
+    index: usize,
+    count: usize,
+    theme: &Theme,
+) -> Plot {
+    let (start, end) = match (data.sample_x.first(), data.sample_x.last()) {
+        (Some(&start), Some(&end)) => (start, end),
+        _ => (0.0, 0.0),
+    };
+    let metrics = analyze(params, &data);
+    let heading = format!(
+        "{} (tile {}/{}, {:.3}–{:.3} s)",
+        params.name,
+        index + 1,
+        count,
+        start,
+        end
+    );
+    let title = format!(
+        "{}<br>{}",
+        generate_title(params, &metrics),
+        format_metric("SQNR", metrics.sqnr_db, " dB")
+    );
+    panel_plot(params, data, &heading, title, theme)
+}
+
+// index番目のパネルに載せる連続信号（灰色の線）とサンプル点（color）の基本の2本
+// 呼び出し側はそれぞれに装飾を足してから add_to で加える
+pub struct SignalTraces {
+    pub continuous: Box<Scatter<f64, f64>>,
+    pub samples: Box<Scatter<f64, f64>>,
+}
+
+impl SignalTraces {
+    pub fn new(
+        (continuous_x, continuous_y): (Vec<f64>, Vec<f64>),
+        (sample_x, sample_y): (Vec<f64>, Vec<f64>),
+        color: &str,
+        index: usize,
+    ) -> Self {
+        Self {
+            continuous: Scatter::new(continuous_x, continuous_y)
+                .name("Original Signal")
+                .mode(Mode::Lines)
+                .line(Line::new().color("rgba(170, 170, 170, 0.5)"))
+                .on_panel(index),
+            samples: Scatter::new(sample_x, sample_y)
+                .name("Sampled & Reconstructed")
+                .mode(Mode::LinesMarkers)
+                .line(Line::new().color(color.to_string()))
+                .marker(Marker::new().size(8).color(color.to_string()).opacity(0.7))
+                .on_panel(index),
+        }
+    }
+
+    // 連続信号、between、サンプル点の順に加える。Fill::ToNextY は直前のトレースとの間を塗るので、
+    // 連続信号との差を塗る再構成のトレースは between の先頭に置く
+    pub fn add_to(self, plot: &mut Plot, between: Vec<Box<Scatter<f64, f64>>>) {
+        plot.add_trace(self.continuous);
+        for trace in between {
+            plot.add_trace(trace);
+        }
+        plot.add_trace(self.samples);
+    }
+}
+
+// index番目のパネルに連続信号とサンプル点の2本を装飾なしで載せる
+pub fn add_signal_traces(
+    plot: &mut Plot,
+    data: SignalData,
+    params: &SignalParams,
+    index: usize,
+    theme: &Theme,
+) {
+    let color = theme.signal_color(index, params);
+    SignalTraces::new(
+        (data.continuous_x, data.continuous_y),
+        (data.sample_x, data.sample_y),
+        &color,
+        index,
+    )
+    .add_to(plot, Vec::new());
+}
+
+fn panel_plot(
+    params: &SignalParams,
+    data: SignalData,
+    heading: &str,
+    title: String,
+    theme: &Theme,
+) -> Plot {
+    let mut plot = Plot::new();
+    add_signal_traces(&mut plot, data, params, 0, theme);
+
+    let mut layout = theme
+        .apply_background(Layout::new())
+        .title(Title::with_text(heading).font(theme.font(12)))
+        .x_axis(theme.styled_axis(AxisKind::Time, "Time (s)", 10, 9))
+        .y_axis(
+            theme
+                .amplitude_axis(theme.styled_axis(AxisKind::Time, "Amplitude", 10, 9))
+                .range(amplitude_range(params)),
+        )
+        .show_legend(theme.legend.show)
+        .legend(theme.legend());
+    layout.add_annotation(panel_title(title, 0, theme.font(9), theme));
+    plot.set_layout(layout);
+    plot
+}
+
+// 量子化の定番の図。アナログ入力の上に、量子化したサンプルを次のサンプルまで保持した階段を重ね、
+// 両者の差（量子化と保持による誤差）を塗る。ステップの大きさ（1 LSB）を右上に書き添える
+pub fn staircase_plot(params: &SignalParams, theme: &Theme) -> Plot {
+    let data = create_sine_wave(params);
+    let metrics = analyze(params, &data);
+    let color = theme.signal_color(0, params);
+    // 階段は連続信号と同じ時刻で評価し、同じ x の上で差を塗れるようにする
+    let staircase = ReconstructionMode::ZeroOrderHold.reconstruct(
+        &data.sample_x,
+        &data.sample_y,
+        params.sampling_rate as f64,
+        &data.continuous_x,
+    );
+
+    let mut plot = Plot::new();
+    plot.add_trace(
+        Scatter::new(data.continuous_x.clone(), data.continuous_y)
+            .name("Analog Input")
+            .mode(Mode::Lines)
+            .line(Line::new().color("rgba(120, 120, 120, 0.9)")),
+    );
+    plot.add_trace(
+        Scatter::new(data.continuous_x, staircase)
+            .name("Quantized (held)")
+            .mode(Mode::Lines)
+            .line(Line::new().color(color.clone()).shape(LineShape::Hv))
+            .fill(Fill::ToNextY) // 直前のトレース（アナログ入力）との間を塗る
+            .fill_color("rgba(214, 39, 40, 0.2)"),
+    );
+    plot.add_trace(
+        Scatter::new(data.sample_x, data.sample_y)
+            .name("Samples")
+            .mode(Mode::Markers)
+            .marker(Marker::new().size(6).color(color)),
+    );
+
+    // 16-bit の LSB は 3e-5 程度なので、小さいステップは指数表記にする
+    let lsb = params.lsb();
+    let step = if lsb >= 1e-3 {
+        format!("{:.4}", lsb)
+    } else {
+        format!("{:.2e}", lsb)
+    };
+    let title = format!(
+        "Step (1 LSB): {} ({}-bit)<br>{}",
+        step,
+        params.bit_depth,
+        format_metric("SQNR", metrics.sqnr_db, " dB")
+    );
+    let mut layout = theme
+        .apply_background(Layout::new())
+        .title(
+            Title::with_text(format!("{} — Quantization Staircase", params.name))
+                .font(theme.font(12)),
+        )
+        .x_axis(theme.styled_axis(AxisKind::Time, "Time (s)", 10, 9))
+        .y_axis(
+            theme
+                .amplitude_axis(theme.styled_axis(AxisKind::Time, "Amplitude", 10, 9))
+                .range(amplitude_range(params)),
+        )
+        .show_legend(theme.legend.show)
+        .legend(theme.legend());
+    layout.add_annotation(panel_title(title, 0, theme.font(9), theme));
+    plot.set_layout(layout);
+    plot
+}
+
+// 1信号の図を既定のテーマで path に書き出す。拡張子が .html なら HTML、それ以外は PNG
+// 比較グリッドに進む前の最小の入口で、サイズやテーマを変えたいときは single_plot を使う
+pub fn render_single(params: &SignalParams, path: &str) {
+    let plot = single_plot(params, &Theme::default());
+    if path.ends_with(".html") {
+        plot.write_html(path);
+    } else {
+        let size = ExportSize::default();
+        plot.write_image(path, ImageFormat::PNG, size.width, size.height, size.scale);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn single_plot_uses_one_pair_of_axes() {
+        let params = SignalParams::new("Aliasing", 10.0, 12, 16);
+        let json: serde_json::Value =
+            serde_json::from_str(&single_plot(&params, &Theme::default()).to_json()).unwrap();
+        assert_eq!(json["data"].as_array().unwrap().len(), 2);
+        assert!(json["layout"].get("xaxis").is_some());
+        assert!(json["layout"].get("xaxis2").is_none());
+        assert!(json["layout"].get("grid").is_none());
+    }
+
+    #[test]
+    fn zero_line_is_drawn_on_the_amplitude_axis_only() {
+        let params = SignalParams::new("Aliasing", 10.0, 12, 16);
+        let mut theme = Theme::default();
+        let plain: serde_json::Value =
+            serde_json::from_str(&single_plot(&params, &theme).to_json()).unwrap();
+        assert!(plain["layout"]["yaxis"].get("zeroline").is_none());
+
+        theme.zero_line.show = true;
+        let json: serde_json::Value =
+            serde_json::from_str(&single_plot(&params, &theme).to_json()).unwrap();
+        let y_axis = &json["layout"]["yaxis"];
+        assert_eq!(y_axis["zeroline"], true);
+        assert_eq!(y_axis["zerolinewidth"], 2);
+        assert_eq!(y_axis["zerolinecolor"], "#555555");
+        assert!(json["layout"]["xaxis"].get("zeroline").is_none());
+    }
+
+    #[test]
+    fn staircase_holds_each_sample_until_the_next() {
+        let params = SignalParams::new("Staircase", 10.0, 24, 3);
+        let json: serde_json::Value =
+            serde_json::from_str(&staircase_plot(&params, &Theme::default()).to_json()).unwrap();
+        let staircase = &json["data"][1];
+        assert_eq!(staircase["fill"], "tonexty");
+        // 保持した値は量子化済みのサンプル値だけから取られる
+        let samples: Vec<f64> = create_sine_wave(&params).sample_y;
+        for value in staircase["y"].as_array().unwrap() {
+            let value = value.as_f64().unwrap();
+            assert!(samples.iter().any(|s| (s - value).abs() < 1e-12));
+        }
+    }
+}
@@ -0,0 +1,369 @@
+use rustfft::FftPlanner;
+
+// fft_complex の結果の型。利用側が rustfft を直接の依存にしなくて済むように再公開する
+pub use rustfft::num_complex::Complex;
+
+use crate::profile::{Stage, Timer};
+
+// dB表示時の下限（振幅ゼロのビンで -inf にならないように）
+const MIN_MAGNITUDE: f64 = 1e-8;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PeakKind {
+    Fundamental, // 入力信号そのものの周波数
+    Alias,       // 折り返しによって現れた周波数
+    Other,       // 高調波・漏れなどそれ以外
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Peak {
+    pub frequency: f64,
+    pub magnitude: f64,
+    pub kind: PeakKind,
+}
+
+fn forward_fft(mut buffer: Vec<Complex<f64>>) -> Vec<Complex<f64>> {
+    let _timer = Timer::start(Stage::Spectrum);
+    if !buffer.is_empty() {
+        FftPlanner::new()
+            .plan_fft_forward(buffer.len())
+            .process(&mut buffer);
+    }
+    buffer
+}
+
+// 実信号の離散フーリエ変換 X[k] = Σ x[n] e^{-j2πkn/N}（k = 0〜N-1、正規化なし）
+// ビン k の周波数は k·fs/N で、k > N/2 は負の周波数 (k - N)·fs/N にあたる
+// spectrum（振幅）はこれを元にしている。相互相関やケプストラムなど位相が要るときに使う
+pub fn fft_complex(samples: &[f64]) -> Vec<Complex<f64>> {
+    forward_fft(samples.iter().map(|&s| Complex::new(s, 0.0)).collect())
+}
+
+// 片側振幅スペクトル（周波数, 振幅）を計算
+// 振幅はサンプル数で正規化しているので、振幅1の正弦波はピークがおよそ1になる
+pub fn spectrum(samples: &[f64], sampling_rate: f64) -> (Vec<f64>, Vec<f64>) {
+    let n = samples.len();
+    if n == 0 {
+        return (Vec::new(), Vec::new());
+    }
+
+    let buffer = fft_complex(samples);
+
+    let bins = n / 2 + 1;
+    let freqs: Vec<f64> = (0..bins)
+        .map(|k| k as f64 * sampling_rate / n as f64)
+        .collect();
+    let mags: Vec<f64> = buffer[..bins]
+        .iter()
+        .enumerate()
+        .map(|(k, c)| {
+            // DC とナイキストのビン以外は負の周波数側の分を合算して2倍
+            let scale = if k == 0 || 2 * k == n { 1.0 } else { 2.0 };
+            scale * c.norm() / n as f64
+        })
+        .collect();
+
+    (freqs, mags)
+}
+
+// 複素信号（I/Q）の両側スペクトル（-fs/2 〜 +fs/2）を計算
+// 共役対称を仮定しないので、正と負の周波数が別々のピークとして現れる
+pub fn complex_spectrum(i: &[f64], q: &[f64], sampling_rate: f64) -> (Vec<f64>, Vec<f64>) {
+    let n = i.len().min(q.len());
+    if n == 0 {
+        return (Vec::new(), Vec::new());
+    }
+
+    let buffer = forward_fft(
+        i.iter()
+            .zip(q)
+            .map(|(&re, &im)| Complex::new(re, im))
+            .collect(),
+    );
+
+    // 負の周波数側を前に持ってくる（fftshift）
+    let half = n / 2;
+    let (freqs, mags) = (0..n)
+        .map(|j| {
+            let k = (j + n - half) % n;
+            let bin = if k >= n - half {
+                k as f64 - n as f64
+            } else {
+                k as f64
+            };
+            (bin * sampling_rate / n as f64, buffer[k].norm() / n as f64)
+        })
+        .unzip();
+
+    (freqs, mags)
+}
+
+// 複素サンプリング後の見かけの周波数（-fs/2 〜 +fs/2 に周回する）
+pub fn wrapped_frequency(signal_freq: f64, sampling_rate: f64) -> f64 {
+    signal_freq - sampling_rate * (signal_freq / sampling_rate).round()
+}
+
+// window_length 点の窓にちょうど整数周期が入る周波数のうち、target に最も近いもの
+// 周波数がビンの中心に乗るのでスペクトル漏れが出ない。0Hz にはせず最低でも1周期にする
+pub fn coherent_frequency(target: f64, window_length: usize, sampling_rate: f64) -> f64 {
+    let resolution = sampling_rate / window_length as f64;
+    (target / resolution).round().max(1.0) * resolution
+}
+
+// サンプリングした信号のスペクトルは fs ごとに複製（レプリカ）が並ぶ
+// 片側の線スペクトル（周波数, 振幅）から、k·fs を中心とする両側スペクトルを作る。振幅は正負に半分ずつ
+pub fn spectral_replica(lines: &[(f64, f64)], sampling_rate: f64, k: i32) -> Vec<(f64, f64)> {
+    let center = k as f64 * sampling_rate;
+    lines
+        .iter()
+        .flat_map(|&(frequency, amplitude)| {
+            [
+                (center - frequency, amplitude / 2.0),
+                (center + frequency, amplitude / 2.0),
+            ]
+        })
+        .collect()
+}
+
+// -order〜order 番目のレプリカ（帯域 -bandwidth〜bandwidth）どうしが重なる区間 (開始, 終了)
+// bandwidth が fs/2 を超えると隣と重なり、そこに落ちた成分はエイリアスになる。重なる区間はまとめて返す
+pub fn replica_overlaps(bandwidth: f64, sampling_rate: f64, order: i32) -> Vec<(f64, f64)> {
+    let mut overlaps: Vec<(f64, f64)> = Vec::new();
+    if bandwidth <= sampling_rate / 2.0 {
+        return overlaps;
+    }
+    for k in -order..order {
+        let start = (k + 1) as f64 * sampling_rate - bandwidth;
+        let end = k as f64 * sampling_rate + bandwidth;
+        match overlaps.last_mut() {
+            Some(last) if start <= last.1 => last.1 = last.1.max(end),
+            _ => overlaps.push((start, end)),
+        }
+    }
+    overlaps
+}
+
+// 折り返し図（横軸 f/fs、縦軸 見かけの周波数/fs）の折れ線の頂点。0 から span·fs まで
+// fs/2 の倍数ごとに 0 と 1/2 の間を往復する三角波になり、aliased_frequency をそのまま描いたものになる
+pub fn folding_diagram(span: f64) -> Vec<(f64, f64)> {
+    let folds = (2.0 * span).ceil() as usize;
+    (0..=folds)
+        .map(|k| (k as f64 / 2.0, if k % 2 == 0 { 0.0 } else { 0.5 }))
+        .collect()
+}
+
+// 回転する物体をフレームレート fs で撮ったときの見かけの回転数（-fs/2 〜 fs/2）
+// aliased_frequency と違って符号を残し、負なら逆回転に見える（ワゴンホイール効果）
+pub fn apparent_rotation(rotation: f64, sampling_rate: f64) -> f64 {
+    rotation - sampling_rate * (rotation / sampling_rate).round()
+}
+
+// 回転図（横軸 真の回転数/fs、縦軸 見かけの回転数/fs）の折れ線。0 から span·fs まで
+// fs の半整数倍で +1/2 から -1/2 に跳ぶのこぎり波になる。跳ぶところは NaN で線を切る
+pub fn rotation_diagram(span: f64) -> Vec<(f64, f64)> {
+    let mut points = vec![(0.0, 0.0)];
+    let mut edge = 0.5;
+    while edge < span {
+        points.extend([(edge, 0.5), (edge, f64::NAN), (edge, -0.5)]);
+        edge += 1.0;
+    }
+    points.push((span, apparent_rotation(span, 1.0)));
+    points
+}
+
+// IEC 61672 の A 特性 (dB)。1kHz で 0 dB になり、低域と 10kHz 以上を下げる
+pub fn a_weighting_db(frequency: f64) -> f64 {
+    let f2 = frequency * frequency;
+    let response = 12194.0_f64.powi(2) * f2 * f2
+        / ((f2 + 20.6_f64.powi(2))
+            * ((f2 + 107.7_f64.powi(2)) * (f2 + 737.9_f64.powi(2))).sqrt()
+            * (f2 + 12194.0_f64.powi(2)));
+    20.0 * response.log10() + 2.0
+}
+
+// 振幅スペクトルに A 特性を掛ける（聴感上の大きさに近づける）。直流は 0 になる
+pub fn a_weighted(freqs: &[f64], mags: &[f64]) -> Vec<f64> {
+    freqs
+        .iter()
+        .zip(mags)
+        .map(|(&f, &m)| m * 10.0_f64.powf(a_weighting_db(f) / 20.0))
+        .collect()
+}
+
+// 振幅を dB に変換
+pub fn magnitude_db(mags: &[f64]) -> Vec<f64> {
+    mags.iter()
+        .map(|&m| 20.0 * m.max(MIN_MAGNITUDE).log10())
+        .collect()
+}
+
+// サンプリング後に観測される見かけの周波数（0 〜 fs/2 に折り返す）
+pub fn aliased_frequency(signal_freq: f64, sampling_rate: f64) -> f64 {
+    let folded = signal_freq.abs() % sampling_rate;
+    if folded > sampling_rate / 2.0 {
+        sampling_rate - folded
+    } else {
+        folded
+    }
+}
+
+// 最大ピークに対して threshold 倍以上の局所最大を、大きい順に最大 count 個返す
+pub fn find_peaks(freqs: &[f64], mags: &[f64], count: usize, threshold: f64) -> Vec<(f64, f64)> {
+    let max = mags.iter().cloned().fold(0.0, f64::max);
+    let mut peaks: Vec<(f64, f64)> = (0..mags.len())
+        .filter(|&k| {
+            let left = if k == 0 { 0.0 } else { mags[k - 1] };
+            let right = mags.get(k + 1).copied().unwrap_or(0.0);
+            mags[k] > left && mags[k] >= right && mags[k] >= max * threshold
+        })
+        .map(|k| (freqs[k], mags[k]))
+        .collect();
+
+    peaks.sort_by(|a, b| b.1.total_cmp(&a.1));
+    peaks.truncate(count);
+    peaks
+}
+
+// ピークを基本波・エイリアス・その他に分類する
+// alias は見かけの周波数（実信号なら aliased_frequency、複素信号なら wrapped_frequency）
+// 周波数分解能（1ビン幅）以内に一致すれば同じ周波数とみなす
+pub fn classify_peaks(
+    peaks: &[(f64, f64)],
+    signal_freq: f64,
+    alias: f64,
+    resolution: f64,
+) -> Vec<Peak> {
+    peaks
+        .iter()
+        .map(|&(frequency, magnitude)| {
+            let kind = if (frequency - signal_freq).abs() <= resolution {
+                PeakKind::Fundamental
+            } else if (frequency - alias).abs() <= resolution {
+                PeakKind::Alias
+            } else {
+                PeakKind::Other
+            };
+            Peak {
+                frequency,
+                magnitude,
+                kind,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn aliased_frequency_folds_at_nyquist_and_wraps_at_fs() {
+        let fs = 20.0;
+        assert_eq!(aliased_frequency(7.0, fs), 7.0); // ナイキスト未満はそのまま
+        assert_eq!(aliased_frequency(10.0, fs), 10.0); // ナイキストちょうどは折り返さない
+        assert_eq!(aliased_frequency(11.0, fs), 9.0); // 少し超えるとナイキストで折り返す
+        assert_eq!(aliased_frequency(20.0, fs), 0.0); // fs は直流に見える
+        assert_eq!(aliased_frequency(23.0, fs), 3.0); // fs を超えると一周して戻る
+        assert_eq!(aliased_frequency(37.0, fs), 3.0); // 2fs 手前では fs - 3Hz の鏡像
+        assert_eq!(aliased_frequency(-3.0, fs), 3.0); // 負の周波数は正の側に
+
+        // デモの 10Hz を 8Hz でサンプリングすると 2Hz に見える
+        assert_eq!(aliased_frequency(10.0, 8.0), 2.0);
+    }
+
+    // cos は実部、sin は虚部（符号は負）に N/2 の大きさで現れ、spectrum の振幅はその正規化
+    #[test]
+    fn fft_complex_keeps_the_phase() {
+        let n = 32;
+        let cos: Vec<f64> = (0..n)
+            .map(|i| (2.0 * std::f64::consts::PI * 4.0 * i as f64 / n as f64).cos())
+            .collect();
+        let sin: Vec<f64> = (0..n)
+            .map(|i| (2.0 * std::f64::consts::PI * 4.0 * i as f64 / n as f64).sin())
+            .collect();
+        let (re, im) = (fft_complex(&cos)[4], fft_complex(&sin)[4]);
+        assert!((re.re - 16.0).abs() < 1e-9 && re.im.abs() < 1e-9);
+        assert!(im.re.abs() < 1e-9 && (im.im + 16.0).abs() < 1e-9);
+        // 実信号のスペクトルは共役対称
+        assert!((fft_complex(&sin)[n - 4] - im.conj()).norm() < 1e-9);
+        assert!((spectrum(&cos, n as f64).1[4] - 1.0).abs() < 1e-9);
+        assert!(fft_complex(&[]).is_empty());
+    }
+
+    #[test]
+    fn a_weighting_matches_the_standard_table() {
+        // IEC 61672 の表の値（±0.1 dB）
+        for (frequency, expected) in [(100.0, -19.1), (1000.0, 0.0), (10_000.0, -2.5)] {
+            let weight = a_weighting_db(frequency);
+            assert!(
+                (weight - expected).abs() < 0.1,
+                "{}Hz: {}",
+                frequency,
+                weight
+            );
+        }
+        assert_eq!(a_weighted(&[0.0], &[1.0]), vec![0.0]);
+    }
+
+    #[test]
+    fn folding_diagram_traces_the_aliased_frequency() {
+        let vertices = folding_diagram(3.0);
+        assert_eq!(vertices.len(), 7);
+        assert_eq!(vertices.last(), Some(&(3.0, 0.0)));
+        // 頂点の間は直線なので、途中の点も aliased_frequency と一致する
+        for ratio in [0.1, 0.4, 0.75, 1.2, 2.9] {
+            let k = (ratio * 2.0_f64).floor() as usize;
+            let ((x0, y0), (x1, y1)) = (vertices[k], vertices[k + 1]);
+            let interpolated = y0 + (y1 - y0) * (ratio - x0) / (x1 - x0);
+            assert!((interpolated - aliased_frequency(ratio * 8.0, 8.0) / 8.0).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn apparent_rotation_reverses_above_nyquist() {
+        // 24fps で撮った車輪: 11 回転/秒は前進、13 回転/秒は 11 回転/秒の逆回転、24 回転/秒は止まって見える
+        assert_eq!(apparent_rotation(11.0, 24.0), 11.0);
+        assert_eq!(apparent_rotation(13.0, 24.0), -11.0);
+        assert_eq!(apparent_rotation(24.0, 24.0), 0.0);
+        assert_eq!(apparent_rotation(25.0, 24.0), 1.0);
+
+        let points = rotation_diagram(2.0);
+        assert_eq!(points.first(), Some(&(0.0, 0.0)));
+        assert_eq!(points.last(), Some(&(2.0, 0.0)));
+        assert_eq!(points.iter().filter(|(_, y)| y.is_nan()).count(), 2);
+    }
+
+    #[test]
+    fn coherent_frequency_removes_leakage() {
+        let (n, fs) = (480, 240.0);
+        let f = coherent_frequency(10.3, n, fs);
+        assert!((f - 10.5).abs() < 1e-12);
+        assert_eq!(coherent_frequency(0.1, n, fs), 0.5);
+
+        let samples: Vec<f64> = (0..n)
+            .map(|i| (2.0 * std::f64::consts::PI * f * i as f64 / fs).sin())
+            .collect();
+        let (_, mags) = spectrum(&samples, fs);
+        let peak = (f * n as f64 / fs).round() as usize;
+        assert!((mags[peak] - 1.0).abs() < 1e-9);
+        let leakage = mags
+            .iter()
+            .enumerate()
+            .filter(|&(k, _)| k != peak)
+            .fold(0.0, |max: f64, (_, &m)| max.max(m));
+        assert!(leakage < 1e-9, "leakage {}", leakage);
+    }
+
+    #[test]
+    fn replicas_overlap_only_above_half_the_sampling_rate() {
+        let replica = spectral_replica(&[(3.0, 1.0)], 8.0, 1);
+        assert_eq!(replica, vec![(5.0, 0.5), (11.0, 0.5)]);
+
+        assert!(replica_overlaps(4.0, 8.0, 2).is_empty());
+        // 帯域 5Hz を 8Hz でサンプリングすると、k·8 ± 4 の前後 1Hz が隣と重なる
+        let overlaps = replica_overlaps(5.0, 8.0, 1);
+        assert_eq!(overlaps, vec![(-5.0, -3.0), (3.0, 5.0)]);
+        // fs を超える帯域では重なりがつながる
+        assert_eq!(replica_overlaps(10.0, 8.0, 1), vec![(-10.0, 10.0)]);
+    }
+}
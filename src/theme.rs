@@ -0,0 +1,290 @@
+use std::process::Command;
+
+use crate::signal::SignalParams;
+
+use plotly::{
+    common::{Anchor, Font, Orientation, Title},
+    layout::{Axis, Legend},
+    Layout,
+};
+use serde::Deserialize;
+
+// 透過背景。スライドなど色付きの背景に重ねる用
+pub const TRANSPARENT: &str = "rgba(0,0,0,0)";
+
+pub const DEFAULT_FONT_FAMILY: &str = "Fira Code";
+
+// 指定のフォントが無いと kaleido は黙って別のフォントに置き換え、6〜8pt の詰めたレイアウトが崩れる
+// 多くの環境にある等幅フォントを後ろに並べておき、先頭から順に見つかったものを使わせる
+pub const FALLBACK_FONT_FAMILIES: &str =
+    "DejaVu Sans Mono, Menlo, Consolas, Liberation Mono, monospace";
+
+// plotly（CSS の font-family）に渡すフォントの候補列
+pub fn font_stack(family: &str) -> String {
+    format!("{}, {}", family, FALLBACK_FONT_FAMILIES)
+}
+
+// fontconfig に family が入っているか。fc-list が使えない環境では確認できないので None
+pub fn font_installed(family: &str) -> Option<bool> {
+    let output = Command::new("fc-list")
+        .args([":", "family"])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let installed = String::from_utf8_lossy(&output.stdout);
+    Some(
+        installed
+            .lines()
+            .flat_map(|line| line.split(','))
+            .any(|name| name.trim().eq_ignore_ascii_case(family.trim())),
+    )
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LegendOrientation {
+    Horizontal,
+    Vertical,
+}
+
+// 凡例の配置。x, y は paper 座標（0.0〜1.0 がプロット領域）
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LegendOptions {
+    pub show: bool,
+    pub x: f64,
+    pub y: f64,
+    pub orientation: LegendOrientation,
+    pub outside: bool, // true ならプロット領域の外側に向かって配置する
+}
+
+impl Default for LegendOptions {
+    // 2x2 グリッドでは下段のタイトルと重ならないよう、下端の外側に横並びで置く
+    fn default() -> Self {
+        Self {
+            show: true,
+            x: 0.5,
+            y: -0.04,
+            orientation: LegendOrientation::Horizontal,
+            outside: true,
+        }
+    }
+}
+
+// 振幅軸の y = 0 の線。既定では plotly の細い線のままで、show にするとグリッドより太く目立たせる
+// 切り捨ての量子化器などで生じる直流のずれを、ゼロを挟んだ対称性から読み取りやすくする
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ZeroLineOptions {
+    pub show: bool,
+    pub color: String,
+    pub width: usize, // px
+}
+
+impl Default for ZeroLineOptions {
+    fn default() -> Self {
+        Self {
+            show: false,
+            color: "#555555".to_string(),
+            width: 2,
+        }
+    }
+}
+
+// 軸の種類。波形パネルの軸とスペクトルパネルの軸でフォントを変えられる
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AxisKind {
+    Time,
+    Spectrum,
+}
+
+// 軸フォントの上書き。None の項目は図ごとの既定値とテーマのフォントを使う
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AxisFont {
+    pub family: Option<String>,
+    pub title_size: Option<usize>,
+    pub tick_size: Option<usize>,
+}
+
+// テーマファイル（--theme-file）からも読める。書かなかったフィールドは light の値になる
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Theme {
+    pub font_family: String,
+    pub text_color: String,
+    pub background: String, // paper とプロット領域の背景色
+    pub grid_color: String,
+    pub guide_color: String, // レイアウトガイド（位置の目安の文字）の色。本文より薄くする
+    pub severity_colors: [String; 3], // エイリアシングの深刻度（低・中・高）の色
+    pub palette: Vec<String>, // 信号ごとのサンプル点の色。信号の順に繰り返し使う
+    pub legend: LegendOptions,
+    pub zero_line: ZeroLineOptions,
+    pub time_axis_font: AxisFont,
+    pub spectrum_axis_font: AxisFont,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::light()
+    }
+}
+
+impl Theme {
+    pub fn light() -> Self {
+        Self {
+            font_family: DEFAULT_FONT_FAMILY.to_string(),
+            text_color: "#333".to_string(),
+            background: "#fff".to_string(),
+            grid_color: "#eee".to_string(),
+            guide_color: "#999999".to_string(),
+            severity_colors: [
+                "rgba(44, 160, 44, 1.0)".to_string(),
+                "rgba(230, 180, 0, 1.0)".to_string(),
+                "rgba(214, 39, 40, 1.0)".to_string(),
+            ],
+            palette: [
+                "rgba(31, 119, 180, 1.0)",
+                "rgba(255, 127, 14, 1.0)",
+                "rgba(44, 160, 44, 1.0)",
+                "rgba(214, 39, 40, 1.0)",
+                "rgba(148, 103, 189, 1.0)",
+                "rgba(140, 86, 75, 1.0)",
+                "rgba(227, 119, 194, 1.0)",
+                "rgba(127, 127, 127, 1.0)",
+            ]
+            .iter()
+            .map(|color| color.to_string())
+            .collect(),
+            legend: LegendOptions::default(),
+            zero_line: ZeroLineOptions::default(),
+            time_axis_font: AxisFont::default(),
+            spectrum_axis_font: AxisFont::default(),
+        }
+    }
+
+    // index 番目の信号の色。信号に色が指定されていればそれを優先する
+    pub fn signal_color(&self, index: usize, params: &SignalParams) -> String {
+        params
+            .color
+            .clone()
+            .unwrap_or_else(|| self.palette[index % self.palette.len()].clone())
+    }
+
+    // 背景を透過にする。白いグリッド線は色付きの背景で見えなくなるので半透明の灰色にする
+    pub fn with_transparent_background(mut self) -> Self {
+        self.background = TRANSPARENT.to_string();
+        self.grid_color = "rgba(127, 127, 127, 0.35)".to_string();
+        self
+    }
+
+    // 背景色をレイアウトに設定する
+    pub fn apply_background(&self, layout: Layout) -> Layout {
+        layout
+            .paper_background_color(self.background.clone())
+            .plot_background_color(self.background.clone())
+    }
+
+    // 深刻度 0.25 未満は緑、0.75 未満は黄、それ以上は赤
+    pub fn severity_color(&self, severity: f64) -> String {
+        let level = if severity < 0.25 {
+            0
+        } else if severity < 0.75 {
+            1
+        } else {
+            2
+        };
+        self.severity_colors[level].clone()
+    }
+
+    // テーマのグリッド色を設定した軸
+    pub fn axis(&self) -> Axis {
+        Axis::new().grid_color(self.grid_color.clone())
+    }
+
+    // 振幅軸に y = 0 の強調線を付ける（zero_line.show のときだけ）
+    pub fn amplitude_axis(&self, axis: Axis) -> Axis {
+        let zero_line = &self.zero_line;
+        if !zero_line.show {
+            return axis;
+        }
+        axis.zero_line(true)
+            .zero_line_color(zero_line.color.clone())
+            .zero_line_width(zero_line.width)
+    }
+
+    pub fn with_axis_font(mut self, kind: AxisKind, font: AxisFont) -> Self {
+        match kind {
+            AxisKind::Time => self.time_axis_font = font,
+            AxisKind::Spectrum => self.spectrum_axis_font = font,
+        }
+        self
+    }
+
+    // タイトルと目盛りのフォントを設定した軸。title_size, tick_size は上書きがないときの大きさ
+    pub fn styled_axis(
+        &self,
+        kind: AxisKind,
+        title: &str,
+        title_size: usize,
+        tick_size: usize,
+    ) -> Axis {
+        let font = match kind {
+            AxisKind::Time => &self.time_axis_font,
+            AxisKind::Spectrum => &self.spectrum_axis_font,
+        };
+        let family = font_stack(font.family.as_deref().unwrap_or(&self.font_family));
+        let sized = |size: usize| {
+            Font::new()
+                .size(size)
+                .color(self.text_color.clone())
+                .family(family.as_str())
+        };
+        self.axis()
+            .title(Title::with_text(title).font(sized(font.title_size.unwrap_or(title_size))))
+            .tick_font(sized(font.tick_size.unwrap_or(tick_size)))
+    }
+
+    pub fn font(&self, size: usize) -> Font {
+        Font::new()
+            .size(size)
+            .color(self.text_color.clone())
+            .family(font_stack(&self.font_family).as_str())
+    }
+
+    pub fn legend(&self) -> Legend {
+        let options = &self.legend;
+        let orientation = match options.orientation {
+            LegendOrientation::Horizontal => Orientation::Horizontal,
+            LegendOrientation::Vertical => Orientation::Vertical,
+        };
+        let legend = Legend::new()
+            .x(options.x)
+            .y(options.y)
+            .orientation(orientation)
+            .font(self.font(7));
+
+        if !options.outside {
+            return legend;
+        }
+
+        // 外側に置く場合は、プロット領域から離れる向きにアンカーを取る
+        let x_anchor = if options.x >= 1.0 {
+            Anchor::Left
+        } else if options.x <= 0.0 {
+            Anchor::Right
+        } else {
+            Anchor::Center
+        };
+        let y_anchor = if options.y <= 0.0 {
+            Anchor::Top
+        } else if options.y >= 1.0 {
+            Anchor::Bottom
+        } else {
+            Anchor::Middle
+        };
+        legend.x_anchor(x_anchor).y_anchor(y_anchor)
+    }
+}
@@ -0,0 +1,81 @@
+use std::io::{self, Write};
+
+// 正規化後のピーク値 (dBFS)。少し余裕を残して i16 の上限で切れないようにする
+pub const NORMALIZE_PEAK_DBFS: f64 = -1.0;
+
+// バッファ全体を同じゲインで拡大・縮小し、最大の絶対値を peak_dbfs に合わせる
+// 掛けたゲイン（倍率）を返す。無音ならそのままにして None
+pub fn normalize_peak(samples: &mut [f64], peak_dbfs: f64) -> Option<f64> {
+    let peak = samples.iter().fold(0.0_f64, |max, &s| max.max(s.abs()));
+    if peak == 0.0 {
+        return None;
+    }
+    let gain = 10.0_f64.powf(peak_dbfs / 20.0) / peak;
+    for sample in samples.iter_mut() {
+        *sample *= gain;
+    }
+    Some(gain)
+}
+
+// ±1 をフルスケールとした 16bit モノラル PCM の WAV を書き出す
+// ±1 を超えた値は i16 の範囲でクリップされる
+pub fn write_wav<W: Write>(samples: &[f64], sample_rate: u32, mut writer: W) -> io::Result<()> {
+    const CHANNELS: u16 = 1;
+    const BITS_PER_SAMPLE: u16 = 16;
+    let block_align = CHANNELS * BITS_PER_SAMPLE / 8;
+    let data_size = samples.len() as u32 * block_align as u32;
+
+    writer.write_all(b"RIFF")?;
+    writer.write_all(&(36 + data_size).to_le_bytes())?;
+    writer.write_all(b"WAVE")?;
+
+    writer.write_all(b"fmt ")?;
+    writer.write_all(&16u32.to_le_bytes())?;
+    writer.write_all(&1u16.to_le_bytes())?; // PCM
+    writer.write_all(&CHANNELS.to_le_bytes())?;
+    writer.write_all(&sample_rate.to_le_bytes())?;
+    writer.write_all(&(sample_rate * block_align as u32).to_le_bytes())?;
+    writer.write_all(&block_align.to_le_bytes())?;
+    writer.write_all(&BITS_PER_SAMPLE.to_le_bytes())?;
+
+    writer.write_all(b"data")?;
+    writer.write_all(&data_size.to_le_bytes())?;
+    for &sample in samples {
+        let value = (sample * i16::MAX as f64)
+            .round()
+            .clamp(i16::MIN as f64, i16::MAX as f64) as i16;
+        writer.write_all(&value.to_le_bytes())?;
+    }
+    writer.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalize_peak_hits_target_level() {
+        let mut samples = vec![0.25, -2.0, 1.5];
+        let gain = normalize_peak(&mut samples, NORMALIZE_PEAK_DBFS).unwrap();
+        let peak = samples.iter().fold(0.0_f64, |max, &s| max.max(s.abs()));
+        assert!((20.0 * peak.log10() - NORMALIZE_PEAK_DBFS).abs() < 1e-9);
+        assert!((gain * 2.0 - peak).abs() < 1e-12);
+
+        let mut silence = vec![0.0; 4];
+        assert_eq!(normalize_peak(&mut silence, NORMALIZE_PEAK_DBFS), None);
+    }
+
+    #[test]
+    fn write_wav_clips_to_i16_range() {
+        let mut bytes = Vec::new();
+        write_wav(&[0.0, 1.0, -1.5], 8000, &mut bytes).unwrap();
+        assert_eq!(bytes.len(), 44 + 3 * 2);
+        assert_eq!(&bytes[..4], b"RIFF");
+        assert_eq!(&bytes[40..44], &6u32.to_le_bytes());
+        let data: Vec<i16> = bytes[44..]
+            .chunks(2)
+            .map(|b| i16::from_le_bytes([b[0], b[1]]))
+            .collect();
+        assert_eq!(data, vec![0, i16::MAX, i16::MIN]);
+    }
+}
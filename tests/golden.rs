@@ -0,0 +1,104 @@
+// 描画結果をコミット済みのゴールデン画像とピクセル単位で比較する
+// kaleido が必要なので `cargo test --features golden-tests` のときだけ動かす
+// ゴールデン画像の作り直し: UPDATE_GOLDEN=1 cargo test --features golden-tests
+#![cfg(feature = "golden-tests")]
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+// 1チャンネルあたりこの値を超えて違うピクセルを「異なる」とみなす（フォントのアンチエイリアス差を吸収）
+const CHANNEL_TOLERANCE: u8 = 16;
+
+// 異なるピクセルがこの割合以下なら一致とする
+const MAX_DIFFERENT_RATIO: f64 = 0.005;
+
+fn golden_path(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/golden")
+        .join(name)
+}
+
+// 固定のプリセット・サイズで out_dir に描画する
+fn render(out_dir: &Path) {
+    let status = Command::new(env!("CARGO_BIN_EXE_graph-builder"))
+        .args(["--preset", "aliasing", "--dpi", "thumbnail", "--seed", "0"])
+        .arg("--out-dir")
+        .arg(out_dir)
+        .status()
+        .expect("failed to run graph-builder");
+    assert!(status.success(), "graph-builder exited with {}", status);
+}
+
+fn compare(name: &str, out_dir: &Path) {
+    let rendered_path = out_dir.join(name);
+    let golden = golden_path(name);
+
+    // 作り直すときも、描画されなかった（読めない）画像をゴールデンにしてしまわないよう先に開く
+    let rendered = image::open(&rendered_path)
+        .unwrap_or_else(|e| {
+            panic!(
+                "{} was not rendered ({}): {}",
+                name,
+                rendered_path.display(),
+                e
+            )
+        })
+        .to_rgba8();
+
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        std::fs::create_dir_all(golden.parent().unwrap()).expect("failed to create tests/golden");
+        std::fs::copy(&rendered_path, &golden).expect("failed to update golden image");
+        println!("{}: golden image updated", golden.display());
+        return;
+    }
+
+    let expected = image::open(&golden)
+        .unwrap_or_else(|e| {
+            panic!(
+                "cannot open {} ({}); run with UPDATE_GOLDEN=1 to create it",
+                golden.display(),
+                e
+            )
+        })
+        .to_rgba8();
+    assert_eq!(
+        rendered.dimensions(),
+        expected.dimensions(),
+        "{}: image size changed",
+        name
+    );
+
+    let different = rendered
+        .pixels()
+        .zip(expected.pixels())
+        .filter(|(a, b)| {
+            a.0.iter()
+                .zip(b.0.iter())
+                .any(|(x, y)| x.abs_diff(*y) > CHANNEL_TOLERANCE)
+        })
+        .count();
+    let ratio = different as f64 / (rendered.width() * rendered.height()) as f64;
+    assert!(
+        ratio <= MAX_DIFFERENT_RATIO,
+        "{}: {:.2}% of pixels differ from the golden image (limit {:.2}%)",
+        name,
+        ratio * 100.0,
+        MAX_DIFFERENT_RATIO * 100.0
+    );
+}
+
+#[test]
+fn rendered_figures_match_golden_images() {
+    let out_dir = std::env::temp_dir().join(format!("graph-builder-golden-{}", std::process::id()));
+    render(&out_dir);
+
+    for name in [
+        "digital_audio_comparison.png",
+        "digital_audio_spectrum.png",
+        "iq_spectrum.png",
+    ] {
+        compare(name, &out_dir);
+    }
+
+    let _ = std::fs::remove_dir_all(&out_dir);
+}